@@ -0,0 +1,573 @@
+use crate::aur::rpc::{aur_base_url, fetch_aur_package};
+use crate::utils::is_command_available;
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const SESSION_COOKIE: &str = "AURSID";
+const SECRET_ATTRIBUTES: [&str; 4] = ["application", "guiman", "service", "aur"];
+
+/// An authenticated AUR web session
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AurSession {
+    pub username: String,
+    pub cookie: String,
+    pub created: String,
+}
+
+/// Public view of the stored session (never exposes the cookie)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AurAccountStatus {
+    pub logged_in: bool,
+    pub username: Option<String>,
+    pub since: Option<String>,
+    pub storage: String,
+}
+
+/// Actions available on an AUR package base
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AurAction {
+    Vote,
+    Unvote,
+    Flag,
+    Notify,
+    Unnotify,
+    Adopt,
+    Disown,
+}
+
+impl AurAction {
+    fn endpoint(self) -> &'static str {
+        match self {
+            AurAction::Vote => "vote",
+            AurAction::Unvote => "unvote",
+            AurAction::Flag => "flag",
+            AurAction::Notify => "notify",
+            AurAction::Unnotify => "unnotify",
+            AurAction::Adopt => "adopt",
+            AurAction::Disown => "disown",
+        }
+    }
+
+    fn success_message(self, package_base: &str) -> String {
+        match self {
+            AurAction::Vote => format!("✓ Voted for {}", package_base),
+            AurAction::Unvote => format!("✓ Removed vote for {}", package_base),
+            AurAction::Flag => format!("✓ Flagged {} as out-of-date", package_base),
+            AurAction::Notify => format!("✓ Notifications enabled for {}", package_base),
+            AurAction::Unnotify => format!("✓ Notifications disabled for {}", package_base),
+            AurAction::Adopt => format!("✓ You now maintain {}", package_base),
+            AurAction::Disown => format!("✓ Disowned {}", package_base),
+        }
+    }
+}
+
+/// Log in to the AUR and store the resulting session cookie
+pub async fn aur_login(username: &str, password: &str) -> Result<AurAccountStatus, String> {
+    if username.trim().is_empty() || password.is_empty() {
+        return Err("Username and password are required".to_string());
+    }
+
+    let client = session_client()?;
+    let response = client
+        .post(format!("{}/login", aur_base_url()))
+        .form(&[
+            ("user", username),
+            ("passwd", password),
+            ("remember_me", "on"),
+            ("next", "/"),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to contact the AUR: {}", e))?;
+
+    let cookie = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(extract_session_cookie)
+        .ok_or_else(|| "AUR login failed: invalid username or password".to_string())?;
+
+    let session = AurSession {
+        username: username.trim().to_string(),
+        cookie,
+        created: chrono::Utc::now().to_rfc3339(),
+    };
+    store_session(&session)?;
+
+    Ok(aur_account_status())
+}
+
+/// Log out of the AUR and forget the stored session
+pub async fn aur_logout() -> Result<String, String> {
+    if let Some(session) = load_session() {
+        // Best effort: invalidate the session server-side as well
+        if let Ok(client) = session_client() {
+            let _ = client
+                .post(format!("{}/logout", aur_base_url()))
+                .header(COOKIE, format!("{}={}", SESSION_COOKIE, session.cookie))
+                .send()
+                .await;
+        }
+    }
+
+    clear_session()?;
+    Ok("Logged out of the AUR".to_string())
+}
+
+/// Report whether an AUR session is stored, and where
+pub fn aur_account_status() -> AurAccountStatus {
+    match load_stored_session() {
+        Some((session, storage)) => AurAccountStatus {
+            logged_in: true,
+            username: Some(session.username),
+            since: Some(session.created),
+            storage: storage.to_string(),
+        },
+        // Where the next login would be stored
+        None => AurAccountStatus {
+            logged_in: false,
+            username: None,
+            since: None,
+            storage: if secret_tool_available() {
+                "keyring"
+            } else {
+                "file"
+            }
+            .to_string(),
+        },
+    }
+}
+
+/// Perform an authenticated action on the package base of `package`
+pub async fn perform_aur_action(
+    package: &str,
+    action: AurAction,
+    comment: Option<String>,
+) -> Result<String, String> {
+    let session = load_session()
+        .ok_or_else(|| "Not logged in to the AUR. Log in from Settings first.".to_string())?;
+
+    let comment = comment.map(|c| c.trim().to_string()).unwrap_or_default();
+    if action == AurAction::Flag && comment.is_empty() {
+        return Err("A comment explaining why the package is out-of-date is required".to_string());
+    }
+
+    let info = fetch_aur_package(package).await?;
+
+    match action {
+        AurAction::Adopt if info.maintainer.is_some() => {
+            return Err(format!(
+                "{} already has a maintainer. Only orphaned packages can be adopted.",
+                info.package_base
+            ));
+        }
+        AurAction::Disown if info.maintainer.as_deref() != Some(session.username.as_str()) => {
+            return Err(format!(
+                "{} is not maintained by {}",
+                info.package_base, session.username
+            ));
+        }
+        _ => {}
+    }
+
+    let mut form: Vec<(&str, &str)> = Vec::new();
+    match action {
+        AurAction::Flag => form.push(("comments", &comment)),
+        AurAction::Disown => {
+            form.push(("comments", &comment));
+            form.push(("confirm", "on"));
+        }
+        _ => {}
+    }
+
+    let client = session_client()?;
+    let response = client
+        .post(format!(
+            "{}/pkgbase/{}/{}",
+            aur_base_url(),
+            info.package_base,
+            action.endpoint()
+        ))
+        .header(COOKIE, format!("{}={}", SESSION_COOKIE, session.cookie))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to contact the AUR: {}", e))?;
+
+    let status = response.status();
+    if status.is_redirection() {
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+
+        if location.contains("/login") {
+            clear_session()?;
+            return Err("AUR session expired. Please log in again.".to_string());
+        }
+        return Ok(action.success_message(&info.package_base));
+    }
+
+    match status.as_u16() {
+        200..=299 => Ok(action.success_message(&info.package_base)),
+        401 | 403 => Err(format!(
+            "The AUR refused to {} {}: not authorized",
+            action.endpoint(),
+            info.package_base
+        )),
+        _ => Err(format!(
+            "The AUR rejected the {} request for {} ({})",
+            action.endpoint(),
+            info.package_base,
+            status
+        )),
+    }
+}
+
+// Helper functions
+
+fn session_client() -> Result<reqwest::Client, String> {
+    // Redirects are handled manually so the session cookie can be captured
+    reqwest::Client::builder()
+        .redirect(Policy::none())
+        .user_agent("GuiMan")
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+fn extract_session_cookie(header: &str) -> Option<String> {
+    let pair = header.split(';').next()?.trim();
+    let value = pair.strip_prefix(&format!("{}=", SESSION_COOKIE))?;
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// `GUIMAN_AUR_SESSION_FILE` forces file storage at that path
+fn secret_tool_available() -> bool {
+    std::env::var_os("GUIMAN_AUR_SESSION_FILE").is_none() && is_command_available("secret-tool")
+}
+
+fn session_file() -> Result<PathBuf, String> {
+    if let Ok(path) = std::env::var("GUIMAN_AUR_SESSION_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+    Ok(dirs::config_dir()
+        .ok_or("Failed to get config directory")?
+        .join("guiman")
+        .join("aur_session.json"))
+}
+
+fn store_session(session: &AurSession) -> Result<(), String> {
+    let json = serde_json::to_string(session)
+        .map_err(|e| format!("Failed to serialize AUR session: {}", e))?;
+
+    // Prefer the desktop keyring (libsecret), fall back to a private file
+    if secret_tool_available() {
+        let child = Command::new("/usr/bin/secret-tool")
+            .arg("store")
+            .arg("--label=GuiMan AUR session")
+            .args(SECRET_ATTRIBUTES)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        if let Ok(mut child) = child {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(json.as_bytes());
+            }
+            if child.wait().map(|s| s.success()).unwrap_or(false) {
+                return Ok(());
+            }
+        }
+    }
+
+    let path = session_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to save AUR session: {}", e))?;
+    file.write_all(json.as_bytes())
+        .map_err(|e| format!("Failed to save AUR session: {}", e))
+}
+
+fn load_session() -> Option<AurSession> {
+    load_stored_session().map(|(session, _)| session)
+}
+
+/// The stored session with `keyring` or `file`, wherever it was found
+fn load_stored_session() -> Option<(AurSession, &'static str)> {
+    if secret_tool_available() {
+        let output = Command::new("/usr/bin/secret-tool")
+            .arg("lookup")
+            .args(SECRET_ATTRIBUTES)
+            .output()
+            .ok()?;
+
+        if output.status.success() && !output.stdout.is_empty() {
+            if let Ok(session) = serde_json::from_slice::<AurSession>(&output.stdout) {
+                return Some((session, "keyring"));
+            }
+        }
+    }
+
+    let content = fs::read_to_string(session_file().ok()?).ok()?;
+    serde_json::from_str(&content)
+        .ok()
+        .map(|session| (session, "file"))
+}
+
+fn clear_session() -> Result<(), String> {
+    if secret_tool_available() {
+        let _ = Command::new("/usr/bin/secret-tool")
+            .arg("clear")
+            .args(SECRET_ATTRIBUTES)
+            .output();
+    }
+
+    let path = session_file()?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove AUR session: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// The AUR and session file settings live in the environment
+    static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    const RPC_INFO: &str = r#"{"version":5,"type":"multiinfo","resultcount":1,"results":[
+        {"ID":1,"Name":"foo","PackageBaseID":2,"PackageBase":"foo-base","Version":"1.0-1",
+         "Maintainer":"alice"}]}"#;
+
+    #[derive(Debug, Clone)]
+    struct Request {
+        method: String,
+        path: String,
+        cookie: Option<String>,
+        body: String,
+    }
+
+    /// Minimal stand-in for the aurweb endpoints used by the account module
+    async fn start_server() -> (String, Arc<Mutex<Vec<Request>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = read_request(&mut stream).await;
+                let response = respond(&request);
+                log.lock().unwrap().push(request);
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (url, requests)
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> Request {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let header_end = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            data.extend_from_slice(&buffer[..read]);
+            if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if read == 0 {
+                break data.len();
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let length: usize = header("content-length")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        while data.len() < header_end + length {
+            let read = stream.read(&mut buffer).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer[..read]);
+        }
+
+        let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+        Request {
+            method: request_line.next().unwrap_or("").to_string(),
+            path: request_line.next().unwrap_or("").to_string(),
+            cookie: header("cookie"),
+            body: String::from_utf8_lossy(&data[header_end..]).to_string(),
+        }
+    }
+
+    fn respond(request: &Request) -> String {
+        let logged_in = request.cookie.as_deref() == Some("AURSID=abc123");
+        let path = request.path.split('?').next().unwrap_or("");
+        let (status, headers, body) = match (request.method.as_str(), path) {
+            ("POST", "/login") if request.body.contains("passwd=secret") => (
+                "302 Found",
+                "Set-Cookie: AURSID=abc123; Path=/; HttpOnly\r\nLocation: /\r\n",
+                "",
+            ),
+            ("POST", "/login") => ("200 OK", "", "Bad username or password."),
+            ("GET", "/rpc/v5/info") => ("200 OK", "Content-Type: application/json\r\n", RPC_INFO),
+            ("POST", _) if path.starts_with("/pkgbase/foo-base/") && logged_in => {
+                ("303 See Other", "Location: /pkgbase/foo-base\r\n", "")
+            }
+            ("POST", _) if path.starts_with("/pkgbase/") => (
+                "303 See Other",
+                "Location: /login?next=/pkgbase/foo-base\r\n",
+                "",
+            ),
+            _ => ("404 Not Found", "", ""),
+        };
+
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    /// Point the module at `url` with file-backed session storage
+    fn use_server(url: &str, name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("guiman-aur-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        std::env::set_var("GUIMAN_AUR_URL", url);
+        std::env::set_var("GUIMAN_AUR_SESSION_FILE", &path);
+        path
+    }
+
+    fn save_test_session(cookie: &str) {
+        store_session(&AurSession {
+            username: "alice".to_string(),
+            cookie: cookie.to_string(),
+            created: "2026-01-01T00:00:00+00:00".to_string(),
+        })
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn login_stores_session_in_file() {
+        let _guard = ENV_LOCK.lock().await;
+        let (url, requests) = start_server().await;
+        let path = use_server(&url, "login");
+
+        let error = aur_login("alice", "wrong").await.unwrap_err();
+        assert!(error.contains("invalid username or password"));
+        assert!(!path.exists());
+        assert!(!aur_account_status().logged_in);
+
+        let status = aur_login("alice", "secret").await.unwrap();
+        assert!(status.logged_in);
+        assert_eq!(status.username.as_deref(), Some("alice"));
+        assert_eq!(status.storage, "file");
+        assert!(fs::read_to_string(&path).unwrap().contains("abc123"));
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].body.contains("user=alice"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn vote_sends_session_cookie() {
+        let _guard = ENV_LOCK.lock().await;
+        let (url, requests) = start_server().await;
+        let path = use_server(&url, "vote");
+        save_test_session("abc123");
+
+        let message = perform_aur_action("foo", AurAction::Vote, None)
+            .await
+            .unwrap();
+        assert_eq!(message, "✓ Voted for foo-base");
+
+        let requests = requests.lock().unwrap().clone();
+        let vote = requests.last().unwrap();
+        assert_eq!(vote.path, "/pkgbase/foo-base/vote");
+        assert_eq!(vote.cookie.as_deref(), Some("AURSID=abc123"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn flag_requires_and_posts_comment() {
+        let _guard = ENV_LOCK.lock().await;
+        let (url, requests) = start_server().await;
+        let path = use_server(&url, "flag");
+        save_test_session("abc123");
+
+        let error = perform_aur_action("foo", AurAction::Flag, Some("  ".to_string()))
+            .await
+            .unwrap_err();
+        assert!(error.contains("comment"));
+        assert!(requests.lock().unwrap().is_empty());
+
+        let message = perform_aur_action("foo", AurAction::Flag, Some("2.0 is out".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(message, "✓ Flagged foo-base as out-of-date");
+
+        let requests = requests.lock().unwrap().clone();
+        let flag = requests.last().unwrap();
+        assert_eq!(flag.path, "/pkgbase/foo-base/flag");
+        assert_eq!(flag.body, "comments=2.0+is+out");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn expired_session_is_cleared() {
+        let _guard = ENV_LOCK.lock().await;
+        let (url, _requests) = start_server().await;
+        let path = use_server(&url, "expired");
+        save_test_session("stale");
+
+        let error = perform_aur_action("foo", AurAction::Notify, None)
+            .await
+            .unwrap_err();
+        assert!(error.contains("expired"));
+        assert!(!path.exists());
+        assert!(!aur_account_status().logged_in);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::aur::rpc::{aur_base_url, fetch_aur_package};
+use crate::models::CommandResult;
+use crate::settings::load_settings;
+use crate::utils::{is_command_available, run_streaming};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+use tauri::Emitter;
+use tauri::Window;
+
+/// State of the clean build chroot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChrootStatus {
+    pub directory: String,
+    pub exists: bool,
+    pub devtools_installed: bool,
+    pub size: String,
+    pub enabled: bool,
+}
+
+/// Report where the chroot lives and whether it is usable
+pub fn get_chroot_status() -> ChrootStatus {
+    let settings = load_settings();
+    let directory = PathBuf::from(&settings.chroot_directory);
+    let exists = chroot_exists(&directory);
+
+    let size = if exists {
+        Command::new("/usr/bin/du")
+            .args(["-sh"])
+            .arg(&directory)
+            .output()
+            .ok()
+            .and_then(|out| {
+                String::from_utf8_lossy(&out.stdout)
+                    .split_whitespace()
+                    .next()
+                    .map(|s| s.to_string())
+            })
+            .unwrap_or_else(|| "Unknown".to_string())
+    } else {
+        "0".to_string()
+    };
+
+    ChrootStatus {
+        directory: settings.chroot_directory,
+        exists,
+        devtools_installed: is_command_available("mkarchroot")
+            && is_command_available("makechrootpkg"),
+        size,
+        enabled: settings.clean_chroot_builds,
+    }
+}
+
+/// Create the clean chroot with `mkarchroot`
+pub async fn create_chroot_async(window: Window) -> Result<CommandResult, String> {
+    ensure_devtools()?;
+    let directory = PathBuf::from(load_settings().chroot_directory);
+
+    if chroot_exists(&directory) {
+        return Err(format!(
+            "A chroot already exists at {}",
+            directory.display()
+        ));
+    }
+
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create chroot directory: {}", e))?;
+
+    tokio::spawn(async move {
+        let root = directory.join("root");
+        let success = run_streaming(
+            &window,
+            "chroot-log",
+            Command::new("/usr/bin/pkexec")
+                .arg("/usr/bin/mkarchroot")
+                .arg(&root)
+                .arg("base-devel"),
+        );
+
+        emit_chroot_complete(&window, success, "Chroot creation");
+    });
+
+    Ok(CommandResult::success(
+        "Chroot creation started".to_string(),
+    ))
+}
+
+/// Update the packages inside the clean chroot
+pub async fn update_chroot_async(window: Window) -> Result<CommandResult, String> {
+    ensure_devtools()?;
+    let directory = PathBuf::from(load_settings().chroot_directory);
+
+    if !chroot_exists(&directory) {
+        return Err("No chroot found. Create one first.".to_string());
+    }
+
+    tokio::spawn(async move {
+        let root = directory.join("root");
+        let success = run_streaming(
+            &window,
+            "chroot-log",
+            Command::new("/usr/bin/pkexec")
+                .arg("/usr/bin/arch-nspawn")
+                .arg(&root)
+                .args(["pacman", "-Syu", "--noconfirm"]),
+        );
+
+        emit_chroot_complete(&window, success, "Chroot update");
+    });
+
+    Ok(CommandResult::success("Chroot update started".to_string()))
+}
+
+/// Delete the clean chroot
+pub fn delete_chroot() -> Result<String, String> {
+    let directory = PathBuf::from(load_settings().chroot_directory);
+
+    // Only remove directories that really are devtools chroots
+    if !chroot_exists(&directory) {
+        return Err(format!("No chroot found at {}", directory.display()));
+    }
+    let directory = directory
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve chroot directory: {}", e))?;
+    if let Some(reason) = unsafe_to_delete(&directory) {
+        return Err(format!(
+            "Refusing to delete {}: {}",
+            directory.display(),
+            reason
+        ));
+    }
+
+    let output = Command::new("/usr/bin/pkexec")
+        .args(["/usr/bin/rm", "-rf", "--one-file-system"])
+        .arg(&directory)
+        .output()
+        .map_err(|e| format!("Failed to delete chroot: {}", e))?;
+
+    if output.status.success() {
+        Ok(format!("Chroot at {} deleted", directory.display()))
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+/// Build an AUR package in the clean chroot and install the result
+pub async fn install_aur_in_chroot_async(
+    window: Window,
+    package: String,
+) -> Result<CommandResult, String> {
+    ensure_devtools()?;
+    let directory = PathBuf::from(load_settings().chroot_directory);
+
+    if !chroot_exists(&directory) {
+        return Err(
+            "Clean chroot builds are enabled but no chroot exists. Create one in Settings first."
+                .to_string(),
+        );
+    }
+
+    let pkg_clone = package.clone();
+    tokio::spawn(async move {
+        let result = match build_in_chroot(&window, &pkg_clone, &directory).await {
+            Ok(built) => {
+                match crate::aur::store_chroot_build(&built) {
+                    Ok(stored) if !stored.is_empty() => {
+                        let _ = window.emit(
+                            "install-log",
+                            format!(
+                                "Added {} to [{}]",
+                                stored.join(", "),
+                                crate::aur::LOCAL_REPO_NAME
+                            ),
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        let _ = window.emit("install-log", format!("ERROR: {}", e));
+                    }
+                }
+
+                let _ = window.emit(
+                    "install-log",
+                    format!("Installing {} built package(s)", built.len()),
+                );
+                run_streaming(
+                    &window,
+                    "install-log",
+                    Command::new("/usr/bin/pkexec")
+                        .args(["/usr/bin/pacman", "-U", "--needed", "--noconfirm"])
+                        .args(&built),
+                )
+            }
+            Err(e) => {
+                let _ = window.emit("install-log", format!("ERROR: {}", e));
+                false
+            }
+        };
+
+        let message = if result {
+            let _ = crate::aur::record_installed_maintainer(&pkg_clone).await;
+            format!(
+                "✓ Installation of {} (clean chroot build) completed successfully!",
+                pkg_clone
+            )
+        } else {
+            format!("✗ Installation of {} failed!", pkg_clone)
+        };
+
+        let _ = window.emit(
+            "install-complete",
+            serde_json::json!({
+                "success": result,
+                "message": message
+            }),
+        );
+    });
+
+    Ok(CommandResult::success(format!(
+        "Clean chroot build of {} started",
+        package
+    )))
+}
+
+/// Clone (or update) the AUR repository and run `makechrootpkg`.
+///
+/// Returns the package files produced by the build.
+pub async fn build_in_chroot(
+    window: &Window,
+    package: &str,
+    chroot_directory: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    let info = fetch_aur_package(package).await?;
+    let build_dir = build_directory()?.join(&info.package_base);
+
+    let fetched = if build_dir.join(".git").exists() {
+        run_streaming(
+            window,
+            "install-log",
+            Command::new("/usr/bin/git")
+                .arg("-C")
+                .arg(&build_dir)
+                .args(["pull", "--ff-only"]),
+        )
+    } else {
+        run_streaming(
+            window,
+            "install-log",
+            Command::new("/usr/bin/git")
+                .arg("clone")
+                .arg(format!("{}/{}.git", aur_base_url(), info.package_base))
+                .arg(&build_dir),
+        )
+    };
+
+    if !fetched {
+        return Err(format!("Failed to fetch sources for {}", info.package_base));
+    }
+
+    let user = std::env::var("USER").map_err(|_| "Failed to determine current user".to_string())?;
+    let started = SystemTime::now();
+
+    // makechrootpkg needs root; pkexec does not preserve the working directory
+    let built = run_streaming(
+        window,
+        "install-log",
+        Command::new("/usr/bin/pkexec")
+            .arg("/usr/bin/env")
+            .arg("-C")
+            .arg(&build_dir)
+            .args(["/usr/bin/makechrootpkg", "-c", "-u", "-r"])
+            .arg(chroot_directory)
+            .args(["-U", &user]),
+    );
+
+    if !built {
+        return Err(format!("Building {} in the clean chroot failed", package));
+    }
+
+    let packages = find_built_packages(&build_dir, started)?;
+    if packages.is_empty() {
+        return Err(format!("No package files were produced for {}", package));
+    }
+
+    Ok(packages)
+}
+
+// Helper functions
+
+fn ensure_devtools() -> Result<(), String> {
+    if is_command_available("mkarchroot") && is_command_available("makechrootpkg") {
+        Ok(())
+    } else {
+        Err("devtools is not installed. Install it with: sudo pacman -S devtools".to_string())
+    }
+}
+
+fn chroot_exists(directory: &Path) -> bool {
+    // mkarchroot drops this marker into every chroot it creates
+    directory.join("root").join(".arch-chroot").exists()
+}
+
+/// Why `directory` (canonical) must never be removed recursively, if it must not
+fn unsafe_to_delete(directory: &Path) -> Option<&'static str> {
+    if directory.parent().is_none() {
+        return Some("it is the filesystem root");
+    }
+    if let Some(home) = dirs::home_dir().and_then(|home| home.canonicalize().ok()) {
+        if home.starts_with(directory) {
+            return Some("it contains the home directory");
+        }
+    }
+    None
+}
+
+fn build_directory() -> Result<PathBuf, String> {
+    let dir = dirs::cache_dir()
+        .ok_or("Failed to get cache directory")?
+        .join("guiman")
+        .join("build");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create build directory: {}", e))?;
+    Ok(dir)
+}
+
+fn find_built_packages(build_dir: &Path, since: SystemTime) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        fs::read_dir(build_dir).map_err(|e| format!("Failed to read build directory: {}", e))?;
+
+    let mut packages = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.contains(".pkg.tar") || name.ends_with(".sig") {
+            continue;
+        }
+
+        let modified = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        if modified >= since {
+            packages.push(path);
+        }
+    }
+
+    packages.sort();
+    Ok(packages)
+}
+
+fn emit_chroot_complete(window: &Window, success: bool, operation: &str) {
+    let message = if success {
+        format!("✓ {} completed successfully!", operation)
+    } else {
+        format!("✗ {} failed!", operation)
+    };
+
+    let _ = window.emit(
+        "chroot-complete",
+        serde_json::json!({
+            "success": success,
+            "message": message
+        }),
+    );
+}
@@ -0,0 +1,261 @@
+use crate::aur::rpc::{aur_base_url, fetch_aur_package};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A comment posted on an AUR package page
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AurComment {
+    pub id: u64,
+    pub author: String,
+    pub date: String,
+    pub edited: Option<String>,
+    pub content: String,
+    pub pinned: bool,
+}
+
+/// AUR package metadata combined with the package page comments
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AurPackageDetails {
+    pub name: String,
+    pub package_base: String,
+    pub version: String,
+    pub description: String,
+    pub url: String,
+    pub maintainer: Option<String>,
+    pub co_maintainers: Vec<String>,
+    pub submitter: Option<String>,
+    pub first_submitted: String,
+    pub last_updated: String,
+    pub out_of_date: Option<String>,
+    pub num_votes: i64,
+    pub popularity: f64,
+    pub comments: Vec<AurComment>,
+}
+
+/// Fetch AUR metadata and page comments for a package.
+///
+/// `offset` and `per_page` page through the (non-pinned) comments.
+pub async fn get_aur_package_details(
+    package: &str,
+    offset: Option<usize>,
+    per_page: Option<usize>,
+) -> Result<AurPackageDetails, String> {
+    let info = fetch_aur_package(package).await?;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/packages/{}", aur_base_url(), info.name))
+        .header("User-Agent", "GuiMan")
+        .query(&[
+            ("O", offset.unwrap_or(0).to_string()),
+            ("PP", per_page.unwrap_or(10).to_string()),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch AUR package page: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Failed to fetch AUR package page for {}: {}",
+            package,
+            response.status()
+        ));
+    }
+
+    let html = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read AUR package page: {}", e))?;
+
+    let mut comments = parse_aur_comments(&html)?;
+    // Pinned comments first, newest first within each group
+    comments.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.id.cmp(&a.id)));
+
+    Ok(AurPackageDetails {
+        name: info.name,
+        package_base: info.package_base,
+        version: info.version,
+        description: info.description.unwrap_or_default(),
+        url: info.url.unwrap_or_default(),
+        maintainer: info.maintainer,
+        co_maintainers: info.co_maintainers,
+        submitter: info.submitter,
+        first_submitted: format_unix_time(info.first_submitted),
+        last_updated: format_unix_time(info.last_modified),
+        out_of_date: info.out_of_date.map(format_unix_time),
+        num_votes: info.num_votes,
+        popularity: info.popularity,
+        comments,
+    })
+}
+
+/// Parse the comment sections of an AUR package page
+pub fn parse_aur_comments(html: &str) -> Result<Vec<AurComment>, String> {
+    let header_re =
+        Regex::new(r#"(?s)<h4 id="comment-(\d+)"[^>]*class="comment-header"[^>]*>(.*?)</h4>"#)
+            .map_err(|e| format!("Failed to compile regex: {}", e))?;
+    let author_re = Regex::new(r#"href="/account/([^"/]+)"#)
+        .map_err(|e| format!("Failed to compile regex: {}", e))?;
+    let date_re = Regex::new(r#"(?s)class="date"[^>]*>(.*?)</a>"#)
+        .map_err(|e| format!("Failed to compile regex: {}", e))?;
+    let edited_re = Regex::new(r#"edited on ([^<\n]+?\(UTC\))"#)
+        .map_err(|e| format!("Failed to compile regex: {}", e))?;
+    let content_re = Regex::new(r#"<div id="comment-\d+-content" class="article-content">"#)
+        .map_err(|e| format!("Failed to compile regex: {}", e))?;
+
+    let mut comments = Vec::new();
+
+    for section in html
+        .split(r#"<div class="comments package-comments">"#)
+        .skip(1)
+    {
+        let pinned = section
+            .split("</h3>")
+            .next()
+            .map(|heading| heading.contains("Pinned Comments"))
+            .unwrap_or(false);
+
+        let headers: Vec<_> = header_re.captures_iter(section).collect();
+        for (i, caps) in headers.iter().enumerate() {
+            let id: u64 = caps[1].parse().unwrap_or(0);
+            let header = &caps[2];
+
+            let author = author_re
+                .captures(header)
+                .map(|c| c[1].to_string())
+                .unwrap_or_else(|| html_to_text(header.split("commented on").next().unwrap_or("")));
+
+            let date = date_re
+                .captures(header)
+                .map(|c| html_to_text(&c[1]))
+                .unwrap_or_default();
+
+            let edited = edited_re.captures(header).map(|c| html_to_text(&c[1]));
+
+            // The comment body runs until the next comment header
+            let body_start = caps.get(0).map(|m| m.end()).unwrap_or(0);
+            let body_end = headers
+                .get(i + 1)
+                .and_then(|next| next.get(0))
+                .map(|m| m.start())
+                .unwrap_or(section.len());
+            let body = &section[body_start..body_end];
+
+            // Only the article-content div, not the pager or footer after the last comment
+            let content = content_re
+                .find(body)
+                .map(|m| html_to_text(div_contents(&body[m.end()..])))
+                .unwrap_or_default();
+
+            comments.push(AurComment {
+                id,
+                author,
+                date,
+                edited,
+                content,
+                pinned,
+            });
+        }
+    }
+
+    Ok(comments)
+}
+
+/// Contents of a div whose opening tag ends right before `html`, up to its matching `</div>`
+fn div_contents(html: &str) -> &str {
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let tag = &html[pos + offset..];
+        if tag.starts_with("</div>") {
+            if depth == 0 {
+                return &html[..pos + offset];
+            }
+            depth -= 1;
+        } else if tag.starts_with("<div") {
+            depth += 1;
+        }
+        pos += offset + 1;
+    }
+    html
+}
+
+fn html_to_text(html: &str) -> String {
+    // aurweb writes a newline after each <br>, which must not double the break
+    let with_breaks = html
+        .replace("<br>\n", "\n")
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("</p>", "\n")
+        .replace("</li>", "\n");
+
+    let mut text = String::with_capacity(with_breaks.len());
+    let mut in_tag = false;
+    for ch in with_breaks.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+
+    let decoded = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+
+    decoded
+        .lines()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn format_unix_time(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M (UTC)").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// aurweb package page, trimmed to the comment sections, pager and footer
+    const PACKAGE_PAGE: &str = include_str!("../../tests/fixtures/aur_package_page.html");
+
+    #[test]
+    fn parses_saved_package_page() {
+        let comments = parse_aur_comments(PACKAGE_PAGE).unwrap();
+        assert_eq!(comments.len(), 3);
+
+        let pinned = &comments[0];
+        assert_eq!(pinned.id, 912345);
+        assert_eq!(pinned.author, "maint");
+        assert_eq!(pinned.date, "2024-01-02 10:00 (UTC)");
+        assert!(pinned.pinned);
+        assert_eq!(
+            pinned.content,
+            "Please read the wiki before reporting build failures."
+        );
+
+        let edited = &comments[1];
+        assert_eq!(edited.author, "someone");
+        assert_eq!(edited.edited.as_deref(), Some("2024-03-05 09:12 (UTC)"));
+        assert!(!edited.pinned);
+        assert_eq!(edited.content, "Builds fine with\nmakepkg -si");
+
+        // The last comment stops at its own div, before the pager and footer
+        let last = &comments[2];
+        assert_eq!(last.author, "another");
+        assert_eq!(last.content, "Thanks & see you.");
+    }
+}
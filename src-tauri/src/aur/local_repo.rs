@@ -0,0 +1,438 @@
+use crate::aur::rpc::fetch_aur_package;
+use crate::models::CommandResult;
+use crate::settings::load_settings;
+use crate::utils::{run_streaming, write_system_file};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+use tauri::Emitter;
+use tauri::Window;
+
+/// Name of the pacman repository holding packages built by GuiMan
+pub const LOCAL_REPO_NAME: &str = "guiman-local";
+
+/// A package file stored in the local repository
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalRepoPackage {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub filename: String,
+    pub size: u64,
+    pub built: String,
+    /// Whether this is the version currently published in the repo database
+    pub current: bool,
+}
+
+/// State of the local repository
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocalRepoStatus {
+    pub name: String,
+    pub directory: String,
+    pub enabled: bool,
+    pub configured: bool,
+    pub package_files: usize,
+    pub packages: usize,
+    pub size: u64,
+}
+
+/// Report the local repository status
+pub fn get_local_repo_status() -> Result<LocalRepoStatus, String> {
+    let settings = load_settings();
+    let packages = list_local_repo_packages()?;
+
+    let mut names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    names.sort();
+    names.dedup();
+
+    Ok(LocalRepoStatus {
+        name: LOCAL_REPO_NAME.to_string(),
+        directory: settings.local_repo_directory,
+        enabled: settings.local_repo_enabled,
+        configured: is_repo_configured(),
+        package_files: packages.len(),
+        packages: names.len(),
+        size: packages.iter().map(|p| p.size).sum(),
+    })
+}
+
+/// List every package file in the local repository, newest first per package
+pub fn list_local_repo_packages() -> Result<Vec<LocalRepoPackage>, String> {
+    let directory = repo_directory();
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let current = database_entries(&directory);
+    let entries =
+        fs::read_dir(&directory).map_err(|e| format!("Failed to read local repository: {}", e))?;
+
+    let mut packages = Vec::new();
+    for entry in entries.flatten() {
+        let filename = entry.file_name().to_string_lossy().to_string();
+        let Some((name, version, arch)) = parse_package_filename(&filename) else {
+            continue;
+        };
+
+        let metadata = entry.metadata().ok();
+        let built = metadata
+            .as_ref()
+            .and_then(|meta| meta.modified().ok())
+            .map(|time| chrono::DateTime::<chrono::Local>::from(time).to_rfc3339())
+            .unwrap_or_default();
+
+        packages.push(LocalRepoPackage {
+            current: current.contains(&format!("{}-{}", name, version)),
+            name,
+            version,
+            arch,
+            filename,
+            size: metadata.map(|meta| meta.len()).unwrap_or(0),
+            built,
+        });
+    }
+
+    packages.sort_by(|a, b| a.name.cmp(&b.name).then(b.built.cmp(&a.built)));
+    Ok(packages)
+}
+
+/// Copy built package files into the local repository and update its database
+pub fn add_to_local_repo(files: &[PathBuf]) -> Result<Vec<String>, String> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let directory = repo_directory();
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create local repository: {}", e))?;
+
+    let mut added = Vec::new();
+    for file in files {
+        let filename = file
+            .file_name()
+            .ok_or_else(|| format!("Invalid package file: {}", file.display()))?;
+        let target = directory.join(filename);
+
+        if file != &target {
+            fs::copy(file, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", file.display(), e))?;
+
+            let signature = PathBuf::from(format!("{}.sig", file.display()));
+            if signature.exists() {
+                let _ = fs::copy(&signature, format!("{}.sig", target.display()));
+            }
+        }
+
+        added.push(target);
+    }
+
+    // Older package files stay on disk so they can be used for downgrades
+    let output = Command::new("/usr/bin/repo-add")
+        .arg(database_path(&directory))
+        .args(&added)
+        .output()
+        .map_err(|e| format!("Failed to run repo-add: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "repo-add failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(added
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Remove a package (all stored versions) from the local repository
+pub fn remove_from_local_repo(package: &str) -> Result<String, String> {
+    let directory = repo_directory();
+    let files: Vec<LocalRepoPackage> = list_local_repo_packages()?
+        .into_iter()
+        .filter(|p| p.name == package)
+        .collect();
+
+    if files.is_empty() {
+        return Err(format!("{} is not in the local repository", package));
+    }
+
+    let output = Command::new("/usr/bin/repo-remove")
+        .arg(database_path(&directory))
+        .arg(package)
+        .output()
+        .map_err(|e| format!("Failed to run repo-remove: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "repo-remove failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    for file in &files {
+        let path = directory.join(&file.filename);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(format!("{}.sig", path.display()));
+    }
+
+    Ok(format!(
+        "Removed {} ({} file(s)) from the local repository",
+        package,
+        files.len()
+    ))
+}
+
+/// Add the `[guiman-local]` section to /etc/pacman.conf (requires root)
+pub fn configure_local_repo() -> Result<String, String> {
+    if is_repo_configured() {
+        return Ok(format!("[{}] is already configured", LOCAL_REPO_NAME));
+    }
+
+    let directory = repo_directory();
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create local repository: {}", e))?;
+
+    // pacman refuses repositories without a database, so create an empty one
+    if !database_path(&directory).exists() {
+        let output = Command::new("/usr/bin/repo-add")
+            .arg(database_path(&directory))
+            .output()
+            .map_err(|e| format!("Failed to run repo-add: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+    }
+
+    let mut content = fs::read_to_string("/etc/pacman.conf")
+        .map_err(|e| format!("Failed to read pacman.conf: {}", e))?;
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!(
+        "\n[{}]\nSigLevel = Optional TrustAll\nServer = file://{}\n",
+        LOCAL_REPO_NAME,
+        directory.display()
+    ));
+
+    write_system_file("/etc/pacman.conf", &content)?;
+    Ok(format!(
+        "[{}] added to pacman.conf. Sync databases to start using it.",
+        LOCAL_REPO_NAME
+    ))
+}
+
+/// Install a specific package file from the local repository.
+///
+/// Installing an older file downgrades the package without rebuilding it.
+pub async fn install_from_local_repo_async(
+    window: Window,
+    filename: String,
+) -> Result<CommandResult, String> {
+    let not_in_repo = || format!("{} is not in the local repository", filename);
+    if parse_package_filename(&filename).is_none() {
+        return Err(not_in_repo());
+    }
+
+    // Resolve symlinks so the file handed to pacman really lives in the repository
+    let directory = repo_directory().canonicalize().map_err(|_| not_in_repo())?;
+    let path = directory
+        .join(&filename)
+        .canonicalize()
+        .map_err(|_| not_in_repo())?;
+    if path.parent() != Some(directory.as_path()) {
+        return Err(not_in_repo());
+    }
+
+    let file_clone = filename.clone();
+    tokio::spawn(async move {
+        let success = run_streaming(
+            &window,
+            "install-log",
+            Command::new("/usr/bin/pkexec")
+                .args(["/usr/bin/pacman", "-U", "--noconfirm"])
+                .arg(&path),
+        );
+
+        let message = if success {
+            format!("✓ Installed {} from the local repository", file_clone)
+        } else {
+            format!("✗ Installation of {} failed!", file_clone)
+        };
+
+        let _ = window.emit(
+            "install-complete",
+            serde_json::json!({
+                "success": success,
+                "message": message
+            }),
+        );
+    });
+
+    Ok(CommandResult::success(format!(
+        "Installation of {} started",
+        filename
+    )))
+}
+
+/// Collect packages an AUR helper just built and add them to the local repository
+pub async fn store_helper_build(
+    helper: &str,
+    package: &str,
+    since: SystemTime,
+) -> Result<Vec<String>, String> {
+    if !load_settings().local_repo_enabled {
+        return Ok(Vec::new());
+    }
+
+    let info = fetch_aur_package(package).await?;
+    let cache = dirs::cache_dir().ok_or("Failed to get cache directory")?;
+    let build_dir = match helper {
+        "paru" => cache.join("paru").join("clone").join(&info.package_base),
+        _ => cache.join("yay").join(&info.package_base),
+    };
+
+    let files = find_package_files(&build_dir, since);
+    add_to_local_repo(&files)
+}
+
+/// Add packages produced by a clean chroot build to the local repository
+pub fn store_chroot_build(files: &[PathBuf]) -> Result<Vec<String>, String> {
+    if !load_settings().local_repo_enabled {
+        return Ok(Vec::new());
+    }
+
+    add_to_local_repo(files)
+}
+
+// Helper functions
+
+fn repo_directory() -> PathBuf {
+    PathBuf::from(load_settings().local_repo_directory)
+}
+
+fn database_path(directory: &Path) -> PathBuf {
+    directory.join(format!("{}.db.tar.zst", LOCAL_REPO_NAME))
+}
+
+fn is_repo_configured() -> bool {
+    fs::read_to_string("/etc/pacman.conf")
+        .map(|content| {
+            content
+                .lines()
+                .any(|line| line.trim() == format!("[{}]", LOCAL_REPO_NAME))
+        })
+        .unwrap_or(false)
+}
+
+/// `name-version` entries currently published in the repo database
+fn database_entries(directory: &Path) -> Vec<String> {
+    let output = Command::new("/usr/bin/tar")
+        .arg("-tf")
+        .arg(database_path(directory))
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_suffix("/desc"))
+            .map(|entry| entry.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn find_package_files(directory: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.contains(".pkg.tar") && !name.ends_with(".sig")
+        })
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .map(|modified| modified >= since)
+                .unwrap_or(false)
+        })
+        .map(|entry| entry.path())
+        .collect();
+
+    files.sort();
+    files
+}
+
+/// Split `name-pkgver-pkgrel-arch.pkg.tar.*` into name, version and arch.
+/// Anything that is not a plain file name, such as a path, is rejected.
+pub fn parse_package_filename(filename: &str) -> Option<(String, String, String)> {
+    if filename.ends_with(".sig") || filename.contains('/') || filename.starts_with('.') {
+        return None;
+    }
+
+    let stem = &filename[..filename.find(".pkg.tar")?];
+    let mut parts = stem.rsplitn(4, '-');
+    let arch = parts.next()?;
+    let pkgrel = parts.next()?;
+    let pkgver = parts.next()?;
+    let name = parts.next()?;
+
+    Some((
+        name.to_string(),
+        format!("{}-{}", pkgver, pkgrel),
+        arch.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_package_filenames() {
+        assert_eq!(
+            parse_package_filename("yay-bin-12.3.5-1-x86_64.pkg.tar.zst"),
+            Some((
+                "yay-bin".to_string(),
+                "12.3.5-1".to_string(),
+                "x86_64".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_package_filename("python-foo-1:2.0-3-any.pkg.tar.xz"),
+            Some((
+                "python-foo".to_string(),
+                "1:2.0-3".to_string(),
+                "any".to_string()
+            ))
+        );
+
+        assert_eq!(
+            parse_package_filename("yay-bin-12.3.5-1-x86_64.pkg.tar.zst.sig"),
+            None
+        );
+        assert_eq!(parse_package_filename("guiman-local.db.tar.zst"), None);
+        assert_eq!(parse_package_filename("x-1-x86_64.pkg.tar.zst"), None);
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_repository() {
+        for filename in [
+            "../../tmp/x-1-1-x86_64.pkg.tar.zst",
+            "/tmp/x-1-1-x86_64.pkg.tar.zst",
+            "sub/x-1-1-x86_64.pkg.tar.zst",
+            "..x-1-1-x86_64.pkg.tar.zst",
+            ".hidden-1-1-x86_64.pkg.tar.zst",
+        ] {
+            assert_eq!(parse_package_filename(filename), None, "{}", filename);
+        }
+    }
+}
@@ -1,5 +1,16 @@
+pub mod account;
+pub mod chroot;
+pub mod details;
+pub mod local_repo;
 pub mod operations;
+pub mod risk;
+pub mod rpc;
 pub mod search;
 
+pub use account::*;
+pub use chroot::*;
+pub use details::*;
+pub use local_repo::*;
 pub use operations::*;
+pub use risk::*;
 pub use search::*;
@@ -3,6 +3,7 @@ use crate::utils::is_command_available;
 use serde_json;
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
+use std::time::SystemTime;
 use tauri::Window;
 use tauri::Emitter;
 use tokio;
@@ -75,105 +76,6 @@ pub fn list_aur_packages(helper: &str) -> Result<Vec<PackageInfo>, String> {
     Ok(packages)
 }
 
-/// Vote for an AUR package
-pub fn vote_aur_package(package: &str, helper: &str) -> Result<String, String> {
-    let helper_cmd = match helper {
-        "yay" => "yay",
-        "paru" => "paru",
-        _ => return Err("Invalid AUR helper".to_string()),
-    };
-
-    if !is_command_available(helper_cmd) {
-        return Err(format!("{} is not installed", helper_cmd));
-    }
-
-    // Check if package exists in AUR first
-    let output = Command::new(format!("/usr/bin/{}", helper_cmd))
-        .args(["-Si", package])
-        .output()
-        .map_err(|e| format!("Failed to check package: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!("Package {} not found in AUR", package));
-    }
-
-    // Since voting requires AUR account and web interface, provide helpful message
-    let aur_url = format!("https://aur.archlinux.org/packages/{}", package);
-    Err(format!("To vote for this package:\n1. Visit: {}\n2. Log in with your AUR account\n3. Click the 'Vote' button\n\nNote: You need an AUR account to vote.", aur_url))
-}
-
-/// Flag AUR package as out-of-date
-pub fn flag_aur_package(
-    package: &str,
-    helper: &str,
-    comment: Option<String>,
-) -> Result<String, String> {
-    let helper_cmd = match helper {
-        "yay" => "yay",
-        "paru" => "paru",
-        _ => return Err("Invalid AUR helper".to_string()),
-    };
-
-    if !is_command_available(helper_cmd) {
-        return Err(format!("{} is not installed", helper_cmd));
-    }
-
-    // Check if package exists in AUR first
-    let output = Command::new(format!("/usr/bin/{}", helper_cmd))
-        .args(["-Si", package])
-        .output()
-        .map_err(|e| format!("Failed to check package: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!("Package {} not found in AUR", package));
-    }
-
-    // Flagging requires web interface
-    let aur_url = format!("https://aur.archlinux.org/packages/{}", package);
-    let message = if let Some(comment) = comment {
-        format!("To flag this package as out-of-date:\n1. Visit: {}\n2. Log in with your AUR account\n3. Click 'Flag Package Out-of-Date'\n4. Add your comment: {}\n\nNote: Only flag if the package is actually outdated.", aur_url, comment)
-    } else {
-        format!("To flag this package as out-of-date:\n1. Visit: {}\n2. Log in with your AUR account\n3. Click 'Flag Package Out-of-Date'\n\nNote: Only flag if the package is actually outdated.", aur_url)
-    };
-
-    Err(message)
-}
-
-/// Adopt an AUR package (for maintainers)
-pub fn adopt_aur_package(package: &str, helper: &str) -> Result<String, String> {
-    let helper_cmd = match helper {
-        "yay" => "yay",
-        "paru" => "paru",
-        _ => return Err("Invalid AUR helper".to_string()),
-    };
-
-    if !is_command_available(helper_cmd) {
-        return Err(format!("{} is not installed", helper_cmd));
-    }
-
-    // Check if package exists in AUR first
-    let output = Command::new(format!("/usr/bin/{}", helper_cmd))
-        .args(["-Si", package])
-        .output()
-        .map_err(|e| format!("Failed to check package: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!("Package {} not found in AUR", package));
-    }
-
-    // Check if package is orphaned by looking for "Maintainer: None"
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let is_orphaned = stdout.contains("Maintainer") && stdout.contains("None");
-
-    let aur_url = format!("https://aur.archlinux.org/packages/{}", package);
-
-    if is_orphaned {
-        Err(format!("To adopt this orphaned package:\n1. Visit: {}\n2. Log in with your AUR account\n3. Click 'Adopt Package'\n\nNote: You will become the maintainer and be responsible for updates.", aur_url))
-    } else {
-        Err(format!("This package already has a maintainer.\nVisit: {} to see package details.\n\nNote: Only orphaned packages can be adopted.", aur_url))
-    }
-}
-
 /// Get AUR package build options and dependencies
 pub fn get_aur_build_info(package: &str, helper: &str) -> Result<String, String> {
     let helper_cmd = match helper {
@@ -270,6 +172,7 @@ pub async fn install_aur_package_async(
             return;
         }
 
+        let started = SystemTime::now();
         let child = Command::new(format!("/usr/bin/{}", helper_cmd))
             .args(["-S", "--needed", "--noconfirm", &pkg_clone])
             .stdout(Stdio::piped())
@@ -313,6 +216,28 @@ pub async fn install_aur_package_async(
                     format!("✗ Installation of {} failed!", pkg_clone)
                 };
 
+                if success {
+                    // Baseline for detecting maintainer changes later on
+                    let _ = crate::aur::record_installed_maintainer(&pkg_clone).await;
+
+                    match crate::aur::store_helper_build(helper_cmd, &pkg_clone, started).await {
+                        Ok(stored) if !stored.is_empty() => {
+                            let _ = window.emit(
+                                "install-log",
+                                format!(
+                                    "Added {} to [{}]",
+                                    stored.join(", "),
+                                    crate::aur::LOCAL_REPO_NAME
+                                ),
+                            );
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            let _ = window.emit("install-log", format!("ERROR: {}", e));
+                        }
+                    }
+                }
+
                 let _ = window.emit(
                     "install-complete",
                     serde_json::json!({
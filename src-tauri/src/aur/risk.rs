@@ -0,0 +1,523 @@
+use crate::aur::rpc::{aur_base_url, fetch_aur_info, AurRpcPackage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Overall risk classification shown as a badge
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Some(RiskLevel::Low),
+            "medium" => Some(RiskLevel::Medium),
+            "high" => Some(RiskLevel::High),
+            _ => None,
+        }
+    }
+
+    fn from_score(score: i32) -> Self {
+        if score >= 75 {
+            RiskLevel::Low
+        } else if score >= 50 {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::High
+        }
+    }
+}
+
+/// A single reason contributing to the risk score
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RiskFactor {
+    pub code: String,
+    pub description: String,
+    pub penalty: i32,
+}
+
+/// Trust assessment of an AUR package
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AurRiskAssessment {
+    pub package: String,
+    pub trust_score: i32,
+    pub level: RiskLevel,
+    pub factors: Vec<RiskFactor>,
+    pub source_domains: Vec<String>,
+    pub sources_checked: bool,
+}
+
+/// Assess AUR packages from RPC metadata.
+///
+/// With `include_sources` the `.SRCINFO` of each package is fetched as well so
+/// source domains and checksum coverage can be taken into account.
+pub async fn assess_aur_packages(
+    packages: &[String],
+    include_sources: bool,
+) -> Result<Vec<AurRiskAssessment>, String> {
+    let infos = fetch_aur_info(packages).await?;
+    let installed = list_foreign_packages();
+    let mut maintainers = load_recorded_maintainers();
+    let mut maintainers_changed = false;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut assessments = Vec::new();
+    for info in &infos {
+        let is_installed = installed.contains(&info.name);
+
+        // First time we see an installed package, remember who maintained it
+        if is_installed && !maintainers.contains_key(&info.name) {
+            if let Some(maintainer) = &info.maintainer {
+                maintainers.insert(info.name.clone(), maintainer.clone());
+                maintainers_changed = true;
+            }
+        }
+
+        let recorded = if is_installed {
+            maintainers.get(&info.name).map(|m| m.as_str())
+        } else {
+            None
+        };
+        let mut factors = assess_metadata(info, recorded, now);
+
+        let mut source_domains = Vec::new();
+        let mut sources_checked = false;
+        if include_sources {
+            if let Ok(srcinfo) = fetch_srcinfo(&info.package_base).await {
+                let analysis = analyze_srcinfo(&srcinfo);
+                factors.extend(assess_sources(&info.name, &analysis));
+                source_domains = analysis.domains.into_iter().collect();
+                sources_checked = true;
+            }
+        }
+
+        let trust_score = (100 - factors.iter().map(|f| f.penalty).sum::<i32>()).max(0);
+        assessments.push(AurRiskAssessment {
+            package: info.name.clone(),
+            trust_score,
+            level: RiskLevel::from_score(trust_score),
+            factors,
+            source_domains,
+            sources_checked,
+        });
+    }
+
+    if maintainers_changed {
+        let _ = save_recorded_maintainers(&maintainers);
+    }
+
+    Ok(assessments)
+}
+
+/// Remember the current maintainer of an AUR package after installing it
+pub async fn record_installed_maintainer(package: &str) -> Result<(), String> {
+    let infos = fetch_aur_info(&[package.to_string()]).await?;
+    let mut maintainers = load_recorded_maintainers();
+
+    if let Some(maintainer) = infos.into_iter().find_map(|info| info.maintainer) {
+        maintainers.insert(package.to_string(), maintainer);
+    } else {
+        maintainers.remove(package);
+    }
+
+    save_recorded_maintainers(&maintainers)
+}
+
+fn assess_metadata(
+    info: &AurRpcPackage,
+    recorded_maintainer: Option<&str>,
+    now: i64,
+) -> Vec<RiskFactor> {
+    let mut factors = Vec::new();
+
+    match (&info.maintainer, recorded_maintainer) {
+        (None, _) => factors.push(factor(
+            "orphaned",
+            "Package is orphaned and has no maintainer".to_string(),
+            25,
+        )),
+        (Some(current), Some(recorded)) if current != recorded => factors.push(factor(
+            "maintainer_changed",
+            format!(
+                "Maintainer changed from {} to {} since install",
+                recorded, current
+            ),
+            20,
+        )),
+        _ => {}
+    }
+
+    if let Some(flagged) = info.out_of_date {
+        let days = (now - flagged) / DAY;
+        factors.push(factor(
+            "out_of_date",
+            format!("Flagged out-of-date {} days ago", days),
+            if days > 180 { 20 } else { 10 },
+        ));
+    }
+
+    if info.num_votes < 5 && info.popularity < 0.1 {
+        factors.push(factor(
+            "low_adoption",
+            format!("Only {} votes and negligible popularity", info.num_votes),
+            15,
+        ));
+    } else if info.num_votes < 20 {
+        factors.push(factor(
+            "few_votes",
+            format!("Only {} votes", info.num_votes),
+            5,
+        ));
+    }
+
+    let days_since_update = (now - info.last_modified) / DAY;
+    if days_since_update > 730 {
+        factors.push(factor(
+            "stale",
+            format!("Not updated for {} days", days_since_update),
+            15,
+        ));
+    } else if days_since_update > 365 {
+        factors.push(factor(
+            "aging",
+            format!("Not updated for {} days", days_since_update),
+            5,
+        ));
+    }
+
+    let days_since_submission = (now - info.first_submitted) / DAY;
+    if days_since_submission < 30 {
+        factors.push(factor(
+            "new_package",
+            format!("Submitted only {} days ago", days_since_submission),
+            10,
+        ));
+    }
+
+    factors
+}
+
+#[derive(Debug, Default)]
+struct SourceAnalysis {
+    domains: BTreeSet<String>,
+    insecure: Vec<String>,
+    unverified: Vec<String>,
+}
+
+fn assess_sources(package: &str, analysis: &SourceAnalysis) -> Vec<RiskFactor> {
+    let mut factors = Vec::new();
+
+    if !analysis.insecure.is_empty() {
+        factors.push(factor(
+            "insecure_source",
+            format!(
+                "Downloads over plain HTTP: {}",
+                analysis.insecure.join(", ")
+            ),
+            15,
+        ));
+    }
+
+    if !analysis.unverified.is_empty() {
+        if package.ends_with("-bin") {
+            factors.push(factor(
+                "unverified_binary",
+                format!(
+                    "Prebuilt binaries are not verified by checksum or signature: {}",
+                    analysis.unverified.join(", ")
+                ),
+                25,
+            ));
+        } else {
+            factors.push(factor(
+                "unverified_source",
+                format!(
+                    "Sources without checksums: {}",
+                    analysis.unverified.join(", ")
+                ),
+                10,
+            ));
+        }
+    }
+
+    factors
+}
+
+async fn fetch_srcinfo(package_base: &str) -> Result<String, String> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/cgit/aur.git/plain/.SRCINFO", aur_base_url()))
+        .header("User-Agent", "GuiMan")
+        .query(&[("h", package_base)])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch .SRCINFO: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch .SRCINFO for {}", package_base));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read .SRCINFO: {}", e))
+}
+
+fn analyze_srcinfo(srcinfo: &str) -> SourceAnalysis {
+    // Sources and checksums are paired by position within the same arch suffix
+    let mut sources: HashMap<String, Vec<String>> = HashMap::new();
+    let mut sums_by_algorithm: HashMap<(String, String), Vec<String>> = HashMap::new();
+    let mut has_pgp_keys = false;
+
+    for line in srcinfo.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let value = value.trim().to_string();
+
+        if key == "validpgpkeys" {
+            has_pgp_keys = true;
+        } else if let Some(suffix) = key.strip_prefix("source") {
+            sources.entry(suffix.to_string()).or_default().push(value);
+        } else if let Some(pos) = key.find("sums") {
+            let algorithm = key[..pos].to_string();
+            let suffix = key[pos + 4..].to_string();
+            sums_by_algorithm
+                .entry((algorithm, suffix))
+                .or_default()
+                .push(value);
+        }
+    }
+
+    let mut sums: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    for ((_, suffix), values) in sums_by_algorithm {
+        sums.entry(suffix).or_default().push(values);
+    }
+
+    let mut analysis = SourceAnalysis::default();
+    for (suffix, entries) in &sources {
+        for (index, entry) in entries.iter().enumerate() {
+            let url = entry.rsplit("::").next().unwrap_or(entry);
+            let Some(scheme_end) = url.find("://") else {
+                continue; // Local file shipped with the PKGBUILD
+            };
+
+            let scheme = &url[..scheme_end];
+            let host = url[scheme_end + 3..]
+                .split(['/', ':', '?'])
+                .next()
+                .unwrap_or("")
+                .to_lowercase();
+            if !host.is_empty() {
+                analysis.domains.insert(host);
+            }
+
+            if scheme == "http" || scheme.ends_with("+http") {
+                analysis.insecure.push(url.to_string());
+            }
+
+            let is_vcs = scheme.contains('+') || matches!(scheme, "git" | "svn" | "hg" | "bzr");
+            let is_signature = url.ends_with(".sig") || url.ends_with(".asc");
+            if is_vcs || is_signature {
+                continue;
+            }
+
+            let verified = sums.get(suffix).is_some_and(|groups| {
+                groups
+                    .iter()
+                    .any(|values| values.get(index).is_some_and(|v| v != "SKIP"))
+            });
+            // Only a detached signature of this very file counts
+            let signed = has_pgp_keys
+                && entries.iter().any(|other| {
+                    let other = other.rsplit("::").next().unwrap_or(other);
+                    other
+                        .strip_prefix(url)
+                        .is_some_and(|ext| ext == ".sig" || ext == ".asc")
+                });
+
+            if !verified && !signed {
+                analysis.unverified.push(url.to_string());
+            }
+        }
+    }
+
+    analysis
+}
+
+fn factor(code: &str, description: String, penalty: i32) -> RiskFactor {
+    RiskFactor {
+        code: code.to_string(),
+        description,
+        penalty,
+    }
+}
+
+fn list_foreign_packages() -> HashSet<String> {
+    Command::new("/usr/bin/pacman")
+        .args(["-Qmq"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn maintainers_file() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("guiman")
+            .join("aur_maintainers.json"),
+    )
+}
+
+fn load_recorded_maintainers() -> HashMap<String, String> {
+    maintainers_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_recorded_maintainers(maintainers: &HashMap<String, String>) -> Result<(), String> {
+    let path = maintainers_file().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(maintainers)
+        .map_err(|e| format!("Failed to serialize maintainer records: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save maintainer records: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRCINFO: &str = "\
+pkgbase = example-bin
+\tpkgver = 1.0
+\tsource = LICENSE
+\tsource = http://example.org/example-1.0.tar.gz
+\tsource = http://example.org/example-1.0.tar.gz.sig
+\tsource = extra-1.0.tar.gz::https://downloads.example.org/extra.tar.gz
+\tsource = git+https://github.com/example/example.git
+\tvalidpgpkeys = 0123456789ABCDEF0123456789ABCDEF01234567
+\tsha256sums = SKIP
+\tsha256sums = SKIP
+\tsha256sums = SKIP
+\tsha256sums = SKIP
+\tsha256sums = SKIP
+\tsource_x86_64 = https://cdn.example.net/example-x86_64.tar.gz
+\tsha256sums_x86_64 = 2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae
+\tsource_aarch64 = https://cdn.example.net/example-aarch64.tar.gz
+\tsha256sums_aarch64 = SKIP
+
+pkgname = example-bin
+";
+
+    fn package(maintainer: Option<&str>, num_votes: i64, now: i64) -> AurRpcPackage {
+        serde_json::from_value(serde_json::json!({
+            "ID": 1,
+            "Name": "example-bin",
+            "PackageBaseID": 1,
+            "PackageBase": "example-bin",
+            "Version": "1.0-1",
+            "NumVotes": num_votes,
+            "Popularity": 1.5,
+            "Maintainer": maintainer,
+            "FirstSubmitted": now - 1000 * DAY,
+            "LastModified": now - 10 * DAY,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn signature_only_covers_its_own_source() {
+        let analysis = analyze_srcinfo(SRCINFO);
+
+        assert_eq!(
+            analysis.domains.into_iter().collect::<Vec<_>>(),
+            [
+                "cdn.example.net",
+                "downloads.example.org",
+                "example.org",
+                "github.com"
+            ]
+        );
+        assert_eq!(
+            analysis.insecure,
+            [
+                "http://example.org/example-1.0.tar.gz",
+                "http://example.org/example-1.0.tar.gz.sig"
+            ]
+        );
+
+        // The signed tarball, the VCS source, the local file and the checksummed
+        // x86_64 source are fine; the renamed download and aarch64 source are not
+        let mut unverified = analysis.unverified;
+        unverified.sort();
+        assert_eq!(
+            unverified,
+            [
+                "https://cdn.example.net/example-aarch64.tar.gz",
+                "https://downloads.example.org/extra.tar.gz"
+            ]
+        );
+    }
+
+    #[test]
+    fn signature_without_keys_does_not_verify() {
+        let srcinfo = SRCINFO.replace("validpgpkeys", "# validpgpkeys");
+        let analysis = analyze_srcinfo(&srcinfo);
+        assert!(analysis
+            .unverified
+            .contains(&"http://example.org/example-1.0.tar.gz".to_string()));
+    }
+
+    #[test]
+    fn scores_binary_packages_by_source_verification() {
+        let analysis = analyze_srcinfo(SRCINFO);
+
+        let factors = assess_sources("example-bin", &analysis);
+        let codes: Vec<_> = factors.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, ["insecure_source", "unverified_binary"]);
+        assert_eq!(factors.iter().map(|f| f.penalty).sum::<i32>(), 40);
+
+        let factors = assess_sources("example", &analysis);
+        assert_eq!(factors[1].code, "unverified_source");
+        assert_eq!(factors[1].penalty, 10);
+    }
+
+    #[test]
+    fn scores_metadata() {
+        let now = 1_800_000_000;
+
+        let healthy = assess_metadata(&package(Some("alice"), 150, now), Some("alice"), now);
+        assert!(healthy.is_empty());
+
+        let factors = assess_metadata(&package(None, 3, now), None, now);
+        let codes: Vec<_> = factors.iter().map(|f| f.code.as_str()).collect();
+        assert_eq!(codes, ["orphaned", "few_votes"]);
+
+        let factors = assess_metadata(&package(Some("mallory"), 150, now), Some("alice"), now);
+        assert_eq!(factors[0].code, "maintainer_changed");
+
+        let score = 100 - factors.iter().map(|f| f.penalty).sum::<i32>();
+        assert_eq!(RiskLevel::from_score(score), RiskLevel::Low);
+        assert_eq!(RiskLevel::from_score(60), RiskLevel::Medium);
+        assert_eq!(RiskLevel::from_score(49), RiskLevel::High);
+    }
+}
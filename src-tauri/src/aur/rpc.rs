@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// Default AUR web endpoint
+pub const AUR_BASE_URL: &str = "https://aur.archlinux.org";
+
+/// Package metadata as returned by the AUR RPC interface (v5)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "PascalCase"))]
+pub struct AurRpcPackage {
+    #[serde(rename(deserialize = "ID"))]
+    pub id: i64,
+    pub name: String,
+    #[serde(rename(deserialize = "PackageBaseID"))]
+    pub package_base_id: i64,
+    pub package_base: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, rename(deserialize = "URL"))]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub num_votes: i64,
+    #[serde(default)]
+    pub popularity: f64,
+    #[serde(default)]
+    pub out_of_date: Option<i64>,
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub submitter: Option<String>,
+    #[serde(default)]
+    pub first_submitted: i64,
+    #[serde(default)]
+    pub last_modified: i64,
+    #[serde(default, rename(deserialize = "URLPath"))]
+    pub url_path: Option<String>,
+    #[serde(default)]
+    pub depends: Vec<String>,
+    #[serde(default)]
+    pub make_depends: Vec<String>,
+    #[serde(default)]
+    pub opt_depends: Vec<String>,
+    #[serde(default)]
+    pub provides: Vec<String>,
+    #[serde(default)]
+    pub license: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub co_maintainers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AurRpcResponse {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    results: Vec<AurRpcPackage>,
+}
+
+/// Base URL of the AUR web interface.
+///
+/// `GUIMAN_AUR_URL` overrides the default so a local stand-in server can be used.
+pub fn aur_base_url() -> String {
+    std::env::var("GUIMAN_AUR_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| AUR_BASE_URL.to_string())
+}
+
+/// Fetch RPC info for one or more AUR packages
+pub async fn fetch_aur_info(packages: &[String]) -> Result<Vec<AurRpcPackage>, String> {
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut query: Vec<(&str, &str)> = Vec::new();
+    for package in packages {
+        query.push(("arg[]", package.as_str()));
+    }
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/rpc/v5/info", aur_base_url()))
+        .header("User-Agent", "GuiMan")
+        .query(&query)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query AUR RPC: {}", e))?;
+
+    parse_rpc_response(response).await
+}
+
+/// Fetch RPC info for a single AUR package
+pub async fn fetch_aur_package(package: &str) -> Result<AurRpcPackage, String> {
+    fetch_aur_info(&[package.to_string()])
+        .await?
+        .into_iter()
+        .find(|pkg| pkg.name == package)
+        .ok_or_else(|| format!("Package {} not found in AUR", package))
+}
+
+async fn parse_rpc_response(response: reqwest::Response) -> Result<Vec<AurRpcPackage>, String> {
+    if !response.status().is_success() {
+        return Err(format!("AUR RPC request failed: {}", response.status()));
+    }
+
+    let body: AurRpcResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse AUR RPC response: {}", e))?;
+
+    if body.kind == "error" {
+        return Err(format!(
+            "AUR RPC error: {}",
+            body.error.unwrap_or_else(|| "unknown error".to_string())
+        ));
+    }
+
+    Ok(body.results)
+}
@@ -0,0 +1,274 @@
+use super::{
+    BackupConfigFile, BackupPackage, BackupRepository, SystemBackup, BACKUP_SCHEMA_VERSION,
+};
+use crate::aur::rpc::fetch_aur_info;
+use crate::pacman::database::load_local_records;
+use crate::pacman::package_index;
+use base64::Engine;
+use std::collections::HashSet;
+use std::fs;
+use std::process::Command;
+
+const PACMAN_CONF: &str = "/etc/pacman.conf";
+const MIRRORLIST: &str = "/etc/pacman.d/mirrorlist";
+
+/// Record the current package state. Foreign packages are marked `local`
+/// until [`mark_aur_packages`] checks them against the AUR.
+pub fn capture_system_state(
+    name: &str,
+    description: &str,
+    timestamp: &str,
+    include_config_files: bool,
+) -> Result<SystemBackup, String> {
+    let index = package_index()?;
+    let mut packages: Vec<BackupPackage> = load_local_records()?
+        .into_iter()
+        .map(|record| BackupPackage {
+            repo: Some(
+                index
+                    .get(&record.name)
+                    .map(|r| r.repo.clone())
+                    .unwrap_or_else(|| "local".to_string()),
+            ),
+            reason: record
+                .install_reason
+                .unwrap_or_else(|| "explicit".to_string()),
+            version: Some(record.version),
+            name: record.name,
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let (config_files, unreadable_files) = if include_config_files {
+        modified_config_files()?
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    Ok(SystemBackup {
+        schema_version: BACKUP_SCHEMA_VERSION,
+        name: name.to_string(),
+        timestamp: timestamp.to_string(),
+        description: description.to_string(),
+        hostname: fs::read_to_string("/etc/hostname")
+            .ok()
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty()),
+        packages,
+        repositories: configured_repositories(),
+        pacman_conf: fs::read_to_string(PACMAN_CONF).ok(),
+        mirrorlist: fs::read_to_string(MIRRORLIST).ok(),
+        ignore_packages: pacman_conf_values("IgnorePkg"),
+        ignore_groups: pacman_conf_values("IgnoreGroup"),
+        config_files,
+        unreadable_files,
+        trigger: None,
+    })
+}
+
+/// Capture the package state off the async runtime and mark AUR packages.
+/// Offline, foreign packages stay marked `local`.
+pub async fn capture_current_state(
+    name: String,
+    description: String,
+    timestamp: String,
+    include_config_files: bool,
+) -> Result<SystemBackup, String> {
+    let mut state = tokio::task::spawn_blocking(move || {
+        capture_system_state(&name, &description, &timestamp, include_config_files)
+    })
+    .await
+    .map_err(|e| format!("Failed to capture system state: {}", e))??;
+
+    let _ = mark_aur_packages(&mut state).await;
+    Ok(state)
+}
+
+/// Mark foreign packages that exist in the AUR as `aur`
+pub async fn mark_aur_packages(backup: &mut SystemBackup) -> Result<(), String> {
+    let foreign: Vec<String> = backup
+        .packages
+        .iter()
+        .filter(|p| p.repo.as_deref() == Some("local"))
+        .map(|p| p.name.clone())
+        .collect();
+
+    let mut found = HashSet::new();
+    // Keep request URLs at a reasonable length
+    for chunk in foreign.chunks(100) {
+        for package in fetch_aur_info(chunk).await? {
+            found.insert(package.name);
+        }
+    }
+
+    for package in &mut backup.packages {
+        if found.contains(&package.name) {
+            package.repo = Some("aur".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Values of a pacman.conf option, as resolved by `pacman-conf`
+pub fn pacman_conf_values(option: &str) -> Vec<String> {
+    Command::new("/usr/bin/pacman-conf")
+        .arg(option)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Sync repositories with their servers, following `Include` lines through `pacman-conf`
+pub fn configured_repositories() -> Vec<BackupRepository> {
+    let Ok(output) = Command::new("/usr/bin/pacman-conf")
+        .arg("--repo-list")
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let mut repository = BackupRepository {
+                name: name.to_string(),
+                servers: Vec::new(),
+                sig_level: Vec::new(),
+            };
+            let details = Command::new("/usr/bin/pacman-conf")
+                .arg(format!("--repo={}", name))
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+                .unwrap_or_default();
+            for line in details.lines() {
+                match line.split_once('=') {
+                    Some((key, value)) if key.trim() == "Server" => {
+                        repository.servers.push(value.trim().to_string())
+                    }
+                    Some((key, value)) if key.trim() == "SigLevel" => {
+                        repository.sig_level.push(value.trim().to_string())
+                    }
+                    _ => {}
+                }
+            }
+            repository
+        })
+        .collect()
+}
+
+// Helper functions
+
+/// Backup files pacman reports as modified, with their contents
+fn modified_config_files() -> Result<(Vec<BackupConfigFile>, Vec<String>), String> {
+    // The parser matches English field names
+    let output = Command::new("/usr/bin/pacman")
+        .arg("-Qii")
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| format!("Failed to query backup files: {}", e))?;
+
+    let mut files = Vec::new();
+    let mut unreadable = Vec::new();
+    for (package, path) in parse_modified_backup_files(&String::from_utf8_lossy(&output.stdout)) {
+        match fs::read(&path) {
+            Ok(bytes) => {
+                let (encoding, content) = match String::from_utf8(bytes) {
+                    Ok(text) => ("utf-8", text),
+                    Err(e) => (
+                        "base64",
+                        base64::engine::general_purpose::STANDARD.encode(e.into_bytes()),
+                    ),
+                };
+                files.push(BackupConfigFile {
+                    path,
+                    package,
+                    encoding: encoding.to_string(),
+                    content,
+                });
+            }
+            Err(_) => unreadable.push(path),
+        }
+    }
+
+    Ok((files, unreadable))
+}
+
+/// `(package, path)` of the `Backup Files` entries marked modified in `pacman -Qii` output.
+/// Entries are a status and a path separated by a tab, e.g. `MODIFIED /etc/foo.conf`.
+fn parse_modified_backup_files(output: &str) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    let mut package = String::new();
+    let mut in_backup = false;
+
+    for line in output.lines() {
+        let entry =
+            if let Some((key, value)) = line.split_once(':').filter(|_| !line.starts_with(' ')) {
+                match key.trim() {
+                    "Name" => {
+                        package = value.trim().to_string();
+                        in_backup = false;
+                        continue;
+                    }
+                    "Backup Files" => {
+                        in_backup = true;
+                        value
+                    }
+                    _ => {
+                        in_backup = false;
+                        continue;
+                    }
+                }
+            } else if in_backup && line.starts_with(' ') {
+                line
+            } else {
+                in_backup = false;
+                continue;
+            };
+
+        let mut path = None;
+        let mut modified = false;
+        for token in entry.split_whitespace() {
+            if token.starts_with('/') {
+                path = Some(token);
+            } else {
+                let status = token.trim_matches(|c| c == '[' || c == ']');
+                modified |= status.eq_ignore_ascii_case("modified");
+            }
+        }
+        if let (Some(path), true) = (path, modified) {
+            files.push((package.clone(), path.to_string()));
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modified_backup_files() {
+        let files =
+            parse_modified_backup_files(include_str!("../../tests/fixtures/pacman_qii.txt"));
+        let expected = [
+            ("pacman", "/etc/pacman.conf"),
+            ("pacman", "/etc/makepkg.conf.d/rust.conf"),
+            ("openssh", "/etc/ssh/sshd_config"),
+            ("tzdata", "/etc/localtime"),
+        ];
+        assert_eq!(
+            files,
+            expected.map(|(package, path)| (package.to_string(), path.to_string()))
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use super::{BackupPackage, SystemBackup};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A package present on both sides with a different value
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PackageDifference {
+    pub name: String,
+    pub left: String,
+    pub right: String,
+}
+
+/// How two package states differ
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StateDiff {
+    /// Name of each side, `hostname` or backup name
+    pub left: String,
+    pub right: String,
+    /// One side only recorded explicit packages, so dependencies were not compared
+    pub explicit_only: bool,
+    pub only_left: Vec<BackupPackage>,
+    pub only_right: Vec<BackupPackage>,
+    pub version_differences: Vec<PackageDifference>,
+    pub reason_differences: Vec<PackageDifference>,
+    pub repo_differences: Vec<PackageDifference>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty()
+            && self.only_right.is_empty()
+            && self.version_differences.is_empty()
+            && self.reason_differences.is_empty()
+            && self.repo_differences.is_empty()
+    }
+
+    /// Plain text report, one section per kind of difference
+    pub fn report(&self) -> String {
+        let mut lines = vec![format!("Package state: {} vs {}", self.left, self.right)];
+        if self.explicit_only {
+            lines.push("Only explicitly installed packages were compared".to_string());
+        }
+        if self.is_empty() {
+            lines.push(String::new());
+            lines.push("No differences".to_string());
+            return lines.join("\n");
+        }
+
+        for (title, packages) in [
+            (format!("Only on {}", self.left), &self.only_left),
+            (format!("Only on {}", self.right), &self.only_right),
+        ] {
+            if packages.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(format!("{} ({}):", title, packages.len()));
+            for package in packages {
+                lines.push(format!(
+                    "  {} {}",
+                    package.name,
+                    package.version.as_deref().unwrap_or("")
+                ));
+            }
+        }
+
+        for (title, differences) in [
+            ("Version differences", &self.version_differences),
+            ("Install reason differences", &self.reason_differences),
+            ("Repository differences", &self.repo_differences),
+        ] {
+            if differences.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push(format!("{} ({}):", title, differences.len()));
+            for difference in differences {
+                lines.push(format!(
+                    "  {}: {} -> {}",
+                    difference.name, difference.left, difference.right
+                ));
+            }
+        }
+
+        lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Compare two package states. When either side comes from a backup without
+/// versions, which only lists explicit packages, dependencies are left out.
+pub fn diff_states(left: &SystemBackup, right: &SystemBackup) -> StateDiff {
+    let explicit_only = !is_complete(left) || !is_complete(right);
+    let left_packages = packages_by_name(left, explicit_only);
+    let right_packages = packages_by_name(right, explicit_only);
+
+    let mut diff = StateDiff {
+        left: state_name(left),
+        right: state_name(right),
+        explicit_only,
+        only_left: Vec::new(),
+        only_right: Vec::new(),
+        version_differences: Vec::new(),
+        reason_differences: Vec::new(),
+        repo_differences: Vec::new(),
+    };
+
+    for (name, package) in &left_packages {
+        let Some(other) = right_packages.get(name) else {
+            diff.only_left.push((*package).clone());
+            continue;
+        };
+
+        if let (Some(left), Some(right)) = (&package.version, &other.version) {
+            if left != right {
+                diff.version_differences.push(difference(name, left, right));
+            }
+        }
+        if !explicit_only && package.reason != other.reason {
+            diff.reason_differences
+                .push(difference(name, &package.reason, &other.reason));
+        }
+        if let (Some(left), Some(right)) = (&package.repo, &other.repo) {
+            if !same_repo(left, right) {
+                diff.repo_differences.push(difference(name, left, right));
+            }
+        }
+    }
+
+    diff.only_right = right_packages
+        .iter()
+        .filter(|(name, _)| !left_packages.contains_key(*name))
+        .map(|(_, package)| (*package).clone())
+        .collect();
+
+    diff
+}
+
+// Helper functions
+
+/// Backups that recorded versions list every package
+fn is_complete(state: &SystemBackup) -> bool {
+    state.packages.iter().any(|p| p.version.is_some())
+}
+
+fn packages_by_name(state: &SystemBackup, explicit_only: bool) -> BTreeMap<&str, &BackupPackage> {
+    state
+        .packages
+        .iter()
+        .filter(|p| !explicit_only || p.reason == "explicit")
+        .map(|p| (p.name.as_str(), p))
+        .collect()
+}
+
+fn state_name(state: &SystemBackup) -> String {
+    match &state.hostname {
+        Some(hostname) if state.name.is_empty() => hostname.clone(),
+        Some(hostname) => format!("{} ({})", state.name, hostname),
+        None if state.name.is_empty() => "this system".to_string(),
+        None => state.name.clone(),
+    }
+}
+
+/// Foreign packages are only marked `aur` when the AUR could be reached during
+/// capture, so `aur` and `local` both just mean "not from a sync repository"
+fn same_repo(left: &str, right: &str) -> bool {
+    let foreign = |repo: &str| repo == "aur" || repo == "local";
+    left == right || (foreign(left) && foreign(right))
+}
+
+fn difference(name: &str, left: &str, right: &str) -> PackageDifference {
+    PackageDifference {
+        name: name.to_string(),
+        left: left.to_string(),
+        right: right.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{parse_backup, BACKUP_SCHEMA_VERSION};
+
+    fn state(name: &str, packages: &[(&str, &str, &str)]) -> SystemBackup {
+        SystemBackup {
+            schema_version: BACKUP_SCHEMA_VERSION,
+            name: name.to_string(),
+            timestamp: String::new(),
+            description: String::new(),
+            hostname: None,
+            packages: packages
+                .iter()
+                .map(|(name, version, repo)| BackupPackage {
+                    name: name.to_string(),
+                    version: Some(version.to_string()),
+                    reason: "explicit".to_string(),
+                    repo: Some(repo.to_string()),
+                })
+                .collect(),
+            repositories: Vec::new(),
+            pacman_conf: None,
+            mirrorlist: None,
+            ignore_packages: Vec::new(),
+            ignore_groups: Vec::new(),
+            config_files: Vec::new(),
+            unreadable_files: Vec::new(),
+            trigger: None,
+        }
+    }
+
+    #[test]
+    fn offline_capture_matches_aur_packages() {
+        // Captured without AUR lookups, the right side only knows `yay` is foreign
+        let left = state(
+            "online",
+            &[("yay", "12.0-1", "aur"), ("vim", "9.1-1", "extra")],
+        );
+        let right = state(
+            "offline",
+            &[("yay", "12.0-1", "local"), ("vim", "9.1-1", "core")],
+        );
+
+        let diff = diff_states(&left, &right);
+        let names: Vec<&str> = diff
+            .repo_differences
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, ["vim"]);
+        assert!(diff.only_left.is_empty() && diff.only_right.is_empty());
+    }
+
+    fn names(packages: &[BackupPackage]) -> Vec<&str> {
+        packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn mark_dependency(state: &mut SystemBackup, name: &str) {
+        for package in &mut state.packages {
+            if package.name == name {
+                package.reason = "dependency".to_string();
+            }
+        }
+    }
+
+    #[test]
+    fn reports_packages_versions_and_reasons() {
+        let mut left = state(
+            "laptop",
+            &[
+                ("firefox", "143.0-1", "extra"),
+                ("glibc", "2.42-1", "core"),
+                ("htop", "3.4.1-1", "extra"),
+                ("python", "3.13.7-1", "core"),
+            ],
+        );
+        let mut right = state(
+            "desktop",
+            &[
+                ("firefox", "143.0.1-1", "extra"),
+                ("glibc", "2.42-1", "core"),
+                ("python", "3.13.7-1", "core"),
+                ("steam", "1.0.0.83-1", "multilib"),
+            ],
+        );
+        mark_dependency(&mut left, "glibc");
+        mark_dependency(&mut right, "glibc");
+        mark_dependency(&mut right, "python");
+
+        let diff = diff_states(&left, &right);
+        assert!(!diff.explicit_only);
+        assert_eq!(names(&diff.only_left), ["htop"]);
+        assert_eq!(names(&diff.only_right), ["steam"]);
+
+        assert_eq!(diff.version_differences.len(), 1);
+        let version = &diff.version_differences[0];
+        assert_eq!(
+            (
+                version.name.as_str(),
+                version.left.as_str(),
+                version.right.as_str()
+            ),
+            ("firefox", "143.0-1", "143.0.1-1")
+        );
+        assert_eq!(diff.reason_differences.len(), 1);
+        let reason = &diff.reason_differences[0];
+        assert_eq!(
+            (
+                reason.name.as_str(),
+                reason.left.as_str(),
+                reason.right.as_str()
+            ),
+            ("python", "explicit", "dependency")
+        );
+        assert!(diff.repo_differences.is_empty());
+
+        let report = diff.report();
+        assert!(report.contains("Only on laptop (1):\n  htop 3.4.1-1"));
+        assert!(report.contains("  firefox: 143.0-1 -> 143.0.1-1"));
+        assert!(!diff_states(&left, &left).report().contains("(1)"));
+    }
+
+    #[test]
+    fn compares_explicit_packages_only_against_version_1_backups() {
+        let old = parse_backup(include_str!("../../tests/fixtures/backup_v1.json")).unwrap();
+        let mut current = state(
+            "",
+            &[
+                ("base", "3-2", "core"),
+                ("firefox", "143.0-1", "extra"),
+                ("glibc", "2.42-1", "core"),
+                ("yay-bin", "12.3.5-1", "local"),
+            ],
+        );
+        mark_dependency(&mut current, "glibc");
+        mark_dependency(&mut current, "firefox");
+
+        let diff = diff_states(&old, &current);
+        assert!(diff.explicit_only);
+        assert_eq!(diff.left, "before-reinstall");
+        assert_eq!(diff.right, "this system");
+        // Dependencies are not compared, so firefox counts as missing here
+        assert_eq!(names(&diff.only_left), ["firefox"]);
+        assert!(diff.only_right.is_empty());
+        // Without versions and reasons on one side only the repository is compared
+        assert!(diff.version_differences.is_empty());
+        assert!(diff.reason_differences.is_empty());
+        assert!(diff.repo_differences.is_empty());
+        assert!(diff
+            .report()
+            .contains("Only explicitly installed packages were compared"));
+    }
+}
@@ -0,0 +1,461 @@
+pub mod capture;
+pub mod diff;
+pub mod restore;
+pub mod schedule;
+
+pub use capture::*;
+pub use diff::*;
+pub use restore::*;
+pub use schedule::*;
+
+use crate::settings::load_settings;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Current layout of backup files; older files are migrated when read
+pub const BACKUP_SCHEMA_VERSION: u32 = 2;
+
+/// Everything needed to rebuild a system's package state
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SystemBackup {
+    pub schema_version: u32,
+    pub name: String,
+    pub timestamp: String,
+    pub description: String,
+    pub hostname: Option<String>,
+    /// Every installed package, explicit and dependency
+    pub packages: Vec<BackupPackage>,
+    pub repositories: Vec<BackupRepository>,
+    /// `/etc/pacman.conf` as it was
+    pub pacman_conf: Option<String>,
+    /// `/etc/pacman.d/mirrorlist` as it was
+    pub mirrorlist: Option<String>,
+    pub ignore_packages: Vec<String>,
+    pub ignore_groups: Vec<String>,
+    /// Package backup files that differ from the packaged version
+    pub config_files: Vec<BackupConfigFile>,
+    /// Modified config files GuiMan could not read
+    pub unreadable_files: Vec<String>,
+    /// What created an automatic backup, `scheduled` or `pre-update`; `None` for manual ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupPackage {
+    pub name: String,
+    /// `None` for packages migrated from backups that did not record versions
+    pub version: Option<String>,
+    /// `explicit` or `dependency`
+    pub reason: String,
+    /// Sync repository, `aur`, `local` for other foreign packages, `None` when unknown
+    pub repo: Option<String>,
+}
+
+/// A sync repository as configured in pacman.conf
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupRepository {
+    pub name: String,
+    pub servers: Vec<String>,
+    pub sig_level: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupConfigFile {
+    pub path: String,
+    /// Package that owns the file
+    pub package: String,
+    /// `utf-8`, or `base64` for binary files
+    pub encoding: String,
+    pub content: String,
+}
+
+/// What backup lists show, without the file contents
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupSummary {
+    pub name: String,
+    pub timestamp: String,
+    pub description: String,
+    pub schema_version: u32,
+    pub explicit_packages: Vec<String>,
+    pub aur_packages: Vec<String>,
+    pub total_packages: usize,
+    pub config_files: usize,
+    pub trigger: Option<String>,
+}
+
+/// Outcome of [`migrate_backups`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupMigration {
+    pub migrated: Vec<String>,
+    /// Files that could not be migrated, with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Backup files written before schema versions existed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PackageBackupV1 {
+    pub name: String,
+    pub timestamp: String,
+    pub description: String,
+    pub explicit_packages: Vec<String>,
+    pub aur_packages: Vec<String>,
+    pub total_packages: usize,
+}
+
+impl SystemBackup {
+    pub fn explicit_packages(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .filter(|p| p.reason == "explicit")
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    pub fn aur_packages(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .filter(|p| p.repo.as_deref() == Some("aur"))
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    pub fn summary(&self) -> BackupSummary {
+        BackupSummary {
+            name: self.name.clone(),
+            timestamp: self.timestamp.clone(),
+            description: self.description.clone(),
+            schema_version: self.schema_version,
+            explicit_packages: self.explicit_packages(),
+            aur_packages: self.aur_packages(),
+            total_packages: self.packages.len(),
+            config_files: self.config_files.len(),
+            trigger: self.trigger.clone(),
+        }
+    }
+}
+
+impl From<PackageBackupV1> for SystemBackup {
+    fn from(old: PackageBackupV1) -> Self {
+        let packages = old
+            .explicit_packages
+            .iter()
+            .map(|name| BackupPackage {
+                name: name.clone(),
+                version: None,
+                reason: "explicit".to_string(),
+                repo: old.aur_packages.contains(name).then(|| "aur".to_string()),
+            })
+            .collect();
+
+        SystemBackup {
+            schema_version: BACKUP_SCHEMA_VERSION,
+            name: old.name,
+            timestamp: old.timestamp,
+            description: old.description,
+            hostname: None,
+            packages,
+            repositories: Vec::new(),
+            pacman_conf: None,
+            mirrorlist: None,
+            ignore_packages: Vec::new(),
+            ignore_groups: Vec::new(),
+            config_files: Vec::new(),
+            unreadable_files: Vec::new(),
+            trigger: None,
+        }
+    }
+}
+
+/// Parse a backup file of any schema version into the current one
+pub fn parse_backup(content: &str) -> Result<SystemBackup, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse backup: {}", e))?;
+
+    match value.get("schema_version").and_then(|v| v.as_u64()) {
+        None | Some(1) => serde_json::from_value::<PackageBackupV1>(value)
+            .map(SystemBackup::from)
+            .map_err(|e| format!("Failed to parse version 1 backup: {}", e)),
+        Some(2) => {
+            serde_json::from_value(value).map_err(|e| format!("Failed to parse backup: {}", e))
+        }
+        Some(version) => Err(format!(
+            "Backup schema version {} is newer than this GuiMan supports",
+            version
+        )),
+    }
+}
+
+/// The configured backup directory, `~/.config/guiman/backups` by default
+pub fn backup_directory() -> Result<PathBuf, String> {
+    let directory = load_settings().backup_directory;
+    if directory.trim().is_empty() {
+        return Err("Failed to determine backup directory: none configured".to_string());
+    }
+    Ok(PathBuf::from(directory))
+}
+
+/// Write `<name>.json`, or `<name>.json.gz` when compression is enabled, plus
+/// `<name>.txt` with the explicit packages for `pacman -S -`
+pub fn save_backup(backup: &SystemBackup) -> Result<PathBuf, String> {
+    validate_backup_name(&backup.name)?;
+    let directory = backup_directory()?;
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let json = serde_json::to_string_pretty(backup)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    let plain = directory.join(format!("{}.json", backup.name));
+    let compressed = directory.join(format!("{}.json.gz", backup.name));
+    let path = if load_settings().backup_compress {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(json.as_bytes())
+            .and_then(|_| encoder.finish())
+            .and_then(|bytes| fs::write(&compressed, bytes))
+            .map_err(|e| format!("Failed to write backup file: {}", e))?;
+        let _ = fs::remove_file(&plain);
+        compressed
+    } else {
+        fs::write(&plain, json).map_err(|e| format!("Failed to write backup file: {}", e))?;
+        let _ = fs::remove_file(&compressed);
+        plain
+    };
+
+    fs::write(
+        directory.join(format!("{}.txt", backup.name)),
+        backup.explicit_packages().join("\n"),
+    )
+    .map_err(|e| format!("Failed to write package list: {}", e))?;
+
+    Ok(path)
+}
+
+pub fn load_backup(name: &str) -> Result<SystemBackup, String> {
+    validate_backup_name(name)?;
+    let directory = backup_directory()?;
+    [format!("{}.json", name), format!("{}.json.gz", name)]
+        .iter()
+        .map(|file| directory.join(file))
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("Backup '{}' not found", name))
+        .and_then(|path| read_backup_file(&path))
+}
+
+/// Read a backup or exported state from any path, e.g. one copied from another
+/// machine. Files ending in `.gz` are decompressed.
+pub fn read_backup_file(path: &Path) -> Result<SystemBackup, String> {
+    let content = if path.extension().and_then(|s| s.to_str()) == Some("gz") {
+        let file =
+            fs::File::open(path).map_err(|e| format!("Failed to read backup file: {}", e))?;
+        let mut content = String::new();
+        GzDecoder::new(file)
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Failed to decompress backup file: {}", e))?;
+        content
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read backup file: {}", e))?
+    };
+    parse_backup(&content)
+}
+
+/// Remove a backup in any of its forms, with its package list
+pub fn delete_backup(name: &str) -> Result<(), String> {
+    validate_backup_name(name)?;
+    let directory = backup_directory()?;
+    for file in [
+        format!("{}.json", name),
+        format!("{}.json.gz", name),
+        format!("{}.txt", name),
+    ] {
+        let path = directory.join(file);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// All readable backups, newest first
+pub fn list_backups() -> Result<Vec<SystemBackup>, String> {
+    let directory = backup_directory()?;
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&directory).map_err(|e| format!("Failed to read backup directory: {}", e))?;
+    let mut backups: Vec<SystemBackup> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            name.ends_with(".json") || name.ends_with(".json.gz")
+        })
+        .filter_map(|path| read_backup_file(&path).ok())
+        .collect();
+
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(backups)
+}
+
+/// Names of the backups in the backup directory, without reading them
+pub fn backup_names() -> Result<Vec<String>, String> {
+    let directory = backup_directory()?;
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&directory).map_err(|e| format!("Failed to read backup directory: {}", e))?;
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| {
+            let file = entry.file_name().to_string_lossy().to_string();
+            file.strip_suffix(".json")
+                .or_else(|| file.strip_suffix(".json.gz"))
+                .map(str::to_string)
+        })
+        .collect())
+}
+
+/// Rewrite older backup files in the current schema, keeping the original
+/// as `<name>.json.v1`. Files that fail are skipped and reported.
+pub fn migrate_backups() -> Result<BackupMigration, String> {
+    let mut migration = BackupMigration {
+        migrated: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let directory = backup_directory()?;
+    if !directory.exists() {
+        return Ok(migration);
+    }
+
+    let entries =
+        fs::read_dir(&directory).map_err(|e| format!("Failed to read backup directory: {}", e))?;
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let Ok(content) = fs::read_to_string(&path) else {
+            migration.skipped.push(format!("{}: unreadable", file));
+            continue;
+        };
+        let version = serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .map(|value| value.get("schema_version").and_then(|v| v.as_u64()));
+        match version {
+            Some(None) | Some(Some(1)) => {}
+            None => {
+                migration.skipped.push(format!("{}: not valid JSON", file));
+                continue;
+            }
+            Some(Some(_)) => continue,
+        }
+
+        match migrate_backup_file(&path, &content) {
+            Ok(name) => migration.migrated.push(name),
+            Err(e) => migration.skipped.push(format!("{}: {}", file, e)),
+        }
+    }
+
+    migration.migrated.sort();
+    migration.skipped.sort();
+    Ok(migration)
+}
+
+/// Backup names become file names
+pub fn validate_backup_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(format!("Invalid backup name: {}", name));
+    }
+    Ok(())
+}
+
+/// Package names as pacman allows them: letters, digits and `@._+-`, not
+/// starting with `-` or `.`. Anything else is refused before reaching pacman.
+pub fn validate_package_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with(['-', '.'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@._+-".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid package name: {}", name))
+    }
+}
+
+// Helper functions
+
+fn migrate_backup_file(path: &Path, content: &str) -> Result<String, String> {
+    let backup = parse_backup(content)?;
+    fs::write(path.with_extension("json.v1"), content)
+        .map_err(|e| format!("Failed to keep original of '{}': {}", backup.name, e))?;
+    let json = serde_json::to_string_pretty(&backup)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write backup '{}': {}", backup.name, e))?;
+    Ok(backup.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_BACKUP: &str = include_str!("../../tests/fixtures/backup_v1.json");
+
+    #[test]
+    fn parses_version_1_backup() {
+        let backup = parse_backup(V1_BACKUP).unwrap();
+        assert_eq!(backup.schema_version, BACKUP_SCHEMA_VERSION);
+        assert_eq!(backup.name, "before-reinstall");
+        assert_eq!(backup.timestamp, "2024-05-01_12-30-00");
+        assert_eq!(backup.explicit_packages(), ["base", "firefox", "yay-bin"]);
+        assert_eq!(backup.aur_packages(), ["yay-bin"]);
+        assert!(backup.packages.iter().all(|p| p.version.is_none()));
+        assert_eq!(backup.packages[0].repo, None);
+        assert_eq!(backup.trigger, None);
+    }
+
+    #[test]
+    fn rejects_newer_schema_versions() {
+        let error = parse_backup(r#"{"schema_version": 3}"#).unwrap_err();
+        assert!(error.contains("newer"), "{}", error);
+    }
+
+    #[test]
+    fn migrates_version_1_file_and_keeps_original() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("before-reinstall.json");
+        fs::write(&path, V1_BACKUP).unwrap();
+
+        assert_eq!(
+            migrate_backup_file(&path, V1_BACKUP).unwrap(),
+            "before-reinstall"
+        );
+
+        let original = fs::read_to_string(directory.path().join("before-reinstall.json.v1"));
+        assert_eq!(original.unwrap(), V1_BACKUP);
+        let migrated = read_backup_file(&path).unwrap();
+        let raw: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["schema_version"], 2);
+        assert_eq!(migrated.explicit_packages(), ["base", "firefox", "yay-bin"]);
+        assert_eq!(migrated.packages[2].repo.as_deref(), Some("aur"));
+    }
+}
@@ -0,0 +1,479 @@
+use super::{pacman_conf_values, validate_package_name, SystemBackup};
+use crate::pacman::graph::{package_graph, Direction, PackageGraph};
+use crate::pacman::rollback::{cache_dirs, cached_packages};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+/// Always kept: `pacman -R` on these, or on anything they need, breaks the system
+const PROTECTED_ROOTS: &[&str] = &["base", "pacman", "linux", "systemd"];
+
+/// Which parts of a restore to perform
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct RestoreOptions {
+    pub install_missing: bool,
+    pub remove_extra: bool,
+    /// Install the exact versions from the backup when the package cache has them
+    pub pin_versions: bool,
+    /// Mark packages explicit or dependency like they were, including missing
+    /// packages as they are installed
+    pub restore_reasons: bool,
+}
+
+/// A package in the backup that is not installed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MissingPackage {
+    pub name: String,
+    pub version: Option<String>,
+    /// Sync repository, `aur` or `local`
+    pub repo: Option<String>,
+    /// Cached file of the backed up version, used when pinning versions
+    pub cache_file: Option<String>,
+    /// `explicit` or `dependency`, as backed up
+    pub reason: String,
+}
+
+/// A package installed in a different version than backed up
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VersionMismatch {
+    pub name: String,
+    pub backup_version: String,
+    pub installed_version: String,
+    /// `None` when the package cache no longer has the backed up version
+    pub cache_file: Option<String>,
+}
+
+/// An installed package that is not in the backup but is kept
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KeptPackage {
+    pub name: String,
+    pub reason: String,
+}
+
+/// An install reason that differs from the backup
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReasonChange {
+    pub name: String,
+    /// `explicit` or `dependency`, as backed up
+    pub reason: String,
+}
+
+/// Everything a restore would change, computed before anything runs
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RestorePlan {
+    pub backup: String,
+    /// The backup lists every package rather than just explicit ones,
+    /// so packages missing from it are really extra
+    pub complete: bool,
+    pub missing: Vec<MissingPackage>,
+    /// Installed packages not in the backup that would be removed
+    pub extra: Vec<String>,
+    /// Extra packages that stay: protected, or needed by packages that stay
+    pub kept: Vec<KeptPackage>,
+    pub version_mismatches: Vec<VersionMismatch>,
+    pub reason_changes: Vec<ReasonChange>,
+}
+
+/// Outcome of one restore step
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreStepResult {
+    pub step: String,
+    pub success: bool,
+    pub output: String,
+}
+
+/// Compare a backup with the installed system
+pub fn plan_restore(backup: &SystemBackup) -> Result<RestorePlan, String> {
+    // Names end up on a root pacman command line
+    for package in &backup.packages {
+        validate_package_name(&package.name)?;
+    }
+
+    let graph = package_graph()?;
+    let complete = backup.packages.iter().any(|p| p.version.is_some());
+    let cache = cached_packages(&cache_dirs());
+    let cache_file = |name: &str, version: &str| {
+        cache
+            .get(&(name.to_string(), version.to_string()))
+            .map(|path| path.to_string_lossy().to_string())
+    };
+
+    let mut plan = RestorePlan {
+        backup: backup.name.clone(),
+        complete,
+        missing: Vec::new(),
+        extra: Vec::new(),
+        kept: Vec::new(),
+        version_mismatches: Vec::new(),
+        reason_changes: Vec::new(),
+    };
+
+    for package in &backup.packages {
+        let installed = graph.get(&package.name).filter(|p| p.installed);
+        let Some(installed) = installed else {
+            plan.missing.push(MissingPackage {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                repo: package.repo.clone(),
+                cache_file: package
+                    .version
+                    .as_deref()
+                    .and_then(|version| cache_file(&package.name, version)),
+                reason: package.reason.clone(),
+            });
+            continue;
+        };
+
+        if let Some(version) = &package.version {
+            if *version != installed.version {
+                plan.version_mismatches.push(VersionMismatch {
+                    name: package.name.clone(),
+                    backup_version: version.clone(),
+                    installed_version: installed.version.clone(),
+                    cache_file: cache_file(&package.name, version),
+                });
+            }
+        }
+
+        let explicit = package.reason == "explicit";
+        if complete && explicit != installed.explicit {
+            plan.reason_changes.push(ReasonChange {
+                name: package.name.clone(),
+                reason: package.reason.clone(),
+            });
+        }
+    }
+
+    let wanted: HashSet<&str> = backup.packages.iter().map(|p| p.name.as_str()).collect();
+    // Older backups only list explicit packages, so only explicit packages can be extra
+    let (extra, kept) = removable_packages(&graph, &wanted, !complete);
+    plan.extra = extra;
+    plan.kept = kept;
+    Ok(plan)
+}
+
+/// Run a restore plan step by step. Every command gets its arguments as a
+/// vector, package names never pass through a shell.
+pub fn execute_restore(
+    plan: &RestorePlan,
+    options: &RestoreOptions,
+    aur_helper: Option<&str>,
+) -> Vec<RestoreStepResult> {
+    let mut results = Vec::new();
+
+    if options.install_missing {
+        // Dependencies first, so explicit packages find them installed with
+        // the right reason instead of pulling them in as explicit
+        let (dependencies, explicit): (Vec<&MissingPackage>, Vec<&MissingPackage>) = plan
+            .missing
+            .iter()
+            .partition(|p| options.restore_reasons && p.reason == "dependency");
+        results.extend(install_missing(&dependencies, true, options, aur_helper));
+        results.extend(install_missing(&explicit, false, options, aur_helper));
+    }
+
+    if options.pin_versions {
+        let files: Vec<&str> = plan
+            .version_mismatches
+            .iter()
+            .filter_map(|m| m.cache_file.as_deref())
+            .collect();
+        if !files.is_empty() {
+            results.push(run_pacman("Pin versions", &["-U", "--noconfirm"], &files));
+        }
+        let unavailable: Vec<String> = plan
+            .version_mismatches
+            .iter()
+            .filter(|m| m.cache_file.is_none())
+            .map(|m| format!("{} {}", m.name, m.backup_version))
+            .collect();
+        if !unavailable.is_empty() {
+            results.push(RestoreStepResult {
+                step: "Pin versions".to_string(),
+                success: false,
+                output: format!("Not in the package cache: {}", unavailable.join(", ")),
+            });
+        }
+    }
+
+    if options.restore_reasons {
+        for (reason, flag) in [("explicit", "--asexplicit"), ("dependency", "--asdeps")] {
+            let names: Vec<&str> = plan
+                .reason_changes
+                .iter()
+                .filter(|c| c.reason == reason)
+                .map(|c| c.name.as_str())
+                .collect();
+            if !names.is_empty() {
+                results.push(run_pacman(
+                    &format!("Mark as {}", reason),
+                    &["-D", flag],
+                    &names,
+                ));
+            }
+        }
+    }
+
+    if options.remove_extra && !plan.extra.is_empty() {
+        let names: Vec<&str> = plan.extra.iter().map(String::as_str).collect();
+        results.push(run_pacman(
+            "Remove extra packages",
+            &["-R", "--noconfirm"],
+            &names,
+        ));
+    }
+
+    if results.is_empty() {
+        results.push(RestoreStepResult {
+            step: "Restore".to_string(),
+            success: true,
+            output: "The system already matches the backup".to_string(),
+        });
+    }
+    results
+}
+
+/// Installed packages not in `wanted`, split into removable ones and ones that
+/// stay because they are protected or needed by packages that stay
+pub fn removable_packages(
+    graph: &PackageGraph,
+    wanted: &HashSet<&str>,
+    explicit_only: bool,
+) -> (Vec<String>, Vec<KeptPackage>) {
+    let mut candidates: Vec<&str> = graph
+        .packages()
+        .iter()
+        .filter(|p| p.installed && (p.explicit || !explicit_only))
+        .filter(|p| !wanted.contains(p.name.as_str()))
+        .map(|p| p.name.as_str())
+        .collect();
+    candidates.sort_unstable();
+
+    let hold = pacman_conf_values("HoldPkg");
+    let mut roots: Vec<&str> = PROTECTED_ROOTS.to_vec();
+    roots.extend(hold.iter().map(String::as_str));
+    roots.retain(|name| graph.get(name).is_some_and(|p| p.installed));
+    let protected: HashMap<&str, &str> = graph
+        .closure(&roots, Direction::Dependencies, None, false)
+        .into_iter()
+        .map(|(level, p)| {
+            let reason = if level == 0 {
+                "Protected package"
+            } else {
+                "Needed by a protected package"
+            };
+            (p.name.as_str(), reason)
+        })
+        .collect();
+
+    let mut kept: Vec<KeptPackage> = Vec::new();
+    let mut removable: HashSet<&str> = HashSet::new();
+    for name in candidates {
+        match protected.get(name) {
+            Some(reason) => kept.push(KeptPackage {
+                name: name.to_string(),
+                reason: reason.to_string(),
+            }),
+            None => {
+                removable.insert(name);
+            }
+        }
+    }
+
+    // Anything a remaining package depends on has to stay, which can in turn keep more
+    loop {
+        let needed: Vec<&str> = removable
+            .iter()
+            .copied()
+            .filter(|name| {
+                graph
+                    .required_by(name)
+                    .iter()
+                    .any(|p| p.installed && !removable.contains(p.name.as_str()))
+            })
+            .collect();
+        if needed.is_empty() {
+            break;
+        }
+        for name in needed {
+            removable.remove(name);
+            kept.push(KeptPackage {
+                name: name.to_string(),
+                reason: "Needed by a package that stays".to_string(),
+            });
+        }
+    }
+
+    let mut extra: Vec<String> = removable.into_iter().map(str::to_string).collect();
+    extra.sort();
+    kept.sort_by(|a, b| a.name.cmp(&b.name));
+    (extra, kept)
+}
+
+/// Run `pacman` through pkexec with the targets as separate arguments after `--`.
+/// Targets are package names, or absolute paths of package files.
+pub fn run_pacman(step: &str, flags: &[&str], targets: &[&str]) -> RestoreStepResult {
+    if let Err(e) = validate_targets(targets, true) {
+        return failed_step(step, e);
+    }
+
+    let result = Command::new("/usr/bin/pkexec")
+        .arg("/usr/bin/pacman")
+        .args(flags)
+        .arg("--")
+        .args(targets)
+        .output();
+    step_result(step, result)
+}
+
+/// Install AUR packages with yay or paru as the current user, marked as
+/// dependencies when `as_deps` is set
+pub fn run_aur_helper(helper: Option<&str>, packages: &[&str], as_deps: bool) -> RestoreStepResult {
+    let step = if as_deps {
+        "Install missing AUR packages as dependencies"
+    } else {
+        "Install missing AUR packages"
+    };
+    let helper = match helper {
+        Some("paru") => "paru",
+        Some("yay") | None => "yay",
+        Some(other) => return failed_step(step, format!("Unsupported AUR helper: {}", other)),
+    };
+    if let Err(e) = validate_targets(packages, false) {
+        return failed_step(step, e);
+    }
+
+    let result = Command::new(format!("/usr/bin/{}", helper))
+        .args(["-S", "--needed", "--noconfirm"])
+        .args(as_deps.then_some("--asdeps"))
+        .arg("--")
+        .args(packages)
+        .output();
+    step_result(step, result)
+}
+
+/// The first AUR helper GuiMan supports that is installed
+pub fn installed_aur_helper() -> Option<String> {
+    let helpers = ["yay", "paru"];
+    for helper in &helpers {
+        if let Ok(output) = Command::new("/usr/bin/which").arg(helper).output() {
+            if output.status.success() && !output.stdout.is_empty() {
+                return Some(helper.to_string());
+            }
+        }
+    }
+    None
+}
+
+// Helper functions
+
+/// Install missing packages from the cache, the repositories or the AUR,
+/// marked as dependencies when `as_deps` is set
+fn install_missing(
+    packages: &[&MissingPackage],
+    as_deps: bool,
+    options: &RestoreOptions,
+    aur_helper: Option<&str>,
+) -> Vec<RestoreStepResult> {
+    let mut results = Vec::new();
+    let suffix = if as_deps { " as dependencies" } else { "" };
+    let flags = |flags: &[&'static str]| -> Vec<&'static str> {
+        let mut flags = flags.to_vec();
+        if as_deps {
+            flags.push("--asdeps");
+        }
+        flags
+    };
+
+    let (pinned, rest): (Vec<&MissingPackage>, Vec<&MissingPackage>) = packages
+        .iter()
+        .partition(|p| options.pin_versions && p.cache_file.is_some());
+    let repo: Vec<&str> = rest
+        .iter()
+        .filter(|p| !matches!(p.repo.as_deref(), Some("aur") | Some("local")))
+        .map(|p| p.name.as_str())
+        .collect();
+    let aur: Vec<&str> = rest
+        .iter()
+        .filter(|p| p.repo.as_deref() == Some("aur"))
+        .map(|p| p.name.as_str())
+        .collect();
+
+    if !pinned.is_empty() {
+        let files: Vec<&str> = pinned
+            .iter()
+            .filter_map(|p| p.cache_file.as_deref())
+            .collect();
+        results.push(run_pacman(
+            &format!("Install backed up versions{}", suffix),
+            &flags(&["-U", "--needed", "--noconfirm"]),
+            &files,
+        ));
+    }
+    if !repo.is_empty() {
+        results.push(run_pacman(
+            &format!("Install missing repository packages{}", suffix),
+            &flags(&["-S", "--needed", "--noconfirm"]),
+            &repo,
+        ));
+    }
+    if !aur.is_empty() {
+        results.push(run_aur_helper(aur_helper, &aur, as_deps));
+    }
+    let local: Vec<&str> = rest
+        .iter()
+        .filter(|p| p.repo.as_deref() == Some("local"))
+        .map(|p| p.name.as_str())
+        .collect();
+    if !local.is_empty() {
+        results.push(RestoreStepResult {
+            step: format!("Install local packages{}", suffix),
+            success: false,
+            output: format!(
+                "Not in any repository or the AUR, install manually: {}",
+                local.join(", ")
+            ),
+        });
+    }
+
+    results
+}
+
+fn validate_targets(targets: &[&str], allow_files: bool) -> Result<(), String> {
+    for target in targets {
+        if allow_files && target.starts_with('/') {
+            continue;
+        }
+        validate_package_name(target)?;
+    }
+    Ok(())
+}
+
+fn failed_step(step: &str, output: String) -> RestoreStepResult {
+    RestoreStepResult {
+        step: step.to_string(),
+        success: false,
+        output,
+    }
+}
+
+fn step_result(step: &str, result: std::io::Result<std::process::Output>) -> RestoreStepResult {
+    match result {
+        Ok(output) => RestoreStepResult {
+            step: step.to_string(),
+            success: output.status.success(),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .trim()
+            .to_string(),
+        },
+        Err(e) => RestoreStepResult {
+            step: step.to_string(),
+            success: false,
+            output: format!("Failed to start: {}", e),
+        },
+    }
+}
@@ -0,0 +1,191 @@
+use super::{
+    backup_names, capture_current_state, delete_backup, list_backups, save_backup, SystemBackup,
+};
+use crate::settings::load_settings;
+use chrono::{Datelike, NaiveDateTime, Utc};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Format of backup timestamps, which also end automatic backup names
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// How often the scheduler checks whether a backup is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Create an automatic backup and apply the retention settings.
+/// `trigger` is `scheduled` or `pre-update`.
+pub async fn create_automatic_backup(trigger: &str) -> Result<SystemBackup, String> {
+    let timestamp = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+    let description = match trigger {
+        "pre-update" => "Automatic backup before system update",
+        _ => "Scheduled backup",
+    };
+
+    let mut backup = capture_current_state(
+        format!("auto_{}_{}", trigger, timestamp),
+        description.to_string(),
+        timestamp,
+        false,
+    )
+    .await?;
+    backup.trigger = Some(trigger.to_string());
+    save_backup(&backup)?;
+
+    // The new backup is saved either way; pruning failures show up on the next run
+    let _ = tokio::task::spawn_blocking(apply_retention).await;
+    Ok(backup)
+}
+
+/// Create scheduled backups every `backup_interval_hours`, for as long as the app runs.
+/// The newest scheduled backup on disk counts, so restarts do not reset the interval.
+pub async fn run_backup_scheduler() {
+    loop {
+        let interval = load_settings().backup_interval_hours;
+        let due = interval > 0
+            && tokio::task::spawn_blocking(move || scheduled_backup_due(interval))
+                .await
+                .unwrap_or(false);
+        if due {
+            // A failed backup is retried on the next check
+            let _ = create_automatic_backup("scheduled").await;
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Keep the newest automatic backup of each of the last `backup_keep_daily` days
+/// and `backup_keep_weekly` weeks that have one, and delete the other automatic
+/// backups. Manual backups are never deleted, and with both limits at 0 nothing is.
+/// Returns the names of the deleted backups.
+pub fn apply_retention() -> Result<Vec<String>, String> {
+    let settings = load_settings();
+    if settings.backup_keep_daily == 0 && settings.backup_keep_weekly == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Newest first, as listed
+    let backups: Vec<(String, NaiveDateTime, bool)> = list_backups()?
+        .into_iter()
+        .filter_map(|backup| {
+            let time = NaiveDateTime::parse_from_str(&backup.timestamp, TIMESTAMP_FORMAT).ok()?;
+            Some((backup.name, time, backup.trigger.is_some()))
+        })
+        .collect();
+
+    let expired = expired_backups(
+        &backups,
+        settings.backup_keep_daily,
+        settings.backup_keep_weekly,
+    );
+    for name in &expired {
+        delete_backup(name)?;
+    }
+    Ok(expired)
+}
+
+// Helper functions
+
+/// Only file names are read: the check runs every few minutes and backups can be
+/// large, so the time of the last scheduled backup comes from its name
+fn scheduled_backup_due(interval_hours: u64) -> bool {
+    let Ok(names) = backup_names() else {
+        return false;
+    };
+    let last = names
+        .iter()
+        .filter_map(|name| name.strip_prefix("auto_scheduled_"))
+        .filter_map(|timestamp| NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok())
+        .max();
+
+    backup_due(last, Utc::now().naive_utc(), interval_hours)
+}
+
+fn backup_due(last: Option<NaiveDateTime>, now: NaiveDateTime, interval_hours: u64) -> bool {
+    match last {
+        Some(last) => (now - last).num_minutes() >= interval_hours as i64 * 60,
+        None => true,
+    }
+}
+
+/// Automatic backups, newest first, that fall outside the daily and weekly
+/// limits. Entries are `(name, time, automatic)`; manual ones never expire and
+/// do not count towards the limits.
+fn expired_backups(
+    backups: &[(String, NaiveDateTime, bool)],
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> Vec<String> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut expired = Vec::new();
+
+    for (name, time, _) in backups.iter().filter(|(_, _, automatic)| *automatic) {
+        let day = time.date();
+        let week = (time.iso_week().year(), time.iso_week().week());
+        let mut keep = false;
+        if !days.contains(&day) && days.len() < keep_daily {
+            days.insert(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < keep_weekly {
+            weeks.insert(week);
+            keep = true;
+        }
+        if !keep {
+            expired.push(name.clone());
+        }
+    }
+
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).unwrap()
+    }
+
+    fn backup(name: &str, timestamp: &str, automatic: bool) -> (String, NaiveDateTime, bool) {
+        (name.to_string(), at(timestamp), automatic)
+    }
+
+    #[test]
+    fn keeps_newest_backup_per_day_and_week() {
+        // Newest first, like list_backups
+        let backups = [
+            backup("wed-evening", "2026-10-14_20-00-00", true),
+            backup("manual-wed", "2026-10-14_12-00-00", false),
+            backup("wed-morning", "2026-10-14_08-00-00", true),
+            backup("tue", "2026-10-13_08-00-00", true),
+            backup("sun", "2026-10-11_08-00-00", true),
+            backup("sat", "2026-10-10_08-00-00", true),
+            backup("last-week", "2026-10-01_08-00-00", true),
+            backup("manual-old", "2026-09-01_08-00-00", false),
+        ];
+
+        // wed-evening fills both a daily and a weekly slot, so the second
+        // weekly slot goes to the previous week
+        assert_eq!(
+            expired_backups(&backups, 2, 2),
+            ["wed-morning", "sat", "last-week"]
+        );
+        assert_eq!(
+            expired_backups(&backups, 1, 0),
+            ["wed-morning", "tue", "sun", "sat", "last-week"]
+        );
+        assert_eq!(
+            expired_backups(&backups, 0, 3),
+            ["wed-morning", "tue", "sat"]
+        );
+    }
+
+    #[test]
+    fn schedules_by_interval() {
+        let now = at("2026-10-14_20-00-00");
+        assert!(backup_due(None, now, 24));
+        assert!(!backup_due(Some(at("2026-10-14_08-00-00")), now, 24));
+        assert!(backup_due(Some(at("2026-10-13_20-00-00")), now, 24));
+    }
+}
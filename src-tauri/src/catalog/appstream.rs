@@ -0,0 +1,507 @@
+use flate2::read::GzDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Locations of the AppStream catalog shipped by `archlinux-appstream-data`
+const CATALOG_DIRS: [&str; 2] = ["/usr/share/swcatalog", "/usr/share/app-info"];
+
+static COMPONENTS: RwLock<Option<Arc<ComponentCache>>> = RwLock::new(None);
+
+/// A screenshot of an application
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Screenshot {
+    pub caption: Option<String>,
+    pub image: String,
+    pub thumbnail: Option<String>,
+    pub default: bool,
+}
+
+/// A release entry with its notes
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Release {
+    pub version: String,
+    /// Release date as `YYYY-MM-DD`
+    pub date: Option<String>,
+    pub description: String,
+}
+
+/// One OARS content rating attribute, e.g. `violence-cartoon: mild`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContentAttribute {
+    pub id: String,
+    pub value: String,
+}
+
+/// A link from the component, e.g. homepage or bug tracker
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ComponentUrl {
+    pub kind: String,
+    pub url: String,
+}
+
+/// An application described in the AppStream catalog
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AppStreamComponent {
+    pub id: String,
+    pub package: String,
+    pub kind: String,
+    /// Repository the catalog belongs to (`extra`, `multilib`, ...)
+    pub repo: String,
+    pub name: String,
+    pub summary: String,
+    /// Long description as plain text, paragraphs separated by blank lines
+    pub description: String,
+    pub developer: Option<String>,
+    pub project_license: Option<String>,
+    pub categories: Vec<String>,
+    pub keywords: Vec<String>,
+    /// Absolute path of the cached icon shipped with the catalog
+    pub icon_path: Option<String>,
+    pub icon_url: Option<String>,
+    pub screenshots: Vec<Screenshot>,
+    /// Newest release first, as listed in the catalog
+    pub releases: Vec<Release>,
+    /// OARS rating type (`oars-1.0`, `oars-1.1`); absent when not rated
+    pub content_rating: Option<String>,
+    /// Rated attributes, excluding those marked `none`
+    pub content_attributes: Vec<ContentAttribute>,
+    /// Desktop file IDs that start the application
+    pub launchables: Vec<String>,
+    pub urls: Vec<ComponentUrl>,
+}
+
+struct ComponentCache {
+    by_package: HashMap<String, AppStreamComponent>,
+    stamp: Option<SystemTime>,
+}
+
+/// Every AppStream catalog file on the system
+pub fn catalog_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in CATALOG_DIRS {
+        for sub in ["xml", "xmls"] {
+            let Ok(entries) = fs::read_dir(Path::new(dir).join(sub)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".xml") || name.ends_with(".xml.gz") {
+                    files.push(entry.path());
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Latest modification time of the catalog files
+pub fn catalog_stamp() -> Option<SystemTime> {
+    catalog_files()
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// Load all components from every catalog file
+pub fn load_components() -> Vec<AppStreamComponent> {
+    catalog_files()
+        .iter()
+        .filter_map(|path| load_catalog_file(path).ok())
+        .flatten()
+        .collect()
+}
+
+/// The AppStream component describing a package, if the catalog has one.
+///
+/// Packages with several components (an app and its addons) resolve to the
+/// application.
+pub fn component_for_package(package: &str) -> Result<Option<AppStreamComponent>, String> {
+    Ok(component_cache()?.by_package.get(package).cloned())
+}
+
+/// AppStream keywords of every package that has any
+pub fn package_keywords() -> Result<HashMap<String, Vec<String>>, String> {
+    Ok(component_cache()?
+        .by_package
+        .iter()
+        .filter(|(_, component)| !component.keywords.is_empty())
+        .map(|(package, component)| (package.clone(), component.keywords.clone()))
+        .collect())
+}
+
+/// Parse one (optionally gzip compressed) AppStream XML catalog
+pub fn load_catalog_file(path: &Path) -> Result<Vec<AppStreamComponent>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let reader: Box<dyn BufRead> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    parse_catalog(reader)
+}
+
+/// Parse AppStream catalog XML
+pub fn parse_catalog<R: BufRead>(input: R) -> Result<Vec<AppStreamComponent>, String> {
+    let mut reader = Reader::from_reader(input);
+    reader.config_mut().trim_text(true);
+    // Self-closing tags like <release .../> arrive as Start and End events
+    reader.config_mut().expand_empty_elements = true;
+
+    let mut components = Vec::new();
+    let mut origin = String::new();
+    let mut current: Option<AppStreamComponent> = None;
+    let mut stack: Vec<Element> = Vec::new();
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("Invalid AppStream XML: {}", e))?;
+
+        match event {
+            Event::Start(start) => {
+                let mut element = Element::from_start(&start);
+                // Children of a translated element are translations too
+                element.localized |= stack.last().is_some_and(|parent| parent.localized);
+                match element.name.as_str() {
+                    "components" => origin = element.attribute("origin").unwrap_or_default(),
+                    "component" => {
+                        current = Some(AppStreamComponent {
+                            kind: element.attribute("type").unwrap_or_default(),
+                            repo: repo_from_origin(&origin),
+                            ..Default::default()
+                        })
+                    }
+                    "screenshot" => {
+                        if let Some(component) = current.as_mut() {
+                            component.screenshots.push(Screenshot {
+                                default: element.attribute("type").as_deref() == Some("default"),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    "release" => {
+                        if let Some(component) = current.as_mut() {
+                            component.releases.push(Release {
+                                version: element.attribute("version").unwrap_or_default(),
+                                date: release_date(&element),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    "content_rating" => {
+                        if let Some(component) = current.as_mut() {
+                            component.content_rating = element.attribute("type");
+                        }
+                    }
+                    "p" | "li" if !element.localized => {
+                        if let Some(description) = current
+                            .as_mut()
+                            .and_then(|component| description_target(component, &stack))
+                        {
+                            start_block(description, &element.name);
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(element);
+            }
+            Event::Text(text) => {
+                let Some(component) = current.as_mut() else {
+                    continue;
+                };
+                let Some(element) = stack.last() else {
+                    continue;
+                };
+                if element.localized {
+                    continue;
+                }
+
+                let value = text
+                    .unescape()
+                    .map_err(|e| format!("Invalid AppStream XML: {}", e))?
+                    .to_string();
+                // Markup inside descriptions is flattened to plain text
+                if let Some(description) = description_target(component, &stack) {
+                    append_text(description, &value);
+                    continue;
+                }
+
+                let parent = stack
+                    .len()
+                    .checked_sub(2)
+                    .map(|i| stack[i].name.as_str())
+                    .unwrap_or("");
+
+                apply_text(component, &origin, parent, element, value);
+            }
+            Event::End(end) => {
+                stack.pop();
+                if end.name().as_ref() == b"component" {
+                    if let Some(component) = current.take() {
+                        if !component.package.is_empty() {
+                            components.push(component);
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(components)
+}
+
+// Helper functions
+
+fn component_cache() -> Result<Arc<ComponentCache>, String> {
+    let stamp = catalog_stamp();
+    if let Some(cache) = COMPONENTS
+        .read()
+        .map_err(|_| "AppStream cache lock poisoned".to_string())?
+        .clone()
+    {
+        if cache.stamp == stamp {
+            return Ok(cache);
+        }
+    }
+
+    let mut by_package: HashMap<String, AppStreamComponent> = HashMap::new();
+    for component in load_components() {
+        let replace = by_package
+            .get(&component.package)
+            .is_none_or(|existing| kind_rank(&component.kind) > kind_rank(&existing.kind));
+        if replace {
+            by_package.insert(component.package.clone(), component);
+        }
+    }
+
+    let cache = Arc::new(ComponentCache { by_package, stamp });
+    *COMPONENTS
+        .write()
+        .map_err(|_| "AppStream cache lock poisoned".to_string())? = Some(cache.clone());
+    Ok(cache)
+}
+
+/// An open XML element and the attributes the parser cares about
+struct Element {
+    name: String,
+    localized: bool,
+    attributes: Vec<(String, String)>,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Self {
+        let attributes: Vec<(String, String)> = start
+            .attributes()
+            .flatten()
+            .map(|attr| {
+                (
+                    String::from_utf8_lossy(attr.key.as_ref()).to_string(),
+                    attr.unescape_value()
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        // Only the untranslated (English) strings are used
+        let localized = attributes
+            .iter()
+            .any(|(key, value)| key == "xml:lang" && value != "en" && value != "C");
+
+        Self {
+            name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+            localized,
+            attributes,
+        }
+    }
+
+    fn attribute(&self, key: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+}
+
+fn apply_text(
+    component: &mut AppStreamComponent,
+    origin: &str,
+    parent: &str,
+    element: &Element,
+    value: String,
+) {
+    match (parent, element.name.as_str()) {
+        ("component", "id") => component.id = value,
+        ("component", "pkgname") => component.package = value,
+        ("component", "name") => component.name = value,
+        ("component", "summary") => component.summary = value,
+        ("component", "developer_name") | ("developer", "name") => {
+            component.developer = Some(value)
+        }
+        ("component", "project_license") => component.project_license = Some(value),
+        ("component", "url") => component.urls.push(ComponentUrl {
+            kind: element.attribute("type").unwrap_or_default(),
+            url: value,
+        }),
+        ("component", "launchable")
+            if element.attribute("type").as_deref() == Some("desktop-id") =>
+        {
+            component.launchables.push(value)
+        }
+        ("content_rating", "content_attribute") if value != "none" => {
+            component.content_attributes.push(ContentAttribute {
+                id: element.attribute("id").unwrap_or_default(),
+                value,
+            })
+        }
+        ("categories", "category") => component.categories.push(value),
+        ("keywords", "keyword") => component.keywords.push(value),
+        ("component", "icon") => match element.attribute("type").as_deref() {
+            Some("cached") => {
+                // Prefer the 64px icon, but take any size over none
+                let width = element
+                    .attribute("width")
+                    .unwrap_or_else(|| "64".to_string());
+                if component.icon_path.is_none() || width == "64" {
+                    component.icon_path = cached_icon_path(origin, &width, &value);
+                }
+            }
+            Some("remote") => component.icon_url = Some(value),
+            _ => {}
+        },
+        ("screenshot", "caption") => {
+            if let Some(screenshot) = component.screenshots.last_mut() {
+                screenshot.caption = Some(value);
+            }
+        }
+        ("screenshot", "image") => {
+            if let Some(screenshot) = component.screenshots.last_mut() {
+                match element.attribute("type").as_deref() {
+                    Some("thumbnail") => {
+                        if screenshot.thumbnail.is_none() {
+                            screenshot.thumbnail = Some(value)
+                        }
+                    }
+                    _ => screenshot.image = value,
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The description a `<description>` in scope belongs to: the component's or,
+/// inside `<release>`, the release notes
+fn description_target<'a>(
+    component: &'a mut AppStreamComponent,
+    stack: &[Element],
+) -> Option<&'a mut String> {
+    let position = stack.iter().rposition(|e| e.name == "description")?;
+    if stack[..position].iter().any(|e| e.name == "release") {
+        component.releases.last_mut().map(|r| &mut r.description)
+    } else {
+        Some(&mut component.description)
+    }
+}
+
+/// Begin a paragraph or list item
+fn start_block(description: &mut String, element: &str) {
+    if !description.is_empty() {
+        description.push_str(if element == "li" { "\n" } else { "\n\n" });
+    }
+    if element == "li" {
+        description.push_str("• ");
+    }
+}
+
+fn append_text(description: &mut String, text: &str) {
+    if !description.is_empty() && !description.ends_with(['\n', ' ']) {
+        description.push(' ');
+    }
+    description.push_str(text);
+}
+
+/// Release date from the `date` or `timestamp` attribute
+fn release_date(element: &Element) -> Option<String> {
+    if let Some(date) = element.attribute("date") {
+        return Some(date.chars().take(10).collect());
+    }
+    let timestamp = element.attribute("timestamp")?.parse::<i64>().ok()?;
+    chrono::DateTime::from_timestamp(timestamp, 0).map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+/// Applications win over addons, fonts and the like when a package has several
+fn kind_rank(kind: &str) -> u8 {
+    match kind {
+        "desktop-application" | "desktop" => 2,
+        "console-application" => 1,
+        _ => 0,
+    }
+}
+
+/// `archlinux-arch-extra` -> `extra`
+fn repo_from_origin(origin: &str) -> String {
+    origin
+        .strip_prefix("archlinux-arch-")
+        .unwrap_or(origin)
+        .to_string()
+}
+
+fn cached_icon_path(origin: &str, width: &str, icon: &str) -> Option<String> {
+    CATALOG_DIRS.iter().find_map(|dir| {
+        let path = Path::new(dir)
+            .join("icons")
+            .join(origin)
+            .join(format!("{}x{}", width, width))
+            .join(icon);
+        path.exists().then(|| path.to_string_lossy().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_self_closing_elements() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<components version="0.16" origin="archlinux-arch-extra">
+  <component type="desktop-application">
+    <id>org.example.App</id>
+    <pkgname>example</pkgname>
+    <name>Example</name>
+    <releases>
+      <release version="2.0" timestamp="1704067200"/>
+      <release version="1.0" timestamp="1672531200"/>
+    </releases>
+    <content_rating type="oars-1.1"/>
+  </component>
+</components>"#;
+
+        let components = parse_catalog(xml.as_bytes()).unwrap();
+        assert_eq!(components.len(), 1);
+
+        let component = &components[0];
+        let versions: Vec<&str> = component
+            .releases
+            .iter()
+            .map(|r| r.version.as_str())
+            .collect();
+        assert_eq!(versions, ["2.0", "1.0"]);
+        assert_eq!(component.content_rating.as_deref(), Some("oars-1.1"));
+    }
+}
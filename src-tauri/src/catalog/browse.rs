@@ -0,0 +1,330 @@
+use crate::catalog::appstream::{catalog_stamp, load_components, Screenshot};
+use crate::catalog::desktop::{installed_desktop_entries, resolve_icon};
+use crate::pacman::package_index;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Curated categories and featured apps shipped with GuiMan
+const CURATED: &str = include_str!("../../catalog/curated.json");
+
+/// Freedesktop main categories and their display names
+const CATEGORIES: [(&str, &str); 11] = [
+    ("AudioVideo", "Audio & Video"),
+    ("Development", "Development"),
+    ("Education", "Education"),
+    ("Game", "Games"),
+    ("Graphics", "Graphics"),
+    ("Network", "Internet"),
+    ("Office", "Office"),
+    ("Science", "Science"),
+    ("Settings", "Settings"),
+    ("System", "System"),
+    ("Utility", "Utilities"),
+];
+
+static CATALOG: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+
+/// A browsable category
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatalogCategory {
+    pub id: String,
+    pub name: String,
+    pub count: usize,
+}
+
+/// An application in the catalog
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CatalogEntry {
+    pub package: String,
+    pub name: String,
+    pub summary: String,
+    pub repo: String,
+    pub version: String,
+    pub installed: bool,
+    pub featured: bool,
+    pub categories: Vec<String>,
+    /// `data:` URI of a local icon, or a remote icon URL
+    pub icon: Option<String>,
+    pub screenshots: Vec<Screenshot>,
+    pub appstream_id: Option<String>,
+}
+
+/// Curated overrides, also the format of `~/.config/guiman/catalog.json`
+#[derive(Debug, Deserialize, Default)]
+struct CuratedCatalog {
+    #[serde(default)]
+    featured: Vec<String>,
+    #[serde(default)]
+    packages: HashMap<String, CuratedPackage>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct CuratedPackage {
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    summary: Option<String>,
+}
+
+/// Catalog data that only changes with the AppStream files and installed apps
+#[derive(Debug, Clone, Default)]
+struct CatalogItem {
+    package: String,
+    name: String,
+    summary: String,
+    categories: BTreeSet<String>,
+    icon_path: Option<PathBuf>,
+    icon_url: Option<String>,
+    screenshots: Vec<Screenshot>,
+    appstream_id: Option<String>,
+    featured: bool,
+}
+
+struct Catalog {
+    items: Vec<CatalogItem>,
+    /// Positions in `items` of the featured apps, in curated order
+    featured: Vec<usize>,
+    stamp: (Option<SystemTime>, Option<SystemTime>),
+}
+
+/// List categories with the number of apps in each
+pub fn get_catalog_categories() -> Result<Vec<CatalogCategory>, String> {
+    let catalog = catalog()?;
+
+    Ok(CATEGORIES
+        .iter()
+        .map(|(id, name)| CatalogCategory {
+            id: id.to_string(),
+            name: name.to_string(),
+            count: catalog
+                .items
+                .iter()
+                .filter(|item| item.categories.contains(*id))
+                .count(),
+        })
+        .collect())
+}
+
+/// Apps in a category, featured ones first
+pub fn browse_category(
+    category: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<CatalogEntry>, String> {
+    let catalog = catalog()?;
+    let mut items: Vec<&CatalogItem> = catalog
+        .items
+        .iter()
+        .filter(|item| item.categories.contains(category))
+        .collect();
+
+    items.sort_by(|a, b| {
+        b.featured
+            .cmp(&a.featured)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    to_entries(items.into_iter().skip(offset).take(limit))
+}
+
+/// Hand-picked apps for the front page
+pub fn get_featured_apps() -> Result<Vec<CatalogEntry>, String> {
+    let catalog = catalog()?;
+    to_entries(catalog.featured.iter().map(|&i| &catalog.items[i]))
+}
+
+// Helper functions
+
+fn catalog() -> Result<Arc<Catalog>, String> {
+    let stamp = (catalog_stamp(), applications_stamp());
+    if let Some(catalog) = CATALOG
+        .read()
+        .map_err(|_| "Catalog lock poisoned".to_string())?
+        .clone()
+    {
+        if catalog.stamp == stamp {
+            return Ok(catalog);
+        }
+    }
+
+    let catalog = Arc::new(build_catalog(stamp)?);
+    *CATALOG
+        .write()
+        .map_err(|_| "Catalog lock poisoned".to_string())? = Some(catalog.clone());
+    Ok(catalog)
+}
+
+fn build_catalog(stamp: (Option<SystemTime>, Option<SystemTime>)) -> Result<Catalog, String> {
+    let index = package_index()?;
+    let curated = load_curated();
+    let featured: HashSet<&str> = curated.featured.iter().map(|s| s.as_str()).collect();
+    let mut items: HashMap<String, CatalogItem> = HashMap::new();
+
+    // AppStream metadata covers installed and available packages alike
+    for component in load_components() {
+        let item = items
+            .entry(component.package.clone())
+            .or_insert_with(|| CatalogItem {
+                package: component.package.clone(),
+                name: component.name.clone(),
+                summary: component.summary.clone(),
+                appstream_id: Some(component.id.clone()),
+                ..Default::default()
+            });
+        item.categories
+            .extend(main_categories(&component.categories));
+        if item.icon_path.is_none() {
+            item.icon_path = component.icon_path.map(PathBuf::from);
+        }
+        if item.icon_url.is_none() {
+            item.icon_url = component.icon_url;
+        }
+        item.screenshots.extend(component.screenshots);
+    }
+
+    // Desktop files fill the gaps for installed apps without AppStream data
+    for entry in installed_desktop_entries() {
+        let item = items
+            .entry(entry.package.clone())
+            .or_insert_with(|| CatalogItem {
+                package: entry.package.clone(),
+                name: entry.name.clone(),
+                summary: entry.comment.clone(),
+                ..Default::default()
+            });
+        item.categories.extend(main_categories(&entry.categories));
+        if item.icon_path.is_none() {
+            item.icon_path = entry.icon.as_deref().and_then(resolve_icon);
+        }
+    }
+
+    for (package, overrides) in &curated.packages {
+        let item = items.entry(package.clone()).or_insert_with(|| CatalogItem {
+            package: package.clone(),
+            name: package.clone(),
+            ..Default::default()
+        });
+        if !overrides.categories.is_empty() {
+            item.categories = main_categories(&overrides.categories).collect();
+        }
+        if let Some(summary) = &overrides.summary {
+            item.summary = summary.clone();
+        }
+    }
+
+    let items: Vec<CatalogItem> = items
+        .into_values()
+        .filter(|item| index.get(&item.package).is_some())
+        .map(|mut item| {
+            item.featured = featured.contains(item.package.as_str());
+            if item.summary.is_empty() {
+                if let Some(record) = index.get(&item.package) {
+                    item.summary = record.description.clone();
+                }
+            }
+            item
+        })
+        .collect();
+
+    let featured = curated
+        .featured
+        .iter()
+        .filter_map(|package| items.iter().position(|item| &item.package == package))
+        .collect();
+
+    Ok(Catalog {
+        items,
+        featured,
+        stamp,
+    })
+}
+
+fn to_entries<'a>(
+    items: impl Iterator<Item = &'a CatalogItem>,
+) -> Result<Vec<CatalogEntry>, String> {
+    let index = package_index()?;
+
+    Ok(items
+        .filter_map(|item| {
+            let record = index.get(&item.package)?;
+            Some(CatalogEntry {
+                package: item.package.clone(),
+                name: item.name.clone(),
+                summary: item.summary.clone(),
+                repo: record.repo.clone(),
+                version: record.version.clone(),
+                installed: record.installed,
+                featured: item.featured,
+                categories: item.categories.iter().cloned().collect(),
+                icon: item
+                    .icon_path
+                    .as_deref()
+                    .and_then(icon_data_uri)
+                    .or_else(|| item.icon_url.clone()),
+                screenshots: item.screenshots.clone(),
+                appstream_id: item.appstream_id.clone(),
+            })
+        })
+        .collect())
+}
+
+/// Keep main categories only, folding `Audio` and `Video` into `AudioVideo`
+fn main_categories(categories: &[String]) -> impl Iterator<Item = String> + '_ {
+    categories.iter().filter_map(|category| {
+        let category = match category.as_str() {
+            "Audio" | "Video" => "AudioVideo",
+            other => other,
+        };
+        CATEGORIES
+            .iter()
+            .any(|(id, _)| *id == category)
+            .then(|| category.to_string())
+    })
+}
+
+/// Built-in curated data with the user's overrides applied on top
+fn load_curated() -> CuratedCatalog {
+    let mut curated: CuratedCatalog = serde_json::from_str(CURATED).unwrap_or_default();
+
+    let user: Option<CuratedCatalog> = dirs::config_dir()
+        .map(|dir| dir.join("guiman").join("catalog.json"))
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    if let Some(user) = user {
+        for package in user.featured {
+            if !curated.featured.contains(&package) {
+                curated.featured.push(package);
+            }
+        }
+        curated.packages.extend(user.packages);
+    }
+
+    curated
+}
+
+fn icon_data_uri(path: &Path) -> Option<String> {
+    let mime = match path.extension()?.to_str()? {
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "xpm" => "image/x-xpixmap",
+        _ => return None,
+    };
+    let data = fs::read(path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(data)
+    ))
+}
+
+fn applications_stamp() -> Option<SystemTime> {
+    fs::metadata("/usr/share/applications")
+        .and_then(|meta| meta.modified())
+        .ok()
+}
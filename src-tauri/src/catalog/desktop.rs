@@ -0,0 +1,200 @@
+use crate::catalog::appstream::component_for_package;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const APPLICATIONS_DIR: &str = "/usr/share/applications";
+
+/// Icon theme locations searched for desktop file icons, best match first
+const ICON_DIRS: [&str; 5] = [
+    "/usr/share/icons/hicolor/64x64/apps",
+    "/usr/share/icons/hicolor/128x128/apps",
+    "/usr/share/icons/hicolor/48x48/apps",
+    "/usr/share/icons/hicolor/scalable/apps",
+    "/usr/share/pixmaps",
+];
+
+/// The `[Desktop Entry]` of an installed application
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntry {
+    pub package: String,
+    pub name: String,
+    pub comment: String,
+    pub categories: Vec<String>,
+    pub icon: Option<String>,
+}
+
+/// Desktop entries of installed applications, with the package that owns each
+pub fn installed_desktop_entries() -> Vec<DesktopEntry> {
+    let Ok(dir) = fs::read_dir(APPLICATIONS_DIR) else {
+        return Vec::new();
+    };
+
+    let paths: Vec<PathBuf> = dir
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .collect();
+
+    let owners = file_owners(&paths);
+
+    paths
+        .iter()
+        .filter_map(|path| {
+            let mut entry = parse_desktop_file(path)?;
+            entry.package = owners.get(path.to_string_lossy().as_ref())?.clone();
+            Some(entry)
+        })
+        .collect()
+}
+
+/// Parse a desktop file, skipping entries hidden from menus
+pub fn parse_desktop_file(path: &Path) -> Option<DesktopEntry> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut entry = DesktopEntry::default();
+
+    let mut in_main_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_main_section = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_section {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "Name" => entry.name = value.trim().to_string(),
+            "Comment" => entry.comment = value.trim().to_string(),
+            "Categories" => {
+                entry.categories = value
+                    .split(';')
+                    .filter(|c| !c.is_empty())
+                    .map(|c| c.trim().to_string())
+                    .collect()
+            }
+            "Icon" => entry.icon = Some(value.trim().to_string()),
+            "NoDisplay" | "Hidden" if value.trim() == "true" => return None,
+            _ => {}
+        }
+    }
+
+    Some(entry)
+}
+
+/// Resolve an icon name from a desktop file to a file on disk
+pub fn resolve_icon(icon: &str) -> Option<PathBuf> {
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        return path.exists().then(|| path.to_path_buf());
+    }
+
+    ICON_DIRS.iter().find_map(|dir| {
+        ["png", "svg", "xpm"]
+            .iter()
+            .map(|ext| Path::new(dir).join(format!("{}.{}", icon, ext)))
+            .find(|candidate| candidate.exists())
+    })
+}
+
+/// Start an installed application through its desktop file.
+///
+/// The AppStream launchable is preferred; otherwise the first visible desktop
+/// file owned by the package is used.
+pub fn launch_app(package: &str) -> Result<String, String> {
+    let installed = Command::new("/usr/bin/pacman")
+        .args(["-Qq", package])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if !installed {
+        return Err(format!("{} is not installed", package));
+    }
+
+    let launchables = component_for_package(package)?
+        .map(|component| component.launchables)
+        .unwrap_or_default();
+    let desktop_file = launchables
+        .iter()
+        .map(|id| Path::new(APPLICATIONS_DIR).join(id))
+        .find(|path| path.exists())
+        .or_else(|| owned_desktop_files(package).into_iter().next())
+        .ok_or_else(|| format!("{} has no application to launch", package))?;
+
+    let desktop_id = desktop_file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut command = if Path::new("/usr/bin/gtk-launch").exists() {
+        let mut command = Command::new("/usr/bin/gtk-launch");
+        command.arg(&desktop_id);
+        command
+    } else {
+        let mut command = Command::new("/usr/bin/gio");
+        command.arg("launch").arg(&desktop_file);
+        command
+    };
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to launch {}: {}", desktop_id, e))?;
+    // Reap the launcher once it hands the app off
+    std::thread::spawn(move || child.wait());
+
+    Ok(format!("Launched {}", desktop_id))
+}
+
+// Helper functions
+
+/// Visible desktop files installed by a package
+fn owned_desktop_files(package: &str) -> Vec<PathBuf> {
+    let Ok(output) = Command::new("/usr/bin/pacman")
+        .args(["-Qlq", package])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.parent() == Some(Path::new(APPLICATIONS_DIR))
+                && path.extension().is_some_and(|ext| ext == "desktop")
+                && parse_desktop_file(path).is_some()
+        })
+        .collect()
+}
+
+/// Map each path to its owning package with a single `pacman -Qo` call
+fn file_owners(paths: &[PathBuf]) -> HashMap<String, String> {
+    if paths.is_empty() {
+        return HashMap::new();
+    }
+
+    let output = Command::new("/usr/bin/pacman")
+        .arg("-Qo")
+        .args(paths)
+        .env("LC_ALL", "C")
+        .output();
+
+    let Ok(output) = output else {
+        return HashMap::new();
+    };
+
+    // Output format: "/path/to/file is owned by package-name 1.2.3"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (path, owner) = line.split_once(" is owned by ")?;
+            let package = owner.split_whitespace().next()?;
+            Some((path.to_string(), package.to_string()))
+        })
+        .collect()
+}
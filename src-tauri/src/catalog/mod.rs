@@ -0,0 +1,5 @@
+pub mod appstream;
+pub mod browse;
+pub mod desktop;
+
+pub use browse::*;
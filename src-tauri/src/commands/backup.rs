@@ -1,17 +1,24 @@
+use crate::backup::{
+    self, BackupMigration, BackupSummary, RestoreOptions, RestorePlan, RestoreStepResult,
+    StateDiff, SystemBackup,
+};
+use crate::manifest;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PackageBackup {
-    pub name: String,
-    pub timestamp: String,
-    pub description: String,
-    pub explicit_packages: Vec<String>,
-    pub aur_packages: Vec<String>,
-    pub total_packages: usize,
+/// One side of a package state comparison
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum StateSource {
+    /// The running system
+    System,
+    /// A backup in the backup directory, by name
+    Backup(String),
+    /// A backup or exported state file, by path
+    File(String),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,255 +30,159 @@ pub struct PackageHook {
     pub enabled: bool,
 }
 
-/// Create a backup of currently installed packages
+/// Create a backup of the package state: versions, install reasons, repositories,
+/// pacman.conf, the mirrorlist and optionally modified config files
 #[tauri::command]
-pub async fn create_package_backup(name: String, description: String) -> Result<String, String> {
+pub async fn create_package_backup(
+    name: String,
+    description: String,
+    include_config_files: Option<bool>,
+) -> Result<String, String> {
     let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S").to_string();
     let backup_name = if name.is_empty() {
         format!("backup_{}", timestamp)
     } else {
         name
     };
+    backup::validate_backup_name(&backup_name)?;
 
-    // Get explicit packages (user-installed)
-    let explicit_output = Command::new("/usr/bin/pacman")
-        .args(["-Qe"])
-        .output()
-        .map_err(|e| format!("Failed to get explicit packages: {}", e))?;
-
-    if !explicit_output.status.success() {
-        return Err("Failed to query explicit packages".to_string());
-    }
-
-    let explicit_packages: Vec<String> = String::from_utf8_lossy(&explicit_output.stdout)
-        .lines()
-        .map(|line| line.split_whitespace().next().unwrap_or("").to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    // Get AUR packages
-    let mut aur_packages = Vec::new();
-    for package in &explicit_packages {
-        let output = Command::new("/usr/bin/pacman")
-            .args(["-Qi", package])
-            .output();
-
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    if line.starts_with("Repository") {
-                        let repo = line.split(':').nth(1).unwrap_or("").trim();
-                        if repo == "local" {
-                            // Check if it's an AUR package by trying yay/paru
-                            if is_aur_package(package).await {
-                                aur_packages.push(package.clone());
-                            }
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    let backup = PackageBackup {
-        name: backup_name.clone(),
-        timestamp: timestamp.clone(),
+    let include_config_files = include_config_files.unwrap_or(false);
+    let state = backup::capture_current_state(
+        backup_name.clone(),
         description,
-        explicit_packages: explicit_packages.clone(),
-        aur_packages,
-        total_packages: explicit_packages.len(),
-    };
-
-    // Save backup to file
-    let backup_dir = get_backup_directory()?;
-    fs::create_dir_all(&backup_dir)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
-
-    let backup_file = format!("{}/{}.json", backup_dir, backup_name);
-    let backup_json = serde_json::to_string_pretty(&backup)
-        .map_err(|e| format!("Failed to serialize backup: {}", e))?;
-
-    fs::write(&backup_file, backup_json)
-        .map_err(|e| format!("Failed to write backup file: {}", e))?;
-
-    // Also create traditional pacman list files
-    let pacman_list = format!("{}/{}.txt", backup_dir, backup_name);
-    let package_list = explicit_packages.join("\n");
-    fs::write(&pacman_list, package_list)
-        .map_err(|e| format!("Failed to write package list: {}", e))?;
-
-    Ok(format!(
-        "Backup '{}' created successfully with {} packages",
+        timestamp,
+        include_config_files,
+    )
+    .await?;
+    backup::save_backup(&state)?;
+
+    let mut message = format!(
+        "Backup '{}' created successfully with {} packages ({} explicit)",
         backup_name,
-        explicit_packages.len()
-    ))
+        state.packages.len(),
+        state.explicit_packages().len()
+    );
+    if include_config_files {
+        message.push_str(&format!(", {} config files", state.config_files.len()));
+        if !state.unreadable_files.is_empty() {
+            message.push_str(&format!(
+                " ({} unreadable without root)",
+                state.unreadable_files.len()
+            ));
+        }
+    }
+    Ok(message)
 }
 
 /// List all available backups
 #[tauri::command]
-pub async fn list_backups() -> Result<Vec<PackageBackup>, String> {
-    let backup_dir = get_backup_directory()?;
-
-    if !Path::new(&backup_dir).exists() {
-        return Ok(Vec::new());
-    }
-
-    let mut backups = Vec::new();
-    let entries =
-        fs::read_dir(&backup_dir).map_err(|e| format!("Failed to read backup directory: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
+pub async fn list_backups() -> Result<Vec<BackupSummary>, String> {
+    Ok(backup::list_backups()?
+        .iter()
+        .map(SystemBackup::summary)
+        .collect())
+}
 
-        if path.extension().and_then(|s| s.to_str()) == Some("json") {
-            if let Ok(content) = fs::read_to_string(&path) {
-                if let Ok(backup) = serde_json::from_str::<PackageBackup>(&content) {
-                    backups.push(backup);
-                }
-            }
-        }
-    }
+/// Full contents of a backup, migrated to the current schema
+#[tauri::command]
+pub async fn get_package_backup(backup_name: String) -> Result<SystemBackup, String> {
+    backup::load_backup(&backup_name)
+}
 
-    // Sort by timestamp (newest first)
-    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+/// Rewrite backups made by older versions in the current schema
+#[tauri::command]
+pub async fn migrate_package_backups() -> Result<BackupMigration, String> {
+    backup::migrate_backups()
+}
 
-    Ok(backups)
+/// What restoring a backup would change, without changing anything
+#[tauri::command]
+pub async fn preview_backup_restore(backup_name: String) -> Result<RestorePlan, String> {
+    let backup = backup::load_backup(&backup_name)?;
+    tokio::task::spawn_blocking(move || backup::plan_restore(&backup))
+        .await
+        .map_err(|e| format!("Failed to compare backup: {}", e))?
 }
 
-/// Restore packages from a backup
+/// Restore packages from a backup following the previewed `plan`. Extra packages
+/// are only removed when they are neither protected nor needed by packages that stay.
+/// Refuses to run when the system or the backup changed since the preview.
 #[tauri::command]
 pub async fn restore_package_backup(
-    backup_name: String,
+    plan: RestorePlan,
     install_missing: bool,
     remove_extra: bool,
-) -> Result<String, String> {
-    let backup_dir = get_backup_directory()?;
-    let backup_file = format!("{}/{}.json", backup_dir, backup_name);
-
-    if !Path::new(&backup_file).exists() {
-        return Err(format!("Backup '{}' not found", backup_name));
-    }
-
-    let backup_content = fs::read_to_string(&backup_file)
-        .map_err(|e| format!("Failed to read backup file: {}", e))?;
-
-    let backup: PackageBackup = serde_json::from_str(&backup_content)
-        .map_err(|e| format!("Failed to parse backup file: {}", e))?;
-
-    let mut results = Vec::new();
-
-    if install_missing {
-        // Get currently installed explicit packages
-        let current_output = Command::new("/usr/bin/pacman")
-            .args(["-Qe"])
-            .output()
-            .map_err(|e| format!("Failed to get current packages: {}", e))?;
-
-        let current_packages: Vec<String> = String::from_utf8_lossy(&current_output.stdout)
-            .lines()
-            .map(|line| line.split_whitespace().next().unwrap_or("").to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
-
-        // Find missing packages
-        let missing_official: Vec<String> = backup
-            .explicit_packages
-            .iter()
-            .filter(|pkg| !backup.aur_packages.contains(pkg) && !current_packages.contains(pkg))
-            .cloned()
-            .collect();
-
-        let missing_aur: Vec<String> = backup
-            .aur_packages
-            .iter()
-            .filter(|pkg| !current_packages.contains(pkg))
-            .cloned()
-            .collect();
-
-        // Install missing official packages
-        if !missing_official.is_empty() {
-            let install_cmd = format!(
-                "pkexec pacman -S --needed --noconfirm {}",
-                missing_official.join(" ")
-            );
-            let output = Command::new("/bin/sh")
-                .args(["-c", &install_cmd])
-                .output()
-                .map_err(|e| format!("Failed to install official packages: {}", e))?;
-
-            if output.status.success() {
-                results.push(format!(
-                    "Installed {} official packages",
-                    missing_official.len()
-                ));
-            } else {
-                results.push(format!(
-                    "Failed to install some official packages: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
-        }
+    pin_versions: Option<bool>,
+    restore_reasons: Option<bool>,
+) -> Result<Vec<RestoreStepResult>, String> {
+    let backup = backup::load_backup(&plan.backup)?;
+    let options = RestoreOptions {
+        install_missing,
+        remove_extra,
+        pin_versions: pin_versions.unwrap_or(false),
+        restore_reasons: restore_reasons.unwrap_or(false),
+    };
 
-        // Install missing AUR packages
-        if !missing_aur.is_empty() {
-            let aur_helper = get_aur_helper().unwrap_or("yay".to_string());
-            let install_cmd = format!(
-                "{} -S --needed --noconfirm {}",
-                aur_helper,
-                missing_aur.join(" ")
+    tokio::task::spawn_blocking(move || {
+        if backup::plan_restore(&backup)? != plan {
+            return Err(
+                "The system or the backup changed since the preview. Review the new plan and restore again."
+                    .to_string(),
             );
-            let output = Command::new("/bin/sh")
-                .args(["-c", &install_cmd])
-                .output()
-                .map_err(|e| format!("Failed to install AUR packages: {}", e))?;
-
-            if output.status.success() {
-                results.push(format!("Installed {} AUR packages", missing_aur.len()));
-            } else {
-                results.push(format!(
-                    "Failed to install some AUR packages: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
         }
+        let aur_helper = backup::installed_aur_helper();
+        Ok(backup::execute_restore(
+            &plan,
+            &options,
+            aur_helper.as_deref(),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Failed to restore backup: {}", e))?
+}
 
-        if missing_official.is_empty() && missing_aur.is_empty() {
-            results.push("All packages from backup are already installed".to_string());
-        }
-    }
+/// Compare two package states, e.g. this system with a backup from another machine
+#[tauri::command]
+pub async fn diff_package_states(
+    left: StateSource,
+    right: StateSource,
+) -> Result<StateDiff, String> {
+    let left = load_state(left).await?;
+    let right = load_state(right).await?;
+    Ok(backup::diff_states(&left, &right))
+}
 
-    if remove_extra {
-        // This is more complex and potentially dangerous, so we'll just provide a warning
-        results.push("Note: Automatic removal of extra packages is not implemented for safety. Please review manually.".to_string());
+/// Difference between two package states as `text` or `json`
+#[tauri::command]
+pub async fn export_state_diff(
+    left: StateSource,
+    right: StateSource,
+    format: String,
+) -> Result<String, String> {
+    let diff = diff_package_states(left, right).await?;
+    match format.as_str() {
+        "text" => Ok(diff.report()),
+        "json" => serde_json::to_string_pretty(&diff)
+            .map_err(|e| format!("Failed to serialize to JSON: {}", e)),
+        _ => Err(format!("Unsupported format: {}", format)),
     }
-
-    Ok(results.join("\n"))
 }
 
 /// Delete a backup
 #[tauri::command]
 pub async fn delete_package_backup(backup_name: String) -> Result<String, String> {
-    let backup_dir = get_backup_directory()?;
-    let backup_file = format!("{}/{}.json", backup_dir, backup_name);
-    let list_file = format!("{}/{}.txt", backup_dir, backup_name);
-
-    // Remove JSON backup file
-    if Path::new(&backup_file).exists() {
-        fs::remove_file(&backup_file)
-            .map_err(|e| format!("Failed to delete backup file: {}", e))?;
-    }
-
-    // Remove text list file
-    if Path::new(&list_file).exists() {
-        fs::remove_file(&list_file).map_err(|e| format!("Failed to delete list file: {}", e))?;
-    }
-
+    backup::delete_backup(&backup_name)?;
     Ok(format!("Backup '{}' deleted successfully", backup_name))
 }
 
+/// Delete automatic backups the retention settings no longer keep
+#[tauri::command]
+pub async fn apply_backup_retention() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(backup::apply_retention)
+        .await
+        .map_err(|e| format!("Failed to apply backup retention: {}", e))?
+}
+
 /// List pacman hooks
 #[tauri::command]
 pub async fn list_pacman_hooks() -> Result<Vec<PackageHook>, String> {
@@ -317,6 +228,26 @@ pub async fn export_packages(format: String) -> Result<String, String> {
 
     match format.as_str() {
         "txt" => Ok(packages.join("\n")),
+        "toml" => {
+            // Declarative manifest, see `plan_manifest`
+            let manifest = tokio::task::spawn_blocking(manifest::current_manifest)
+                .await
+                .map_err(|e| format!("Failed to read system state: {}", e))??;
+            manifest::render_manifest(&manifest)
+        }
+        "state" => {
+            // Everything another machine needs to diff against or restore from
+            let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+            let state = backup::capture_current_state(
+                format!("state_{}", timestamp),
+                "Exported package state".to_string(),
+                timestamp,
+                false,
+            )
+            .await?;
+            serde_json::to_string_pretty(&state)
+                .map_err(|e| format!("Failed to serialize to JSON: {}", e))
+        }
         "json" => {
             let json = serde_json::to_string_pretty(&packages)
                 .map_err(|e| format!("Failed to serialize to JSON: {}", e))?;
@@ -333,42 +264,15 @@ pub async fn export_packages(format: String) -> Result<String, String> {
 
 // Helper functions
 
-fn get_backup_directory() -> Result<String, String> {
-    let home = std::env::var("HOME").map_err(|_| {
-        "Failed to determine backup directory: HOME environment variable not set".to_string()
-    })?;
-    Ok(format!("{}/.config/guiman/backups", home))
-}
-
-async fn is_aur_package(package: &str) -> bool {
-    let aur_helpers = ["yay", "paru"];
-    for helper in &aur_helpers {
-        let output = Command::new(format!("/usr/bin/{}", helper))
-            .args(["-Si", package])
-            .output();
-
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if stdout.contains("Repository") && stdout.contains("aur") {
-                    return true;
-                }
-            }
-        }
-    }
-    false
-}
-
-fn get_aur_helper() -> Option<String> {
-    let helpers = ["yay", "paru"];
-    for helper in &helpers {
-        if let Ok(output) = Command::new("/usr/bin/which").arg(helper).output() {
-            if output.status.success() && !output.stdout.is_empty() {
-                return Some(helper.to_string());
-            }
+async fn load_state(source: StateSource) -> Result<SystemBackup, String> {
+    match source {
+        StateSource::System => {
+            let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+            backup::capture_current_state(String::new(), String::new(), timestamp, false).await
         }
+        StateSource::Backup(name) => backup::load_backup(&name),
+        StateSource::File(path) => backup::read_backup_file(Path::new(&path)),
     }
-    None
 }
 
 fn parse_hook_file(path: &str, content: &str) -> PackageHook {
@@ -0,0 +1,49 @@
+use crate::catalog;
+
+/// List app categories with their sizes
+#[tauri::command]
+pub async fn get_catalog_categories() -> Result<Vec<catalog::CatalogCategory>, String> {
+    tokio::task::spawn_blocking(catalog::get_catalog_categories)
+        .await
+        .map_err(|e| format!("Failed to load catalog: {}", e))?
+}
+
+/// Browse the apps in a category
+#[tauri::command]
+pub async fn browse_category(
+    category: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<catalog::CatalogEntry>, String> {
+    tokio::task::spawn_blocking(move || {
+        catalog::browse_category(&category, offset.unwrap_or(0), limit.unwrap_or(50))
+    })
+    .await
+    .map_err(|e| format!("Failed to load catalog: {}", e))?
+}
+
+/// Get the hand-picked featured apps
+#[tauri::command]
+pub async fn get_featured_apps() -> Result<Vec<catalog::CatalogEntry>, String> {
+    tokio::task::spawn_blocking(catalog::get_featured_apps)
+        .await
+        .map_err(|e| format!("Failed to load catalog: {}", e))?
+}
+
+/// Get the AppStream metadata of a package, if it has any
+#[tauri::command]
+pub async fn get_app_metadata(
+    package: String,
+) -> Result<Option<catalog::appstream::AppStreamComponent>, String> {
+    tokio::task::spawn_blocking(move || catalog::appstream::component_for_package(&package))
+        .await
+        .map_err(|e| format!("Failed to load AppStream data: {}", e))?
+}
+
+/// Launch an installed GUI application
+#[tauri::command]
+pub async fn launch_app(package: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || catalog::desktop::launch_app(&package))
+        .await
+        .map_err(|e| format!("Failed to launch app: {}", e))?
+}
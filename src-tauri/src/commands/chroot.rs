@@ -0,0 +1,27 @@
+use crate::aur;
+use crate::models::CommandResult;
+use tauri::Window;
+
+/// Get the clean build chroot status
+#[tauri::command]
+pub async fn get_chroot_status() -> Result<aur::ChrootStatus, String> {
+    Ok(aur::get_chroot_status())
+}
+
+/// Create the clean build chroot
+#[tauri::command]
+pub async fn create_chroot(window: Window) -> Result<CommandResult, String> {
+    aur::create_chroot_async(window).await
+}
+
+/// Update the packages inside the clean build chroot
+#[tauri::command]
+pub async fn update_chroot(window: Window) -> Result<CommandResult, String> {
+    aur::update_chroot_async(window).await
+}
+
+/// Delete the clean build chroot
+#[tauri::command]
+pub async fn delete_chroot() -> Result<String, String> {
+    aur::delete_chroot()
+}
@@ -1,8 +1,13 @@
 use crate::error::{GuiManError, Result};
-use crate::models::PackageDependencyInfo;
+use crate::models::{CommandResult, PackageDependencyInfo};
+use crate::pacman::{
+    self, export_graph, package_graph, Direction, ExportOptions, GraphFormat, GraphPackage,
+    InstallExplanation, OptionalDependency, RemovalImpact, UnneededOptionalDependency,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::process::Command;
+use tauri::Window;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DependencyNode {
@@ -10,9 +15,13 @@ pub struct DependencyNode {
     pub version: String,
     pub repo: String,
     pub installed: bool,
+    pub explicit: bool,
+    /// Resolved package names, so virtual dependencies like `sh` appear as `bash`
     pub dependencies: Vec<String>,
     pub optional_deps: Vec<String>,
     pub required_by: Vec<String>,
+    /// Dependencies no known package satisfies
+    pub unresolved: Vec<String>,
     pub level: usize,
 }
 
@@ -23,34 +32,15 @@ pub struct DependencyGraph {
     pub max_depth: usize,
 }
 
-/// Get dependency tree for a package
+/// Get dependency tree for a package; without `max_depth` the full closure is returned
 #[tauri::command]
 pub async fn get_dependency_tree(
     package: String,
     max_depth: Option<usize>,
 ) -> std::result::Result<DependencyGraph, String> {
-    println!("Getting dependency tree for: {}", package);
-    let max_depth = max_depth.unwrap_or(2); // Reduced default depth
-    let mut graph = DependencyGraph {
-        root: package.clone(),
-        nodes: HashMap::new(),
-        max_depth,
-    };
-
-    let mut visited = HashSet::new();
-    match build_dependency_tree(&package, 0, max_depth, &mut graph.nodes, &mut visited) {
-        Ok(_) => {
-            println!(
-                "Successfully built dependency tree with {} nodes",
-                graph.nodes.len()
-            );
-            Ok(graph)
-        }
-        Err(e) => {
-            println!("Error building dependency tree: {}", e);
-            Err(e)
-        }
-    }
+    tokio::task::spawn_blocking(move || build_graph(&package, Direction::Dependencies, max_depth))
+        .await
+        .map_err(|e| format!("Failed to build dependency tree: {}", e))?
 }
 
 /// Get reverse dependency tree (what depends on this package)
@@ -59,112 +49,186 @@ pub async fn get_reverse_dependency_tree(
     package: String,
     max_depth: Option<usize>,
 ) -> std::result::Result<DependencyGraph, String> {
-    let max_depth = max_depth.unwrap_or(2); // Reduced default depth
-    let mut graph = DependencyGraph {
-        root: package.clone(),
-        nodes: HashMap::new(),
+    tokio::task::spawn_blocking(move || build_graph(&package, Direction::Dependents, max_depth))
+        .await
+        .map_err(|e| format!("Failed to build dependency tree: {}", e))?
+}
+
+/// Export the dependency graph of a package as DOT, GraphML or JSON
+#[tauri::command]
+pub async fn export_dependency_graph(
+    package: String,
+    format: String,
+    reverse: Option<bool>,
+    max_depth: Option<usize>,
+    include_optional: Option<bool>,
+    mark_installed: Option<bool>,
+    mark_explicit: Option<bool>,
+) -> std::result::Result<String, String> {
+    let format =
+        GraphFormat::parse(&format).ok_or_else(|| format!("Unsupported format: {}", format))?;
+    let options = ExportOptions {
+        direction: if reverse.unwrap_or(false) {
+            Direction::Dependents
+        } else {
+            Direction::Dependencies
+        },
         max_depth,
+        include_optional: include_optional.unwrap_or(false),
+        mark_installed: mark_installed.unwrap_or(true),
+        mark_explicit: mark_explicit.unwrap_or(true),
     };
 
-    let mut visited = HashSet::new();
-    build_reverse_dependency_tree(&package, 0, max_depth, &mut graph.nodes, &mut visited)?;
-
-    Ok(graph)
+    tokio::task::spawn_blocking(move || {
+        let graph = package_graph()?;
+        export_graph(&graph, &package, format, &options)
+    })
+    .await
+    .map_err(|e| format!("Failed to export dependency graph: {}", e))?
 }
 
-fn build_dependency_tree(
-    package: &str,
-    level: usize,
-    max_depth: usize,
-    nodes: &mut HashMap<String, DependencyNode>,
-    visited: &mut HashSet<String>,
-) -> std::result::Result<(), String> {
-    // Prevent runaway processing
-    if level > max_depth || visited.contains(package) || nodes.len() > 50 {
-        return Ok(());
-    }
-
-    visited.insert(package.to_string());
-
-    // Try to get package info - first from installed packages, then from repos, then from AUR
-    let pkg_info = get_package_info_for_deps(package)?;
-
-    // Get what requires this package (only works for installed packages)
-    let required_by = get_required_by(package).unwrap_or_default();
+/// Explain why an installed package is present, as chains up to explicit packages
+#[tauri::command]
+pub async fn explain_package(package: String) -> std::result::Result<InstallExplanation, String> {
+    tokio::task::spawn_blocking(move || {
+        let graph = package_graph()?;
+        pacman::explain_package(&graph, &package)
+    })
+    .await
+    .map_err(|e| format!("Failed to explain package: {}", e))?
+}
 
-    let node = DependencyNode {
-        name: package.to_string(),
-        version: pkg_info.version,
-        repo: pkg_info.repository,
-        installed: pkg_info.installed,
-        dependencies: pkg_info.dependencies.clone(),
-        optional_deps: pkg_info.optional_dependencies.clone(),
-        required_by,
-        level,
-    };
+/// Show what removing packages would break, disable or orphan, and the space freed
+#[tauri::command]
+pub async fn analyze_removal_impact(
+    packages: Vec<String>,
+) -> std::result::Result<RemovalImpact, String> {
+    tokio::task::spawn_blocking(move || {
+        let graph = package_graph()?;
+        pacman::analyze_removal(&graph, &packages)
+    })
+    .await
+    .map_err(|e| format!("Failed to analyze removal: {}", e))?
+}
 
-    nodes.insert(package.to_string(), node);
+/// List a package's optional dependencies with whether each is installed
+#[tauri::command]
+pub async fn get_optional_dependencies(
+    package: String,
+) -> std::result::Result<Vec<OptionalDependency>, String> {
+    tokio::task::spawn_blocking(move || {
+        let graph = package_graph()?;
+        pacman::optional_features(&graph, &package)
+    })
+    .await
+    .map_err(|e| format!("Failed to load optional dependencies: {}", e))?
+}
 
-    // Recursively build dependency tree
-    for dep in &pkg_info.dependencies {
-        let dep_name = clean_dependency_name(dep);
-        if let Err(e) = build_dependency_tree(&dep_name, level + 1, max_depth, nodes, visited) {
-            println!(
-                "Warning: Failed to build dependency tree for {}: {}",
-                dep_name, e
-            );
-            // Continue with other dependencies instead of failing completely
-        }
-    }
+/// Install optional dependencies with `--asdeps`
+#[tauri::command]
+pub async fn install_optional_dependencies(
+    window: Window,
+    packages: Vec<String>,
+) -> std::result::Result<CommandResult, String> {
+    pacman::install_optional_dependencies_async(window, packages).await
+}
 
-    Ok(())
+/// List installed dependencies nothing requires or wants as optional anymore
+#[tauri::command]
+pub async fn list_unneeded_optional_dependencies(
+) -> std::result::Result<Vec<UnneededOptionalDependency>, String> {
+    tokio::task::spawn_blocking(|| {
+        let graph = package_graph()?;
+        Ok(pacman::unneeded_optional_dependencies(&graph))
+    })
+    .await
+    .map_err(|e| format!("Failed to load optional dependencies: {}", e))?
 }
 
-fn build_reverse_dependency_tree(
+fn build_graph(
     package: &str,
-    level: usize,
-    max_depth: usize,
-    nodes: &mut HashMap<String, DependencyNode>,
-    visited: &mut HashSet<String>,
-) -> std::result::Result<(), String> {
-    // Prevent runaway processing
-    if level > max_depth || visited.contains(package) || nodes.len() > 50 {
-        return Ok(());
-    }
-
-    visited.insert(package.to_string());
-
-    // Try to get package info - first from installed packages, then from repos, then from AUR
-    let pkg_info = get_package_info_for_deps(package)?;
-
-    let required_by = get_required_by(package).unwrap_or_default();
-
-    let node = DependencyNode {
-        name: package.to_string(),
-        version: pkg_info.version,
-        repo: pkg_info.repository,
-        installed: pkg_info.installed,
-        dependencies: pkg_info.dependencies,
-        optional_deps: pkg_info.optional_dependencies,
-        required_by: required_by.clone(),
-        level,
+    direction: Direction,
+    max_depth: Option<usize>,
+) -> std::result::Result<DependencyGraph, String> {
+    let graph = package_graph()?;
+    let mut nodes = HashMap::new();
+
+    let reached = if graph.get(package).is_some() {
+        graph.closure(&[package], direction, max_depth, false)
+    } else if direction == Direction::Dependencies {
+        // Not in any database (an AUR package that is not installed): ask the
+        // helper for its dependencies and continue through the graph from there
+        let info = get_package_info_for_deps(package).map_err(|e| e.to_string())?;
+        let dependencies: Vec<String> = info
+            .dependencies
+            .iter()
+            .map(|dep| {
+                graph
+                    .resolve(dep)
+                    .map_or_else(|| clean_dependency_name(dep), |p| p.name.clone())
+            })
+            .collect();
+
+        nodes.insert(
+            package.to_string(),
+            DependencyNode {
+                name: package.to_string(),
+                version: info.version,
+                repo: info.repository,
+                installed: info.installed,
+                explicit: false,
+                dependencies: dependencies.clone(),
+                optional_deps: info.optional_dependencies,
+                required_by: Vec::new(),
+                unresolved: Vec::new(),
+                level: 0,
+            },
+        );
+
+        if max_depth == Some(0) {
+            Vec::new()
+        } else {
+            let roots: Vec<&str> = dependencies.iter().map(|d| d.as_str()).collect();
+            graph
+                .closure(&roots, direction, max_depth.map(|d| d - 1), false)
+                .into_iter()
+                .map(|(level, node)| (level + 1, node))
+                .collect()
+        }
+    } else {
+        return Err(GuiManError::PackageNotFound {
+            package: package.to_string(),
+        }
+        .to_string());
     };
 
-    nodes.insert(package.to_string(), node);
-
-    // Recursively build reverse dependency tree
-    for req_by in required_by {
-        if let Err(e) = build_reverse_dependency_tree(&req_by, level + 1, max_depth, nodes, visited)
-        {
-            println!(
-                "Warning: Failed to build reverse dependency tree for {}: {}",
-                req_by, e
-            );
-            // Continue with other dependencies instead of failing completely
-        }
+    for (level, node) in reached {
+        let names = |packages: Vec<&GraphPackage>| -> Vec<String> {
+            packages.into_iter().map(|p| p.name.clone()).collect()
+        };
+        nodes.insert(
+            node.name.clone(),
+            DependencyNode {
+                name: node.name.clone(),
+                version: node.version.clone(),
+                repo: node.repo.clone(),
+                installed: node.installed,
+                explicit: node.explicit,
+                dependencies: names(graph.dependencies(&node.name)),
+                optional_deps: names(graph.optional_dependencies(&node.name)),
+                required_by: names(graph.required_by(&node.name)),
+                unresolved: graph.unresolved(&node.name).to_vec(),
+                level,
+            },
+        );
     }
 
-    Ok(())
+    let depth = nodes.values().map(|n| n.level).max().unwrap_or(0);
+    Ok(DependencyGraph {
+        root: package.to_string(),
+        nodes,
+        max_depth: max_depth.unwrap_or(depth),
+    })
 }
 
 fn extract_dependencies(output: &str) -> Vec<String> {
@@ -238,34 +302,6 @@ fn extract_field(output: &str, field: &str) -> String {
     "Unknown".to_string()
 }
 
-fn get_required_by(package: &str) -> std::result::Result<Vec<String>, String> {
-    let output = Command::new("/usr/bin/pacman")
-        .args(["-Qi", package])
-        .output()
-        .map_err(|e| format!("Failed to get required by: {}", e))?;
-
-    if !output.status.success() {
-        return Ok(Vec::new());
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines() {
-        if line.starts_with("Required By") {
-            let req_str = line.split(':').nth(1).unwrap_or("").trim();
-            if req_str == "None" {
-                return Ok(Vec::new());
-            }
-            return Ok(req_str
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .filter(|s| !s.is_empty())
-                .collect());
-        }
-    }
-
-    Ok(Vec::new())
-}
-
 fn get_package_info_for_deps(package: &str) -> Result<PackageDependencyInfo> {
     // println!("Getting package info for: {}", package); // Commented out to reduce spam
 
@@ -1,4 +1,7 @@
+use crate::models::CommandResult;
+use crate::pacman;
 use std::process::Command;
+use tauri::Window;
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct FileInfo {
@@ -64,40 +67,41 @@ pub async fn find_file_owner(file_path: String) -> Result<String, String> {
     Err("Could not parse package owner".to_string())
 }
 
-/// Search for files matching a pattern across all packages
+/// Search the sync file databases by full path, file name or regex.
+///
+/// Without a `mode`, patterns containing a slash are treated as paths.
 #[tauri::command]
-pub async fn search_files(pattern: String) -> Result<Vec<FileInfo>, String> {
-    let output = Command::new("/usr/bin/pacman")
-        .args(["-Fl"])
-        .output()
-        .map_err(|e| format!("Failed to search files: {}", e))?;
+pub async fn search_files(
+    pattern: String,
+    mode: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<pacman::FileMatch>, String> {
+    let mode = match mode.as_deref() {
+        Some(mode) => pacman::FileSearchMode::parse(mode)
+            .ok_or_else(|| format!("Unknown file search mode '{}'", mode))?,
+        None if pattern.contains('/') => pacman::FileSearchMode::Exact,
+        None => pacman::FileSearchMode::Basename,
+    };
+
+    pacman::search_file_database(&pattern, mode, limit.unwrap_or(100))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let pattern_lower = pattern.to_lowercase();
+/// Find which packages provide a command, installed or not
+#[tauri::command]
+pub async fn find_command_providers(command: String) -> Result<Vec<pacman::FileMatch>, String> {
+    pacman::find_command_providers(&command)
+}
 
-    let files: Vec<FileInfo> = stdout
-        .lines()
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let package = parts[0].to_string();
-                let path = parts[1].to_string();
-
-                if path.to_lowercase().contains(&pattern_lower) {
-                    let is_directory = path.ends_with('/');
-                    return Some(FileInfo {
-                        path,
-                        package,
-                        is_directory,
-                    });
-                }
-            }
-            None
-        })
-        .take(100) // Limit results
-        .collect();
+/// Get the freshness of the sync file databases
+#[tauri::command]
+pub async fn get_file_database_status() -> Result<pacman::FileDatabaseStatus, String> {
+    Ok(pacman::get_file_database_status())
+}
 
-    Ok(files)
+/// Download the sync file databases (`pacman -Fy`)
+#[tauri::command]
+pub async fn refresh_file_database(window: Window) -> Result<CommandResult, String> {
+    pacman::refresh_file_database_async(window).await
 }
 
 /// Get backup files for a package
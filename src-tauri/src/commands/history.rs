@@ -0,0 +1,81 @@
+use crate::models::CommandResult;
+use crate::pacman::log::{self, HistoryFilter, HistoryPage, LogAction};
+use crate::pacman::rollback::{self, RollbackPlan};
+use crate::pacman::timeline::{self, PackageTimeline};
+use tauri::Window;
+
+/// Page through pacman transactions, newest first, across rotated logs
+#[tauri::command]
+pub async fn get_transaction_history(
+    offset: Option<usize>,
+    limit: Option<usize>,
+    since: Option<String>,
+    until: Option<String>,
+    package: Option<String>,
+    action: Option<String>,
+) -> Result<HistoryPage, String> {
+    let filter = HistoryFilter {
+        since: parse_bound(since.as_deref(), log::parse_log_timestamp)?,
+        until: parse_bound(until.as_deref(), log::parse_until_timestamp)?,
+        package: package.filter(|p| !p.trim().is_empty()),
+        action: match action.as_deref().filter(|a| !a.is_empty()) {
+            Some(action) => Some(
+                LogAction::parse(action).ok_or_else(|| format!("Unknown action: {}", action))?,
+            ),
+            None => None,
+        },
+    };
+
+    tokio::task::spawn_blocking(move || {
+        log::query_history(&filter, offset.unwrap_or(0), limit.unwrap_or(50))
+    })
+    .await
+    .map_err(|e| format!("Failed to read pacman log: {}", e))?
+}
+
+/// Preview the inverse of a transaction, including versions missing from the cache
+#[tauri::command]
+pub async fn preview_rollback(transaction_id: String) -> Result<RollbackPlan, String> {
+    tokio::task::spawn_blocking(move || rollback::plan_rollback(&transaction_id))
+        .await
+        .map_err(|e| format!("Failed to plan rollback: {}", e))?
+}
+
+/// Undo a transaction following the plan from `preview_rollback`; `allow_partial`
+/// runs it even when some previous versions are gone from the cache
+#[tauri::command]
+pub async fn rollback_transaction(
+    window: Window,
+    plan: RollbackPlan,
+    allow_partial: Option<bool>,
+) -> Result<CommandResult, String> {
+    rollback::execute_rollback_async(window, plan, allow_partial.unwrap_or(false)).await
+}
+
+/// Install/upgrade/removal timeline of one package with its changelog and,
+/// unless `include_upstream` is false, packaging commits between versions
+#[tauri::command]
+pub async fn get_package_timeline(
+    package: String,
+    include_upstream: Option<bool>,
+) -> Result<PackageTimeline, String> {
+    tokio::task::spawn_blocking(move || {
+        timeline::package_timeline(&package, include_upstream.unwrap_or(true))
+    })
+    .await
+    .map_err(|e| format!("Failed to build package timeline: {}", e))?
+}
+
+// Helper functions
+
+fn parse_bound(
+    value: Option<&str>,
+    parse: fn(&str) -> Option<chrono::DateTime<chrono::FixedOffset>>,
+) -> Result<Option<chrono::DateTime<chrono::FixedOffset>>, String> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => parse(value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid date: {}", value)),
+        None => Ok(None),
+    }
+}
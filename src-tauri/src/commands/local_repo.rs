@@ -0,0 +1,36 @@
+use crate::aur;
+use crate::models::CommandResult;
+use tauri::Window;
+
+/// Get the status of the `[guiman-local]` repository
+#[tauri::command]
+pub async fn get_local_repo_status() -> Result<aur::LocalRepoStatus, String> {
+    aur::get_local_repo_status()
+}
+
+/// List package files stored in the local repository
+#[tauri::command]
+pub async fn list_local_repo_packages() -> Result<Vec<aur::LocalRepoPackage>, String> {
+    aur::list_local_repo_packages()
+}
+
+/// Add the local repository to pacman.conf
+#[tauri::command]
+pub async fn configure_local_repo() -> Result<String, String> {
+    aur::configure_local_repo()
+}
+
+/// Remove a package from the local repository
+#[tauri::command]
+pub async fn remove_from_local_repo(package: String) -> Result<String, String> {
+    aur::remove_from_local_repo(&package)
+}
+
+/// Reinstall or downgrade a package from a file in the local repository
+#[tauri::command]
+pub async fn install_from_local_repo(
+    window: Window,
+    filename: String,
+) -> Result<CommandResult, String> {
+    aur::install_from_local_repo_async(window, filename).await
+}
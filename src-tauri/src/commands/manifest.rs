@@ -0,0 +1,74 @@
+use crate::backup::{self, RestoreStepResult};
+use crate::manifest::{self, Manifest, ManifestPlan};
+use std::path::PathBuf;
+
+/// Path of the manifest used when none is given
+#[tauri::command]
+pub async fn get_manifest_path() -> Result<String, String> {
+    Ok(manifest::default_manifest_path()?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Read a manifest as TOML text
+#[tauri::command]
+pub async fn read_manifest(path: Option<String>) -> Result<String, String> {
+    let manifest = manifest::load_manifest(&manifest_path(path)?)?;
+    manifest::render_manifest(&manifest)
+}
+
+/// Validate and save manifest TOML
+#[tauri::command]
+pub async fn write_manifest(path: Option<String>, content: String) -> Result<(), String> {
+    let manifest = manifest::parse_manifest(&content)?;
+    manifest::save_manifest(&manifest_path(path)?, &manifest)
+}
+
+/// Write a manifest describing the running system
+#[tauri::command]
+pub async fn create_manifest_from_system(path: Option<String>) -> Result<String, String> {
+    let path = manifest_path(path)?;
+    let manifest: Manifest = tokio::task::spawn_blocking(manifest::current_manifest)
+        .await
+        .map_err(|e| format!("Failed to read system state: {}", e))??;
+    manifest::save_manifest(&path, &manifest)?;
+    Ok(format!(
+        "Manifest with {} packages written to {}",
+        manifest.packages.len() + manifest.aur.len(),
+        path.display()
+    ))
+}
+
+/// What applying a manifest would change
+#[tauri::command]
+pub async fn plan_manifest(path: Option<String>) -> Result<ManifestPlan, String> {
+    let manifest = manifest::load_manifest(&manifest_path(path)?)?;
+    tokio::task::spawn_blocking(move || manifest::plan_manifest(&manifest))
+        .await
+        .map_err(|e| format!("Failed to plan manifest: {}", e))?
+}
+
+/// Converge the system to a manifest following the `plan` from `plan_manifest`.
+/// Refuses to run when the manifest or the system changed since the preview.
+#[tauri::command]
+pub async fn apply_manifest(
+    path: Option<String>,
+    plan: ManifestPlan,
+) -> Result<Vec<RestoreStepResult>, String> {
+    let manifest = manifest::load_manifest(&manifest_path(path)?)?;
+    tokio::task::spawn_blocking(move || {
+        let aur_helper = backup::installed_aur_helper();
+        manifest::apply_manifest(&manifest, &plan, aur_helper.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to apply manifest: {}", e))
+}
+
+// Helper functions
+
+fn manifest_path(path: Option<String>) -> Result<PathBuf, String> {
+    match path.filter(|p| !p.trim().is_empty()) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => manifest::default_manifest_path(),
+    }
+}
@@ -1,19 +1,33 @@
 pub mod backup;
+pub mod catalog;
+pub mod chroot;
 pub mod dependencies;
 pub mod files;
 pub mod groups;
+pub mod history;
+pub mod local_repo;
+pub mod manifest;
 pub mod package;
 pub mod repos;
 pub mod search;
+pub mod settings;
+pub mod snapshots;
 pub mod system;
 pub mod updater;
 
 pub use backup::*;
+pub use catalog::*;
+pub use chroot::*;
 pub use dependencies::*;
 pub use files::*;
 pub use groups::*;
+pub use history::*;
+pub use local_repo::*;
+pub use manifest::*;
 pub use package::*;
 pub use repos::*;
 pub use search::*;
+pub use settings::*;
+pub use snapshots::*;
 pub use system::*;
 pub use updater::*;
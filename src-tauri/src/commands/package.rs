@@ -1,5 +1,7 @@
+use crate::catalog::appstream::{component_for_package, AppStreamComponent};
 use crate::models::{CommandResult, PackageInfo};
-use crate::{aur, pacman, utils};
+use crate::pacman::OptionalDependency;
+use crate::{aur, pacman, settings, utils};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::process::Command;
@@ -22,6 +24,8 @@ pub struct EnhancedPackageInfo {
     pub provides: Vec<String>,
     pub depends_on: Vec<String>,
     pub optional_deps: Vec<String>,
+    /// Parsed optional dependencies with their installed state
+    pub optional_dependencies: Vec<OptionalDependency>,
     pub required_by: Vec<String>,
     pub optional_for: Vec<String>,
     pub conflicts_with: Vec<String>,
@@ -34,6 +38,8 @@ pub struct EnhancedPackageInfo {
     pub install_reason: String,
     pub install_script: bool,
     pub validated_by: String,
+    /// Human name, long description, screenshots and more from AppStream
+    pub appstream: Option<AppStreamComponent>,
 }
 
 #[tauri::command]
@@ -71,6 +77,7 @@ pub async fn get_enhanced_package_info(pkg: String) -> Result<EnhancedPackageInf
         provides: Vec::new(),
         depends_on: Vec::new(),
         optional_deps: Vec::new(),
+        optional_dependencies: Vec::new(),
         required_by: Vec::new(),
         optional_for: Vec::new(),
         conflicts_with: Vec::new(),
@@ -83,6 +90,7 @@ pub async fn get_enhanced_package_info(pkg: String) -> Result<EnhancedPackageInf
         install_reason: String::new(),
         install_script: false,
         validated_by: String::new(),
+        appstream: None,
     };
 
     for line in stdout.lines() {
@@ -117,6 +125,22 @@ pub async fn get_enhanced_package_info(pkg: String) -> Result<EnhancedPackageInf
         }
     }
 
+    let name = info.name.clone();
+    info.appstream = tokio::task::spawn_blocking(move || component_for_package(&name))
+        .await
+        .ok()
+        .and_then(|result| result.ok())
+        .flatten();
+
+    let name = info.name.clone();
+    info.optional_dependencies = tokio::task::spawn_blocking(move || {
+        pacman::package_graph().and_then(|graph| pacman::optional_features(&graph, &name))
+    })
+    .await
+    .ok()
+    .and_then(|result| result.ok())
+    .unwrap_or_default();
+
     Ok(info)
 }
 
@@ -193,13 +217,26 @@ pub async fn get_package_info(
     Ok(serde_json::Value::Object(info))
 }
 
+#[tauri::command]
+pub async fn get_aur_package_details(
+    pkg: String,
+    offset: Option<usize>,
+    per_page: Option<usize>,
+) -> Result<aur::AurPackageDetails, String> {
+    aur::get_aur_package_details(&pkg, offset, per_page).await
+}
+
 #[tauri::command]
 pub async fn list_aur_packages(helper: String) -> Result<Vec<PackageInfo>, String> {
     aur::list_aur_packages(&helper)
 }
 
 #[tauri::command]
-pub async fn install_package(window: Window, pkg: String) -> Result<CommandResult, String> {
+pub async fn install_package(
+    window: Window,
+    pkg: String,
+    clean_chroot: Option<bool>,
+) -> Result<CommandResult, String> {
     // Check if package exists in official repositories
     let is_official = Command::new("/usr/bin/pacman")
         .args(["-Si", &pkg])
@@ -210,6 +247,9 @@ pub async fn install_package(window: Window, pkg: String) -> Result<CommandResul
     if is_official {
         // Use pacman for official packages
         pacman::install_package_async(window, pkg).await
+    } else if clean_chroot.unwrap_or_else(|| settings::load_settings().clean_chroot_builds) {
+        // Build AUR packages in a clean chroot to keep makedepends off the host
+        aur::install_aur_in_chroot_async(window, pkg).await
     } else {
         // Use AUR helper for AUR packages (async with real-time output)
         aur::install_aur_package_async(window, pkg).await
@@ -237,22 +277,67 @@ pub async fn remove_package(window: Window, pkg: String) -> Result<CommandResult
 // AUR Advanced Features
 
 #[tauri::command]
-pub async fn vote_aur_package(package: String, helper: String) -> Result<String, String> {
-    aur::vote_aur_package(&package, &helper)
+pub async fn aur_login(
+    username: String,
+    password: String,
+) -> Result<aur::AurAccountStatus, String> {
+    aur::aur_login(&username, &password).await
 }
 
 #[tauri::command]
-pub async fn flag_aur_package(
+pub async fn aur_logout() -> Result<String, String> {
+    aur::aur_logout().await
+}
+
+#[tauri::command]
+pub async fn get_aur_account_status() -> Result<aur::AurAccountStatus, String> {
+    Ok(aur::aur_account_status())
+}
+
+#[tauri::command]
+pub async fn vote_aur_package(package: String, vote: Option<bool>) -> Result<String, String> {
+    let action = if vote.unwrap_or(true) {
+        aur::AurAction::Vote
+    } else {
+        aur::AurAction::Unvote
+    };
+    aur::perform_aur_action(&package, action, None).await
+}
+
+#[tauri::command]
+pub async fn flag_aur_package(package: String, comment: Option<String>) -> Result<String, String> {
+    aur::perform_aur_action(&package, aur::AurAction::Flag, comment).await
+}
+
+#[tauri::command]
+pub async fn set_aur_notifications(package: String, enabled: bool) -> Result<String, String> {
+    let action = if enabled {
+        aur::AurAction::Notify
+    } else {
+        aur::AurAction::Unnotify
+    };
+    aur::perform_aur_action(&package, action, None).await
+}
+
+#[tauri::command]
+pub async fn adopt_aur_package(package: String) -> Result<String, String> {
+    aur::perform_aur_action(&package, aur::AurAction::Adopt, None).await
+}
+
+#[tauri::command]
+pub async fn disown_aur_package(
     package: String,
-    helper: String,
     comment: Option<String>,
 ) -> Result<String, String> {
-    aur::flag_aur_package(&package, &helper, comment)
+    aur::perform_aur_action(&package, aur::AurAction::Disown, comment).await
 }
 
 #[tauri::command]
-pub async fn adopt_aur_package(package: String, helper: String) -> Result<String, String> {
-    aur::adopt_aur_package(&package, &helper)
+pub async fn assess_aur_packages(
+    packages: Vec<String>,
+    include_sources: Option<bool>,
+) -> Result<Vec<aur::AurRiskAssessment>, String> {
+    aur::assess_aur_packages(&packages, include_sources.unwrap_or(false)).await
 }
 
 #[tauri::command]
@@ -137,6 +137,8 @@ pub async fn sync_databases() -> Result<String, String> {
         .map_err(|e| format!("Failed to sync databases: {}", e))?;
 
     if output.status.success() {
+        // Pick up the new package metadata right away
+        let _ = tokio::task::spawn_blocking(crate::pacman::refresh_package_index).await;
         Ok("Databases synced successfully".to_string())
     } else {
         Err(String::from_utf8_lossy(&output.stderr).to_string())
@@ -1,120 +1,172 @@
-use crate::aur;
+use crate::aur::rpc::AurRpcPackage;
 use crate::models::PackageInfo;
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use std::process::Command;
-
+use crate::pacman::SearchFields;
+use crate::search_query::{Candidate, SearchQuery};
+use crate::{aur, pacman};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::collections::HashMap;
+
+/// Search the package index (and optionally the AUR), ranked and paginated.
+///
+/// The query may contain filters such as `repo:extra size:>50M -git`.
 #[tauri::command]
 pub async fn search_package(
     query: String,
     aur_enabled: Option<bool>,
     aur_helper: Option<String>,
+    max_aur_risk: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
 ) -> Result<Vec<PackageInfo>, String> {
-    if query.trim().is_empty() {
+    let parsed = SearchQuery::parse(&query)?;
+    if parsed.is_empty() {
         return Ok(Vec::new());
     }
+    let text = parsed.text();
+
+    // Building the index runs pacman, keep it off the async runtime
+    let index = tokio::task::spawn_blocking(pacman::package_index)
+        .await
+        .map_err(|e| format!("Failed to load package index: {}", e))??;
+
+    let official = if text.is_empty() {
+        // Filter-only query, consider every repository package
+        index
+            .packages()
+            .iter()
+            .filter(|record| record.repo != "local")
+            .map(|record| (0, record))
+            .collect()
+    } else {
+        index.search(&parsed.terms)
+    };
+
+    let mut scored_packages: Vec<(i64, PackageInfo)> = official
+        .into_iter()
+        .filter(|(_, record)| parsed.matches(&Candidate::from_record(record)))
+        .map(|(score, record)| (score, record.to_package_info()))
+        .collect();
 
-    let matcher = SkimMatcherV2::default();
-    let mut scored_packages: Vec<(i64, PackageInfo)> = Vec::new();
-
-    // Search official repositories
-    let output = Command::new("/usr/bin/pacman")
-        .args(["-Sl"])
-        .output()
-        .map_err(|e| format!("Failed to execute pacman: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 3 {
-            let repo = parts[0];
-            let name = parts[1];
-            let version = parts[2];
-            let installed = line.contains("[installed]");
-
-            if let Some(score) = matcher.fuzzy_match(name, &query) {
-                scored_packages.push((
-                    score,
-                    PackageInfo {
-                        name: name.to_string(),
-                        version: version.to_string(),
-                        repo: repo.to_string(),
-                        description: String::new(),
-                        installed,
-                    },
-                ));
+    // The AUR can only be searched by text
+    if aur_enabled.unwrap_or(false) && !text.is_empty() && parsed.allows_repo("aur") {
+        let matcher = SkimMatcherV2::default();
+        let helper = aur_helper.unwrap_or_else(|| "yay".to_string());
+        if let Ok(mut aur_results) = aur::search_aur(&text, &helper, &matcher) {
+            if let Some(max_level) = max_aur_risk.as_deref().and_then(aur::RiskLevel::parse) {
+                // Without an assessment the risk limit cannot be honoured, so
+                // an unreachable AUR leaves only the official results
+                if filter_by_risk(&mut aur_results, max_level).await.is_err() {
+                    aur_results.clear();
+                }
             }
+            scored_packages.extend(rank_aur_results(aur_results, &parsed).await);
         }
     }
 
-    // Search AUR if enabled
-    if aur_enabled.unwrap_or(false) {
-        let helper = aur_helper.unwrap_or_else(|| "yay".to_string());
-        if let Ok(aur_results) = aur::search_aur(&query, &helper, &matcher) {
-            scored_packages.extend(aur_results);
-        }
-    }
+    // Stable sort keeps the index order (shorter names first) for equal scores
+    scored_packages.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
 
-    scored_packages.sort_by(|a, b| b.0.cmp(&a.0));
-    scored_packages.truncate(50);
+    Ok(scored_packages
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(50))
+        .map(|(_, pkg)| pkg)
+        .collect())
+}
 
-    let mut packages: Vec<PackageInfo> = scored_packages.into_iter().map(|(_, pkg)| pkg).collect();
+/// Rebuild the in-memory package index
+#[tauri::command]
+pub async fn refresh_package_index() -> Result<usize, String> {
+    let index = tokio::task::spawn_blocking(pacman::refresh_package_index)
+        .await
+        .map_err(|e| format!("Failed to rebuild package index: {}", e))??;
 
-    // Get descriptions for official packages only
-    enrich_package_descriptions(&mut packages)?;
+    Ok(index.packages().len())
+}
 
-    Ok(packages)
+/// Apply query filters to AUR results and rank them on the same scale as the index.
+///
+/// When metadata filters are used but the AUR cannot be reached, no AUR result
+/// can be checked and none are returned.
+async fn rank_aur_results(
+    results: Vec<(i64, PackageInfo)>,
+    query: &SearchQuery,
+) -> Vec<(i64, PackageInfo)> {
+    // Metadata filters need the full RPC info, plain text queries do not
+    let details: HashMap<String, AurRpcPackage> = if query.needs_metadata() {
+        let names: Vec<String> = results.iter().map(|(_, pkg)| pkg.name.clone()).collect();
+        let Ok(infos) = aur::rpc::fetch_aur_info(&names).await else {
+            return Vec::new();
+        };
+        infos
+            .into_iter()
+            .map(|info| (info.name.clone(), info))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    results
+        .into_iter()
+        .filter_map(|(_, pkg)| {
+            let fields = match details.get(&pkg.name) {
+                Some(info) => {
+                    if !query.matches(&Candidate::from_aur(info, pkg.installed)) {
+                        return None;
+                    }
+                    SearchFields::new(
+                        &pkg.name,
+                        &pkg.description,
+                        &info.provides,
+                        &[],
+                        &info.keywords,
+                    )
+                }
+                None if query.needs_metadata() => return None,
+                None => {
+                    let candidate = Candidate {
+                        name: &pkg.name,
+                        repo: "aur",
+                        installed: pkg.installed,
+                        ..Default::default()
+                    };
+                    if !query.matches(&candidate) {
+                        return None;
+                    }
+                    SearchFields::new(&pkg.name, &pkg.description, &[], &[], &[])
+                }
+            };
+
+            let score = pacman::score_fields(&query.terms, &fields).unwrap_or(1);
+            Some((score, pkg))
+        })
+        .collect()
 }
 
-/// Enrich packages with descriptions from pacman
-fn enrich_package_descriptions(packages: &mut [PackageInfo]) -> Result<(), String> {
-    let official_packages: Vec<String> = packages
+/// Drop AUR results whose risk level exceeds `max_level`
+async fn filter_by_risk(
+    results: &mut Vec<(i64, PackageInfo)>,
+    max_level: aur::RiskLevel,
+) -> Result<(), String> {
+    // Fetching every .SRCINFO would make searches slow, so sources are only
+    // checked for prebuilt -bin packages, where unverified downloads weigh most
+    let (binaries, others): (Vec<String>, Vec<String>) = results
         .iter()
-        .filter(|p| p.repo != "aur")
-        .map(|p| p.name.clone())
+        .map(|(_, pkg)| pkg.name.clone())
+        .partition(|name| name.ends_with("-bin"));
+    let mut assessments = aur::assess_aur_packages(&others, false).await?;
+    assessments.extend(aur::assess_aur_packages(&binaries, true).await?);
+
+    let levels: HashMap<String, aur::RiskLevel> = assessments
+        .into_iter()
+        .map(|assessment| (assessment.package, assessment.level))
         .collect();
 
-    if official_packages.is_empty() {
-        return Ok(());
-    }
-
-    let info_output = Command::new("/usr/bin/pacman")
-        .args(["-Si"])
-        .args(&official_packages)
-        .output()
-        .ok();
-
-    if let Some(info) = info_output {
-        let info_str = String::from_utf8_lossy(&info.stdout);
-        let mut current_name = String::new();
-        let mut current_desc = String::new();
-
-        for line in info_str.lines() {
-            if line.starts_with("Name") {
-                if !current_name.is_empty() && !current_desc.is_empty() {
-                    if let Some(pkg) = packages
-                        .iter_mut()
-                        .find(|p| p.name == current_name && p.repo != "aur")
-                    {
-                        pkg.description = current_desc.clone();
-                    }
-                }
-                current_name = line.split(':').nth(1).unwrap_or("").trim().to_string();
-                current_desc = String::new();
-            } else if line.starts_with("Description") {
-                current_desc = line.split(':').nth(1).unwrap_or("").trim().to_string();
-            }
-        }
-
-        if !current_name.is_empty() && !current_desc.is_empty() {
-            if let Some(pkg) = packages
-                .iter_mut()
-                .find(|p| p.name == current_name && p.repo != "aur")
-            {
-                pkg.description = current_desc;
-            }
-        }
-    }
+    results.retain(|(_, pkg)| {
+        levels
+            .get(&pkg.name)
+            .is_some_and(|level| *level <= max_level)
+    });
 
     Ok(())
 }
@@ -0,0 +1,13 @@
+use crate::settings::{self, Settings};
+
+/// Get the backend settings
+#[tauri::command]
+pub async fn get_app_settings() -> Result<Settings, String> {
+    Ok(settings::load_settings())
+}
+
+/// Save the backend settings
+#[tauri::command]
+pub async fn save_app_settings(settings: Settings) -> Result<(), String> {
+    settings::save_settings(&settings)
+}
@@ -0,0 +1,48 @@
+use crate::settings;
+use crate::snapshot::{self, Snapshot, SnapshotStatus};
+
+/// Which snapshot backends are available and which one transactions use
+#[tauri::command]
+pub async fn get_snapshot_status() -> Result<SnapshotStatus, String> {
+    tokio::task::spawn_blocking(snapshot::snapshot_status)
+        .await
+        .map_err(|e| format!("Failed to check snapshot support: {}", e))
+}
+
+/// List the snapshots of the active backend, newest first
+#[tauri::command]
+pub async fn list_snapshots() -> Result<Vec<Snapshot>, String> {
+    tokio::task::spawn_blocking(|| {
+        let backend = snapshot::active_backend(&settings::load_settings())
+            .ok_or("No snapshot backend available")?;
+        let mut snapshots = backend.list()?;
+        snapshots.reverse();
+        Ok(snapshots)
+    })
+    .await
+    .map_err(|e| format!("Failed to list snapshots: {}", e))?
+}
+
+/// Delete a snapshot of the active backend
+#[tauri::command]
+pub async fn delete_snapshot(id: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let backend = snapshot::active_backend(&settings::load_settings())
+            .ok_or("No snapshot backend available")?;
+        backend.delete(&id)
+    })
+    .await
+    .map_err(|e| format!("Failed to delete snapshot: {}", e))?
+}
+
+/// Apply the cleanup policy now, returning the deleted snapshot ids
+#[tauri::command]
+pub async fn cleanup_snapshots() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(|| {
+        let settings = settings::load_settings();
+        let backend = snapshot::active_backend(&settings).ok_or("No snapshot backend available")?;
+        snapshot::cleanup(backend.as_ref(), settings.snapshot_keep)
+    })
+    .await
+    .map_err(|e| format!("Failed to clean up snapshots: {}", e))?
+}
@@ -5,10 +5,17 @@
 
 // Module declarations
 mod aur;
+mod backup;
+mod catalog;
 mod commands;
 mod error;
+mod manifest;
 mod models;
 mod pacman;
+mod popularity;
+mod search_query;
+mod settings;
+mod snapshot;
 mod utils;
 
 use commands::*;
@@ -17,10 +24,26 @@ fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_shell::init())
+        .setup(|_app| {
+            // Warm the package index so the first search is instant
+            std::thread::spawn(|| {
+                let _ = pacman::package_index();
+            });
+            tauri::async_runtime::spawn(backup::run_backup_scheduler());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Search commands
             search_package,
+            refresh_package_index,
             get_popular_packages,
+            get_recommended_packages,
+            // Catalog commands
+            get_catalog_categories,
+            browse_category,
+            get_featured_apps,
+            get_app_metadata,
+            launch_app,
             // Package commands
             list_installed,
             get_package_counts,
@@ -28,16 +51,46 @@ fn main() {
             check_updates,
             list_orphans,
             get_package_history,
+            get_transaction_history,
+            preview_rollback,
+            rollback_transaction,
+            get_package_timeline,
             get_package_info,
+            get_aur_package_details,
             list_aur_packages,
             install_package,
             remove_package,
             // AUR Advanced Features
+            aur_login,
+            aur_logout,
+            get_aur_account_status,
             vote_aur_package,
             flag_aur_package,
+            set_aur_notifications,
             adopt_aur_package,
+            disown_aur_package,
+            assess_aur_packages,
             get_aur_build_info,
             install_aur_with_options,
+            // Clean chroot commands
+            get_chroot_status,
+            create_chroot,
+            update_chroot,
+            delete_chroot,
+            // Local repository commands
+            get_local_repo_status,
+            list_local_repo_packages,
+            configure_local_repo,
+            remove_from_local_repo,
+            install_from_local_repo,
+            // Settings commands
+            get_app_settings,
+            save_app_settings,
+            // Snapshot commands
+            get_snapshot_status,
+            list_snapshots,
+            delete_snapshot,
+            cleanup_snapshots,
             // System commands
             update_system,
             clean_cache,
@@ -53,6 +106,9 @@ fn main() {
             list_package_files,
             find_file_owner,
             search_files,
+            find_command_providers,
+            get_file_database_status,
+            refresh_file_database,
             list_package_backups,
             // Repository commands
             list_repositories,
@@ -64,11 +120,29 @@ fn main() {
             // Dependency commands
             get_dependency_tree,
             get_reverse_dependency_tree,
+            export_dependency_graph,
+            explain_package,
+            analyze_removal_impact,
+            get_optional_dependencies,
+            install_optional_dependencies,
+            list_unneeded_optional_dependencies,
             // Backup commands
             create_package_backup,
             list_backups,
+            get_package_backup,
+            migrate_package_backups,
+            preview_backup_restore,
             restore_package_backup,
             delete_package_backup,
+            apply_backup_retention,
+            diff_package_states,
+            export_state_diff,
+            get_manifest_path,
+            read_manifest,
+            write_manifest,
+            create_manifest_from_system,
+            plan_manifest,
+            apply_manifest,
             list_pacman_hooks,
             export_packages,
             // Updater commands
@@ -0,0 +1,632 @@
+use crate::backup::{
+    configured_repositories, pacman_conf_values, removable_packages, run_aur_helper, run_pacman,
+    validate_package_name, KeptPackage, RestoreStepResult,
+};
+use crate::pacman::graph::package_graph;
+use crate::pacman::{package_index, refresh_package_index};
+use crate::utils::write_system_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PACMAN_CONF: &str = "/etc/pacman.conf";
+
+/// Repositories every Arch system has; generated manifests leave them out
+const STANDARD_REPOSITORIES: &[&str] = &[
+    "core",
+    "extra",
+    "multilib",
+    "core-testing",
+    "extra-testing",
+    "multilib-testing",
+];
+
+/// Desired package state of a machine, stored as TOML:
+///
+/// ```toml
+/// packages = ["git", "neovim"]
+/// groups = ["base-devel"]
+/// aur = ["paru-bin"]
+/// ignore = ["linux"]
+/// prune = false
+///
+/// [[repositories]]
+/// name = "chaotic-aur"
+/// include = "/etc/pacman.d/chaotic-mirrorlist"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Manifest {
+    /// Repository packages installed explicitly
+    pub packages: Vec<String>,
+    /// Groups whose members are all installed
+    pub groups: Vec<String>,
+    /// AUR packages installed explicitly
+    pub aur: Vec<String>,
+    /// `IgnorePkg` in pacman.conf; left alone when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+    /// Remove explicitly installed packages the manifest does not list
+    pub prune: bool,
+    /// Repositories added to pacman.conf when missing. Existing ones are never removed.
+    pub repositories: Vec<ManifestRepository>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ManifestRepository {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<String>,
+    /// Mirrorlist file, instead of or next to `servers`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig_level: Option<String>,
+}
+
+/// What converging the system to a manifest would change
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ManifestPlan {
+    /// Repository packages to install, including missing group members
+    pub install: Vec<String>,
+    pub install_aur: Vec<String>,
+    /// Listed packages installed as dependencies
+    pub mark_explicit: Vec<String>,
+    /// Unlisted explicit packages removed when pruning
+    pub remove: Vec<String>,
+    /// Unlisted explicit packages that stay: protected, or needed by packages that stay
+    pub kept: Vec<KeptPackage>,
+    pub add_repositories: Vec<String>,
+    /// New `IgnorePkg` list, when it differs from pacman.conf
+    pub ignore: Option<Vec<String>>,
+    /// Packages and groups found in no configured repository
+    pub unknown: Vec<String>,
+}
+
+impl ManifestPlan {
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty()
+            && self.install_aur.is_empty()
+            && self.mark_explicit.is_empty()
+            && self.remove.is_empty()
+            && self.add_repositories.is_empty()
+            && self.ignore.is_none()
+    }
+}
+
+/// `~/.config/guiman/manifest.toml`
+pub fn default_manifest_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("guiman").join("manifest.toml"))
+        .ok_or_else(|| "Failed to get config directory".to_string())
+}
+
+pub fn parse_manifest(content: &str) -> Result<Manifest, String> {
+    toml::from_str(content).map_err(|e| format!("Failed to parse manifest: {}", e))
+}
+
+pub fn render_manifest(manifest: &Manifest) -> Result<String, String> {
+    toml::to_string_pretty(manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))
+}
+
+pub fn load_manifest(path: &Path) -> Result<Manifest, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+    parse_manifest(&content)
+}
+
+pub fn save_manifest(path: &Path, manifest: &Manifest) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create manifest directory: {}", e))?;
+    }
+    fs::write(path, render_manifest(manifest)?)
+        .map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+/// Manifest describing the running system. Foreign packages are assumed to come from the AUR.
+pub fn current_manifest() -> Result<Manifest, String> {
+    let index = package_index()?;
+    let mut manifest = Manifest {
+        ignore: Some(pacman_conf_values("IgnorePkg")),
+        ..Manifest::default()
+    };
+
+    for record in index.packages() {
+        if record.install_reason.as_deref() != Some("explicit") {
+            continue;
+        }
+        if record.repo == "local" {
+            manifest.aur.push(record.name.clone());
+        } else {
+            manifest.packages.push(record.name.clone());
+        }
+    }
+    manifest.packages.sort();
+    manifest.aur.sort();
+
+    manifest.repositories = configured_repositories()
+        .into_iter()
+        .filter(|repo| !STANDARD_REPOSITORIES.contains(&repo.name.as_str()))
+        .map(|repo| ManifestRepository {
+            name: repo.name,
+            servers: repo.servers,
+            include: None,
+            sig_level: (!repo.sig_level.is_empty()).then(|| repo.sig_level.join(" ")),
+        })
+        .collect();
+
+    Ok(manifest)
+}
+
+/// Compare a manifest with the installed system
+pub fn plan_manifest(manifest: &Manifest) -> Result<ManifestPlan, String> {
+    validate_manifest(manifest)?;
+    let index = package_index()?;
+    let graph = package_graph()?;
+    let installed = |name: &str| graph.get(name).filter(|p| p.installed);
+
+    let mut plan = ManifestPlan {
+        install: Vec::new(),
+        install_aur: Vec::new(),
+        mark_explicit: Vec::new(),
+        remove: Vec::new(),
+        kept: Vec::new(),
+        add_repositories: Vec::new(),
+        ignore: None,
+        unknown: Vec::new(),
+    };
+
+    let mut wanted: HashSet<&str> = HashSet::new();
+    let mut install: BTreeSet<&str> = BTreeSet::new();
+
+    for name in &manifest.packages {
+        wanted.insert(name);
+        match installed(name) {
+            Some(package) if !package.explicit => plan.mark_explicit.push(name.clone()),
+            Some(_) => {}
+            None if index.get(name).is_some_and(|r| r.repo != "local") => {
+                install.insert(name);
+            }
+            None => plan.unknown.push(name.clone()),
+        }
+    }
+
+    for group in &manifest.groups {
+        let members: Vec<&str> = index
+            .packages()
+            .iter()
+            .filter(|r| r.repo != "local" && r.groups.contains(group))
+            .map(|r| r.name.as_str())
+            .collect();
+        if members.is_empty() {
+            plan.unknown.push(group.clone());
+        }
+        for member in members {
+            wanted.insert(member);
+            if installed(member).is_none() {
+                install.insert(member);
+            }
+        }
+    }
+    plan.install = install.into_iter().map(str::to_string).collect();
+
+    for name in &manifest.aur {
+        wanted.insert(name);
+        match installed(name) {
+            Some(package) if !package.explicit => plan.mark_explicit.push(name.clone()),
+            Some(_) => {}
+            None => plan.install_aur.push(name.clone()),
+        }
+    }
+
+    if manifest.prune {
+        let (remove, kept) = removable_packages(&graph, &wanted, true);
+        plan.remove = remove;
+        plan.kept = kept;
+    }
+
+    let configured: HashSet<String> = configured_repositories()
+        .into_iter()
+        .map(|repo| repo.name)
+        .collect();
+    plan.add_repositories = manifest
+        .repositories
+        .iter()
+        .filter(|repo| !configured.contains(&repo.name))
+        .map(|repo| repo.name.clone())
+        .collect();
+
+    if let Some(ignore) = &manifest.ignore {
+        let current: BTreeSet<String> = pacman_conf_values("IgnorePkg").into_iter().collect();
+        let desired: BTreeSet<String> = ignore.iter().cloned().collect();
+        if current != desired {
+            plan.ignore = Some(desired.into_iter().collect());
+        }
+    }
+
+    plan.mark_explicit.sort();
+    Ok(plan)
+}
+
+/// Converge the system to a manifest following the `reviewed` plan. pacman.conf
+/// changes come first so new repositories can provide packages; removals come
+/// last. Refuses to run when the manifest or the system changed since the review.
+pub fn apply_manifest(
+    manifest: &Manifest,
+    reviewed: &ManifestPlan,
+    aur_helper: Option<&str>,
+) -> Vec<RestoreStepResult> {
+    let mut results = Vec::new();
+    let mut plan = match plan_manifest(manifest) {
+        Ok(plan) if plan == *reviewed => plan,
+        Ok(_) => {
+            return vec![failed_step(
+                "Plan",
+                "The manifest or the system changed since the preview. Review the new plan and apply again."
+                    .to_string(),
+            )]
+        }
+        Err(e) => return vec![failed_step("Plan", e)],
+    };
+    if plan.is_empty() && plan.unknown.is_empty() {
+        return vec![RestoreStepResult {
+            step: "Apply manifest".to_string(),
+            success: true,
+            output: "The system already matches the manifest".to_string(),
+        }];
+    }
+
+    if !plan.add_repositories.is_empty() || plan.ignore.is_some() {
+        let step = "Update pacman.conf";
+        if let Err(e) = update_pacman_conf(manifest, &plan) {
+            // Installing without the new repositories or ignore list is not what was reviewed
+            results.push(failed_step(step, e));
+            return results;
+        }
+        results.push(RestoreStepResult {
+            step: step.to_string(),
+            success: true,
+            output: String::new(),
+        });
+
+        if !plan.add_repositories.is_empty() {
+            // Installing from a freshly synced database without upgrading is a partial upgrade
+            let sync = run_pacman("Sync databases and upgrade", &["-Syu", "--noconfirm"], &[]);
+            let synced = sync.success;
+            results.push(sync);
+            if !synced {
+                return results;
+            }
+            let _ = refresh_package_index();
+            match plan_manifest(manifest) {
+                Ok(mut updated) => {
+                    // New repositories resolve packages that were unknown, but
+                    // nothing is removed that the preview did not show
+                    updated.remove.retain(|name| reviewed.remove.contains(name));
+                    plan = updated;
+                }
+                Err(e) => {
+                    results.push(failed_step("Plan", e));
+                    return results;
+                }
+            }
+        }
+    }
+
+    if !plan.unknown.is_empty() {
+        results.push(failed_step(
+            "Resolve packages",
+            format!("Not in any repository: {}", plan.unknown.join(", ")),
+        ));
+    }
+
+    if !plan.install.is_empty() {
+        let names: Vec<&str> = plan.install.iter().map(String::as_str).collect();
+        results.push(run_pacman(
+            "Install packages",
+            &["-S", "--needed", "--noconfirm"],
+            &names,
+        ));
+    }
+    if !plan.install_aur.is_empty() {
+        let names: Vec<&str> = plan.install_aur.iter().map(String::as_str).collect();
+        results.push(run_aur_helper(aur_helper, &names, false));
+    }
+    if !plan.mark_explicit.is_empty() {
+        let names: Vec<&str> = plan.mark_explicit.iter().map(String::as_str).collect();
+        results.push(run_pacman(
+            "Mark as explicit",
+            &["-D", "--asexplicit"],
+            &names,
+        ));
+    }
+    if !plan.remove.is_empty() {
+        let names: Vec<&str> = plan.remove.iter().map(String::as_str).collect();
+        results.push(run_pacman(
+            "Remove unlisted packages",
+            &["-R", "--noconfirm"],
+            &names,
+        ));
+    }
+    results
+}
+
+// Helper functions
+
+fn failed_step(step: &str, output: String) -> RestoreStepResult {
+    RestoreStepResult {
+        step: step.to_string(),
+        success: false,
+        output,
+    }
+}
+
+/// Rewrite pacman.conf with the plan's repositories and `IgnorePkg` (requires root)
+fn update_pacman_conf(manifest: &Manifest, plan: &ManifestPlan) -> Result<(), String> {
+    let mut content = fs::read_to_string(PACMAN_CONF)
+        .map_err(|e| format!("Failed to read pacman.conf: {}", e))?;
+
+    if let Some(ignore) = &plan.ignore {
+        content = set_ignore_pkg(&content, ignore);
+    }
+    for repo in manifest
+        .repositories
+        .iter()
+        .filter(|repo| plan.add_repositories.contains(&repo.name))
+    {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&repository_section(repo));
+    }
+
+    write_system_file(PACMAN_CONF, &content)
+}
+
+/// Manifest values end up in pacman.conf and on root command lines, so
+/// anything that could add lines, sections or options is refused
+fn validate_manifest(manifest: &Manifest) -> Result<(), String> {
+    for name in manifest.packages.iter().chain(&manifest.aur) {
+        validate_package_name(name)?;
+    }
+    for value in manifest.ignore.iter().flatten() {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            return Err(format!("Invalid IgnorePkg entry: {:?}", value));
+        }
+        validate_conf_value("IgnorePkg", value)?;
+    }
+
+    for repo in &manifest.repositories {
+        let valid_name = !repo.name.is_empty()
+            && repo
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+        if !valid_name {
+            return Err(format!("Invalid repository name: {:?}", repo.name));
+        }
+        if repo.servers.is_empty() && repo.include.is_none() {
+            return Err(format!(
+                "Repository [{}] has no server or include",
+                repo.name
+            ));
+        }
+        for server in &repo.servers {
+            validate_conf_value("Server", server)?;
+        }
+        if let Some(include) = &repo.include {
+            validate_conf_value("Include", include)?;
+        }
+        if let Some(sig_level) = &repo.sig_level {
+            validate_conf_value("SigLevel", sig_level)?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_conf_value(key: &str, value: &str) -> Result<(), String> {
+    if value.contains(['\n', '\r', '[', ']']) {
+        return Err(format!("Invalid {} value: {:?}", key, value));
+    }
+    Ok(())
+}
+
+fn repository_section(repo: &ManifestRepository) -> String {
+    let mut section = format!("\n[{}]\n", repo.name);
+    if let Some(sig_level) = &repo.sig_level {
+        section.push_str(&format!("SigLevel = {}\n", sig_level));
+    }
+    for server in &repo.servers {
+        section.push_str(&format!("Server = {}\n", server));
+    }
+    if let Some(include) = &repo.include {
+        section.push_str(&format!("Include = {}\n", include));
+    }
+    section
+}
+
+/// Replace the `IgnorePkg` lines of the `[options]` section with a single one
+fn set_ignore_pkg(content: &str, packages: &[String]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_options = false;
+
+    for current in content.lines() {
+        let trimmed = current.trim();
+        if trimmed.starts_with('[') {
+            in_options = trimmed == "[options]";
+            lines.push(current.to_string());
+            if in_options && !packages.is_empty() {
+                lines.push(format!("IgnorePkg = {}", packages.join(" ")));
+            }
+            continue;
+        }
+        let is_ignore = trimmed
+            .split_once('=')
+            .is_some_and(|(key, _)| key.trim() == "IgnorePkg");
+        if !(in_options && is_ignore) {
+            lines.push(current.to_string());
+        }
+    }
+
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_repository(repo: ManifestRepository) -> Manifest {
+        Manifest {
+            repositories: vec![repo],
+            ..Manifest::default()
+        }
+    }
+
+    #[test]
+    fn rejects_values_that_inject_into_pacman_conf() {
+        let repo = ManifestRepository {
+            name: "custom".to_string(),
+            servers: vec!["https://example.org/$repo/$arch".to_string()],
+            include: None,
+            sig_level: Some("Optional TrustAll".to_string()),
+        };
+        assert!(validate_manifest(&with_repository(repo.clone())).is_ok());
+
+        let invalid = [
+            ManifestRepository {
+                name: "options]\n[evil".to_string(),
+                ..repo.clone()
+            },
+            ManifestRepository {
+                name: "custom repo".to_string(),
+                ..repo.clone()
+            },
+            ManifestRepository {
+                servers: vec!["https://example.org\n[options]\nHookDir = /tmp".to_string()],
+                ..repo.clone()
+            },
+            ManifestRepository {
+                include: Some("/etc/pacman.d/mirrorlist\rXferCommand = /tmp/x".to_string()),
+                ..repo.clone()
+            },
+            ManifestRepository {
+                sig_level: Some("Never]".to_string()),
+                ..repo.clone()
+            },
+        ];
+        for repo in invalid {
+            assert!(validate_manifest(&with_repository(repo)).is_err());
+        }
+
+        let ignore = Manifest {
+            ignore: Some(vec!["linux\nHookDir = /tmp".to_string()]),
+            ..Manifest::default()
+        };
+        assert!(validate_manifest(&ignore).is_err());
+
+        let option = Manifest {
+            packages: vec!["--hookdir=/tmp".to_string()],
+            ..Manifest::default()
+        };
+        assert!(validate_manifest(&option).is_err());
+    }
+
+    #[test]
+    fn requires_a_server_or_include() {
+        let repo = ManifestRepository {
+            name: "custom".to_string(),
+            ..ManifestRepository::default()
+        };
+        assert!(validate_manifest(&with_repository(repo)).is_err());
+    }
+
+    #[test]
+    fn replaces_ignore_pkg_only_in_options() {
+        let content = "\
+# General options
+[options]
+HoldPkg     = pacman glibc
+IgnorePkg   = linux
+#IgnorePkg  = mesa
+IgnorePkg=nvidia nvidia-utils
+Architecture = auto
+
+[core]
+IgnorePkg = not-an-option-here
+Include = /etc/pacman.d/mirrorlist
+";
+        let expected = "\
+# General options
+[options]
+IgnorePkg = firefox linux-lts
+HoldPkg     = pacman glibc
+#IgnorePkg  = mesa
+Architecture = auto
+
+[core]
+IgnorePkg = not-an-option-here
+Include = /etc/pacman.d/mirrorlist
+";
+        let packages = ["firefox".to_string(), "linux-lts".to_string()];
+        assert_eq!(set_ignore_pkg(content, &packages), expected);
+
+        let cleared = set_ignore_pkg(content, &[]);
+        assert!(!cleared
+            .lines()
+            .take_while(|line| *line != "[core]")
+            .any(|line| line.starts_with("IgnorePkg")));
+        assert!(cleared.contains("[core]\nIgnorePkg = not-an-option-here\n"));
+    }
+
+    #[test]
+    fn renders_repository_sections() {
+        let repo = ManifestRepository {
+            name: "chaotic-aur".to_string(),
+            servers: vec!["https://cdn.example.org/$repo/$arch".to_string()],
+            include: Some("/etc/pacman.d/chaotic-mirrorlist".to_string()),
+            sig_level: Some("Required DatabaseOptional".to_string()),
+        };
+        assert_eq!(
+            repository_section(&repo),
+            "\n[chaotic-aur]\nSigLevel = Required DatabaseOptional\n\
+             Server = https://cdn.example.org/$repo/$arch\n\
+             Include = /etc/pacman.d/chaotic-mirrorlist\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_manifest_toml() {
+        let manifest = parse_manifest(
+            r#"
+packages = ["git", "neovim"]
+groups = ["base-devel"]
+aur = ["paru-bin"]
+prune = true
+
+[[repositories]]
+name = "chaotic-aur"
+include = "/etc/pacman.d/chaotic-mirrorlist"
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.packages, ["git", "neovim"]);
+        assert_eq!(manifest.ignore, None);
+        assert!(manifest.prune);
+        assert_eq!(manifest.repositories[0].servers, Vec::<String>::new());
+
+        let rendered = render_manifest(&manifest).unwrap();
+        assert!(!rendered.contains("ignore"));
+        assert!(!rendered.contains("servers"));
+        let reparsed = parse_manifest(&rendered).unwrap();
+        assert_eq!(reparsed.aur, manifest.aur);
+        assert_eq!(
+            reparsed.repositories[0].include,
+            manifest.repositories[0].include
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use crate::models::PackageInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Full metadata of a package as reported by `pacman -Si` / `pacman -Qi`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PackageRecord {
+    pub name: String,
+    pub version: String,
+    /// Sync repository, or `local` for packages not found in any repository
+    pub repo: String,
+    pub description: String,
+    pub architecture: String,
+    pub url: String,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub provides: Vec<String>,
+    pub depends: Vec<String>,
+    pub optional_depends: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+    pub download_size: u64,
+    pub installed_size: u64,
+    pub packager: String,
+    pub build_date: String,
+    pub installed: bool,
+    pub installed_version: Option<String>,
+    /// `explicit` or `dependency` for installed packages
+    pub install_reason: Option<String>,
+}
+
+impl PackageRecord {
+    /// Summary used by the package lists
+    pub fn to_package_info(&self) -> PackageInfo {
+        PackageInfo {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            repo: self.repo.clone(),
+            description: self.description.clone(),
+            installed: self.installed,
+        }
+    }
+}
+
+/// Combine sync records with installed state from `pacman -Qi` records.
+///
+/// Installed packages not found in any repository are appended as `local`.
+pub fn merge_records(
+    mut packages: Vec<PackageRecord>,
+    local: Vec<PackageRecord>,
+) -> Vec<PackageRecord> {
+    let mut local_by_name: HashMap<String, PackageRecord> = local
+        .into_iter()
+        .map(|record| (record.name.clone(), record))
+        .collect();
+
+    for package in &mut packages {
+        if let Some(local) = local_by_name.remove(&package.name) {
+            package.installed = true;
+            package.installed_version = Some(local.version);
+            package.install_reason = local.install_reason;
+        }
+    }
+
+    // Whatever is left was installed from outside the sync repositories
+    let mut foreign: Vec<PackageRecord> = local_by_name.into_values().collect();
+    foreign.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.extend(foreign);
+
+    packages
+}
+
+/// Parse `pacman -Si` output for all sync packages
+pub fn load_sync_records() -> Result<Vec<PackageRecord>, String> {
+    let stdout = run_pacman(&["-Si"])?;
+    Ok(parse_package_records(&stdout))
+}
+
+/// Parse `pacman -Qi` output for all installed packages
+pub fn load_local_records() -> Result<Vec<PackageRecord>, String> {
+    let stdout = run_pacman(&["-Qi"])?;
+    let mut records = parse_package_records(&stdout);
+    for record in &mut records {
+        record.repo = "local".to_string();
+        record.installed = true;
+        record.installed_version = Some(record.version.clone());
+    }
+    Ok(records)
+}
+
+/// Parse the `Key : Value` blocks printed by `pacman -Si` and `pacman -Qi`
+pub fn parse_package_records(output: &str) -> Vec<PackageRecord> {
+    let mut records = Vec::new();
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                records.push(record_from_fields(&fields));
+                fields.clear();
+            }
+            continue;
+        }
+
+        // Multi-line values (Optional Deps) continue on indented lines
+        if line.starts_with(' ') {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_string(), vec![value.trim().to_string()]));
+        }
+    }
+
+    if !fields.is_empty() {
+        records.push(record_from_fields(&fields));
+    }
+
+    records
+}
+
+/// Convert sizes like `12.34 MiB` to bytes
+pub fn parse_size(value: &str) -> u64 {
+    let mut parts = value.split_whitespace();
+    let number: f64 = parts.next().and_then(|n| n.parse().ok()).unwrap_or(0.0);
+
+    let multiplier = match parts.next().unwrap_or("B") {
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+
+    (number * multiplier).max(0.0) as u64
+}
+
+// Helper functions
+
+fn run_pacman(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("/usr/bin/pacman")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| format!("Failed to execute pacman: {}", e))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn record_from_fields(fields: &[(String, Vec<String>)]) -> PackageRecord {
+    let mut record = PackageRecord::default();
+
+    for (key, values) in fields {
+        let first = values.first().map(|v| v.as_str()).unwrap_or("");
+        let value = if first == "None" { "" } else { first };
+
+        match key.as_str() {
+            "Repository" => record.repo = value.to_string(),
+            "Name" => record.name = value.to_string(),
+            "Version" => record.version = value.to_string(),
+            "Description" => record.description = value.to_string(),
+            "Architecture" => record.architecture = value.to_string(),
+            "URL" => record.url = value.to_string(),
+            "Licenses" => record.licenses = split_list(value),
+            "Groups" => record.groups = split_list(value),
+            "Provides" => record.provides = split_list(value),
+            "Depends On" => record.depends = split_list(value),
+            "Optional Deps" => {
+                record.optional_depends = values
+                    .iter()
+                    .filter(|v| v.as_str() != "None" && !v.is_empty())
+                    .cloned()
+                    .collect()
+            }
+            "Conflicts With" => record.conflicts = split_list(value),
+            "Replaces" => record.replaces = split_list(value),
+            "Download Size" => record.download_size = parse_size(value),
+            "Installed Size" => record.installed_size = parse_size(value),
+            "Packager" => record.packager = value.to_string(),
+            "Build Date" => record.build_date = value.to_string(),
+            "Install Reason" => {
+                record.install_reason = Some(if value.starts_with("Explicitly") {
+                    "explicit".to_string()
+                } else {
+                    "dependency".to_string()
+                })
+            }
+            _ => {}
+        }
+    }
+
+    record
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pacman_si_output() {
+        let records = parse_package_records(include_str!("../../tests/fixtures/pacman_si.txt"));
+        assert_eq!(records.len(), 3);
+
+        let vim = &records[0];
+        assert_eq!(vim.repo, "extra");
+        assert_eq!(vim.name, "vim");
+        assert_eq!(vim.version, "9.1.0866-1");
+        assert_eq!(vim.groups, Vec::<String>::new());
+        assert_eq!(
+            vim.provides,
+            ["xxd", "vim-minimal", "vim-python3", "vim-plugin-runtime"]
+        );
+        assert_eq!(
+            vim.optional_depends,
+            [
+                "python: Python language support",
+                "ruby: Ruby language support",
+                "lua: Lua language support"
+            ]
+        );
+        assert_eq!(vim.download_size, 2_022_819);
+        assert_eq!(vim.installed_size, 4_687_134);
+        assert_eq!(vim.packager, "Levente Polyak <anthraxx@archlinux.org>");
+        assert_eq!(vim.install_reason, None);
+
+        let gimp = &records[1];
+        assert_eq!(gimp.groups, ["gnome-extra"]);
+        assert!(gimp.provides.is_empty());
+        assert!(gimp.optional_depends.is_empty());
+    }
+
+    #[test]
+    fn merges_installed_state() {
+        let sync = parse_package_records(include_str!("../../tests/fixtures/pacman_si.txt"));
+        let local = parse_package_records(
+            "Name            : vim\nVersion         : 9.1.0700-1\n\
+             Install Reason  : Explicitly installed\n\n\
+             Name            : yay\nVersion         : 12.3.5-1\n\
+             Install Reason  : Installed as a dependency for another package\n",
+        );
+
+        let merged = merge_records(sync, local);
+        assert_eq!(merged.len(), 4);
+        assert!(merged[0].installed);
+        assert_eq!(merged[0].installed_version.as_deref(), Some("9.1.0700-1"));
+        assert_eq!(merged[0].install_reason.as_deref(), Some("explicit"));
+        assert!(!merged[1].installed);
+        assert_eq!(merged[3].name, "yay");
+        assert_eq!(merged[3].install_reason.as_deref(), Some("dependency"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::pacman::graph::PackageGraph;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper bound on chains returned, packages like glibc have thousands
+const MAX_CHAINS: usize = 10;
+
+/// A path from the queried package up to an explicitly installed one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstallChain {
+    /// The queried package first, the explicitly installed package last
+    pub packages: Vec<String>,
+    /// `optional[i]` is set when `packages[i + 1]` only optionally depends on `packages[i]`
+    pub optional: Vec<bool>,
+    /// Readable form, e.g. `libfoo ← bar ← baz (explicit)`; `⇠` marks optional links
+    pub text: String,
+}
+
+/// Why an installed package is on the system
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstallExplanation {
+    pub package: String,
+    pub explicit: bool,
+    /// Shortest chains from explicitly installed packages, hard dependencies preferred
+    pub chains: Vec<InstallChain>,
+    /// Only optional dependencies of explicit packages keep it installed
+    pub optional_only: bool,
+    /// Installed as a dependency, but no explicit package leads to it
+    pub orphan: bool,
+}
+
+/// Explain why an installed package is present
+pub fn explain_package(graph: &PackageGraph, package: &str) -> Result<InstallExplanation, String> {
+    let target = graph
+        .get(package)
+        .filter(|p| p.installed)
+        .ok_or_else(|| format!("{} is not installed", package))?;
+
+    let mut explanation = InstallExplanation {
+        package: target.name.clone(),
+        explicit: target.explicit,
+        chains: Vec::new(),
+        optional_only: false,
+        orphan: false,
+    };
+    if target.explicit {
+        return Ok(explanation);
+    }
+
+    explanation.chains = shortest_chains(graph, &target.name, false);
+    if explanation.chains.is_empty() {
+        explanation.chains = shortest_chains(graph, &target.name, true);
+        explanation.optional_only = !explanation.chains.is_empty();
+        explanation.orphan = explanation.chains.is_empty();
+    }
+
+    Ok(explanation)
+}
+
+// Helper functions
+
+/// Walk dependents layer by layer until explicitly installed packages show up,
+/// then enumerate the shortest paths back down to the target
+fn shortest_chains(
+    graph: &PackageGraph,
+    target: &str,
+    include_optional: bool,
+) -> Vec<InstallChain> {
+    // For each reached package, the packages one layer closer to the target it depends on
+    let mut links: HashMap<&str, Vec<(&str, bool)>> = HashMap::new();
+    let mut distance: HashMap<&str, usize> = HashMap::from([(target, 0)]);
+    let mut layer = vec![target];
+    let mut depth = 0;
+    let mut explicit = Vec::new();
+
+    while explicit.is_empty() && !layer.is_empty() {
+        let mut next = Vec::new();
+        for &name in &layer {
+            let mut dependents: Vec<(&str, bool)> = graph
+                .required_by(name)
+                .into_iter()
+                .map(|p| (p.name.as_str(), false))
+                .collect();
+            if include_optional {
+                dependents.extend(
+                    graph
+                        .optional_for(name)
+                        .into_iter()
+                        .map(|p| (p.name.as_str(), true)),
+                );
+            }
+
+            for (dependent, optional) in dependents {
+                match distance.get(dependent) {
+                    None => {
+                        distance.insert(dependent, depth + 1);
+                        next.push(dependent);
+                    }
+                    Some(&d) if d == depth + 1 => {}
+                    Some(_) => continue,
+                }
+                links.entry(dependent).or_default().push((name, optional));
+            }
+        }
+
+        depth += 1;
+        explicit = next
+            .iter()
+            .copied()
+            .filter(|name| graph.get(name).is_some_and(|p| p.explicit))
+            .collect();
+        layer = next;
+    }
+
+    explicit.sort_unstable();
+    let mut chains = Vec::new();
+    for root in explicit {
+        collect_paths(&links, root, vec![(root, false)], &mut chains);
+        if chains.len() >= MAX_CHAINS {
+            break;
+        }
+    }
+    chains.truncate(MAX_CHAINS);
+    chains
+}
+
+/// Depth-first enumeration from an explicit package down to the target.
+/// `path` holds each package with whether it is an optional dependency of the previous one.
+fn collect_paths<'a>(
+    links: &HashMap<&'a str, Vec<(&'a str, bool)>>,
+    current: &'a str,
+    path: Vec<(&'a str, bool)>,
+    chains: &mut Vec<InstallChain>,
+) {
+    if chains.len() >= MAX_CHAINS {
+        return;
+    }
+
+    let Some(children) = links.get(current) else {
+        // Reached the target: reverse so it comes first
+        let packages: Vec<String> = path.iter().rev().map(|(n, _)| n.to_string()).collect();
+        let optional: Vec<bool> = path.iter().skip(1).rev().map(|(_, o)| *o).collect();
+        let mut text = packages[0].clone();
+        for (name, optional) in packages.iter().skip(1).zip(&optional) {
+            text.push_str(if *optional { " ⇠ " } else { " ← " });
+            text.push_str(name);
+        }
+        text.push_str(" (explicit)");
+        chains.push(InstallChain {
+            packages,
+            optional,
+            text,
+        });
+        return;
+    };
+
+    for &(child, optional) in children {
+        let mut next = path.clone();
+        next.push((child, optional));
+        collect_paths(links, child, next, chains);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::models::CommandResult;
+use crate::utils::run_streaming;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+use tauri::Emitter;
+use tauri::Window;
+
+const SYNC_DB_DIR: &str = "/var/lib/pacman/sync";
+
+/// File databases older than this are reported as stale
+const MAX_FILES_DB_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Directories searched when looking up a command name
+const BINARY_DIRS: [&str; 4] = ["usr/bin/", "usr/sbin/", "bin/", "sbin/"];
+
+/// How a file search pattern is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSearchMode {
+    /// Full path such as `/usr/bin/rg`
+    Exact,
+    /// File name in any directory
+    Basename,
+    /// POSIX extended regular expression, as pacman interprets it
+    Regex,
+}
+
+impl FileSearchMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "exact" | "path" => Some(FileSearchMode::Exact),
+            "basename" | "name" => Some(FileSearchMode::Basename),
+            "regex" => Some(FileSearchMode::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// A file in the sync file databases
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileMatch {
+    pub path: String,
+    pub package: String,
+    pub repo: String,
+    pub version: String,
+    pub is_directory: bool,
+    pub installed: bool,
+}
+
+/// Freshness of one repository's `.files` database
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileDatabaseInfo {
+    pub repo: String,
+    pub exists: bool,
+    pub last_updated: Option<String>,
+    pub stale: bool,
+}
+
+/// Freshness of all file databases
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileDatabaseStatus {
+    pub databases: Vec<FileDatabaseInfo>,
+    pub available: bool,
+    pub up_to_date: bool,
+}
+
+/// Check whether `pacman -Fy` has been run and is newer than the package databases
+pub fn get_file_database_status() -> FileDatabaseStatus {
+    let mut databases = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(SYNC_DB_DIR) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(repo) = name.strip_suffix(".db") else {
+                continue;
+            };
+
+            let files_path = Path::new(SYNC_DB_DIR).join(format!("{}.files", repo));
+            let files_modified = modified_time(&files_path);
+            let db_modified = modified_time(&entry.path());
+
+            // Stale when the package list moved on without the file list, or it is simply old
+            let stale = match files_modified {
+                Some(files_time) => {
+                    db_modified.is_some_and(|db_time| db_time > files_time)
+                        || files_time
+                            .elapsed()
+                            .map(|age| age > MAX_FILES_DB_AGE)
+                            .unwrap_or(false)
+                }
+                None => true,
+            };
+
+            databases.push(FileDatabaseInfo {
+                repo: repo.to_string(),
+                exists: files_modified.is_some(),
+                last_updated: files_modified
+                    .map(|time| chrono::DateTime::<chrono::Local>::from(time).to_rfc3339()),
+                stale,
+            });
+        }
+    }
+
+    databases.sort_by(|a, b| a.repo.cmp(&b.repo));
+
+    FileDatabaseStatus {
+        available: !databases.is_empty() && databases.iter().all(|db| db.exists),
+        up_to_date: !databases.is_empty() && databases.iter().all(|db| !db.stale),
+        databases,
+    }
+}
+
+/// Download the file databases with `pacman -Fy`
+pub async fn refresh_file_database_async(window: Window) -> Result<CommandResult, String> {
+    tokio::spawn(async move {
+        let success = run_streaming(
+            &window,
+            "files-log",
+            Command::new("/usr/bin/pkexec").args(["/usr/bin/pacman", "-Fy"]),
+        );
+
+        let message = if success {
+            "✓ File databases updated".to_string()
+        } else {
+            "✗ Updating the file databases failed!".to_string()
+        };
+
+        let _ = window.emit(
+            "files-complete",
+            serde_json::json!({
+                "success": success,
+                "message": message
+            }),
+        );
+    });
+
+    Ok(CommandResult::success(
+        "File database update started".to_string(),
+    ))
+}
+
+/// Search the sync file databases, including packages that are not installed
+pub fn search_file_database(
+    pattern: &str,
+    mode: FileSearchMode,
+    limit: usize,
+) -> Result<Vec<FileMatch>, String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if !get_file_database_status().available {
+        return Err("The file database has not been downloaded yet. Refresh it first.".to_string());
+    }
+
+    let mut command = Command::new("/usr/bin/pacman");
+    command.args(["-F", "--machinereadable"]);
+
+    match mode {
+        FileSearchMode::Exact => {
+            // pacman only matches full paths when the target contains a slash
+            if !pattern.contains('/') {
+                return Err(format!("'{}' is not a path", pattern));
+            }
+            command.arg(pattern);
+        }
+        FileSearchMode::Basename => {
+            if pattern.contains('/') {
+                return Err(format!("'{}' is not a file name", pattern));
+            }
+            command.arg(pattern);
+        }
+        FileSearchMode::Regex => {
+            // pacman compiles the pattern itself (POSIX ERE) and reports invalid ones
+            command.arg("--regex").arg(pattern);
+        }
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to search file database: {}", e))?;
+
+    // pacman exits with 1 and no message when nothing matched
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !stderr.trim().is_empty() {
+        return Err(format!("File search failed: {}", stderr.trim()));
+    }
+
+    let installed = installed_packages();
+    let mut matches = parse_machine_readable(&String::from_utf8_lossy(&output.stdout), &installed);
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+/// Which packages ship an executable called `command`, installed ones first
+pub fn find_command_providers(command: &str) -> Result<Vec<FileMatch>, String> {
+    let command = command.trim();
+    if command.is_empty() || command.contains('/') {
+        return Err(format!("'{}' is not a command name", command));
+    }
+
+    let mut providers: Vec<FileMatch> =
+        search_file_database(command, FileSearchMode::Basename, usize::MAX)?
+            .into_iter()
+            .filter(|file| {
+                BINARY_DIRS
+                    .iter()
+                    .any(|dir| file.path == format!("{}{}", dir, command))
+            })
+            .collect();
+
+    providers.sort_by(|a, b| {
+        b.installed
+            .cmp(&a.installed)
+            .then(a.path.len().cmp(&b.path.len()))
+            .then(a.package.cmp(&b.package))
+    });
+
+    Ok(providers)
+}
+
+// Helper functions
+
+/// Parse `repo\0package\0version\0path` lines
+fn parse_machine_readable(output: &str, installed: &HashSet<String>) -> Vec<FileMatch> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\0');
+            let repo = parts.next()?;
+            let package = parts.next()?;
+            let version = parts.next()?;
+            let path = parts.next()?;
+
+            Some(FileMatch {
+                path: path.to_string(),
+                package: package.to_string(),
+                repo: repo.to_string(),
+                version: version.to_string(),
+                is_directory: path.ends_with('/'),
+                installed: installed.contains(package),
+            })
+        })
+        .collect()
+}
+
+fn installed_packages() -> HashSet<String> {
+    Command::new("/usr/bin/pacman")
+        .args(["-Qq"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
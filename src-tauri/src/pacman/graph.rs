@@ -0,0 +1,378 @@
+use crate::pacman::database::{load_local_records, PackageRecord};
+use crate::pacman::index::{package_index, PackageIndex};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+static GRAPH: RwLock<Option<Arc<PackageGraph>>> = RwLock::new(None);
+
+/// Which edges to follow when walking the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From a package to what it depends on
+    Dependencies,
+    /// From a package to the installed packages that depend on it
+    Dependents,
+}
+
+/// A package as seen by the dependency graph.
+///
+/// Installed packages carry their local metadata, which may differ from the
+/// sync database while an update is pending.
+#[derive(Debug, Clone, Default)]
+pub struct GraphPackage {
+    pub name: String,
+    pub version: String,
+    pub repo: String,
+    pub installed: bool,
+    pub explicit: bool,
+    pub installed_size: u64,
+    pub provides: Vec<String>,
+    pub depends: Vec<String>,
+    /// Raw `name: description` entries
+    pub optional_depends: Vec<String>,
+}
+
+/// Resolved dependency edges over every known package
+pub struct PackageGraph {
+    index: Arc<PackageIndex>,
+    packages: Vec<GraphPackage>,
+    by_name: HashMap<String, usize>,
+    providers: HashMap<String, Vec<usize>>,
+    depends: Vec<Vec<usize>>,
+    optional: Vec<Vec<usize>>,
+    required_by: Vec<Vec<usize>>,
+    optional_for: Vec<Vec<usize>>,
+    unresolved: Vec<Vec<String>>,
+}
+
+impl PackageGraph {
+    /// Build the graph from the package index and the local database
+    pub fn build() -> Result<Self, String> {
+        let (index, local) = thread::scope(|scope| {
+            let local = scope.spawn(load_local_records);
+            let index = package_index();
+            let local = local
+                .join()
+                .map_err(|_| "Failed to read the local database".to_string());
+            (index, local)
+        });
+        Ok(Self::from_records(index?, local??))
+    }
+
+    fn from_records(index: Arc<PackageIndex>, local: Vec<PackageRecord>) -> Self {
+        let local: HashMap<&str, &PackageRecord> =
+            local.iter().map(|r| (r.name.as_str(), r)).collect();
+
+        let mut packages = Vec::new();
+        let mut by_name = HashMap::new();
+        for record in index.packages() {
+            if by_name.contains_key(&record.name) {
+                continue;
+            }
+            by_name.insert(record.name.clone(), packages.len());
+            packages.push(graph_package(
+                record,
+                local.get(record.name.as_str()).copied(),
+            ));
+        }
+
+        let mut providers: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, package) in packages.iter().enumerate() {
+            for provided in &package.provides {
+                providers
+                    .entry(dependency_name(provided).to_string())
+                    .or_default()
+                    .push(i);
+            }
+        }
+
+        let mut graph = Self {
+            index,
+            packages,
+            by_name,
+            providers,
+            depends: Vec::new(),
+            optional: Vec::new(),
+            required_by: Vec::new(),
+            optional_for: Vec::new(),
+            unresolved: Vec::new(),
+        };
+        graph.resolve_edges();
+        graph
+    }
+
+    /// Resolve every dependency string to a package, spread over all cores
+    fn resolve_edges(&mut self) {
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
+        let chunk_size = self.packages.len().div_ceil(workers).max(1);
+
+        let resolved: Vec<(Vec<usize>, Vec<usize>, Vec<String>)> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .packages
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let graph = &*self;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|package| graph.resolve_package(package))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                // A lost chunk would silently shift every index after it
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        let mut required_by = vec![Vec::new(); self.packages.len()];
+        let mut optional_for = vec![Vec::new(); self.packages.len()];
+        for (i, (depends, optional, _)) in resolved.iter().enumerate() {
+            // Like pacman, only installed packages count as dependents
+            if !self.packages[i].installed {
+                continue;
+            }
+            for &dependency in depends {
+                required_by[dependency].push(i);
+            }
+            for &dependency in optional {
+                optional_for[dependency].push(i);
+            }
+        }
+
+        let (depends, rest): (Vec<_>, Vec<_>) = resolved
+            .into_iter()
+            .map(|(depends, optional, unresolved)| (depends, (optional, unresolved)))
+            .unzip();
+        let (optional, unresolved) = rest.into_iter().unzip();
+
+        self.depends = depends;
+        self.optional = optional;
+        self.unresolved = unresolved;
+        self.required_by = required_by;
+        self.optional_for = optional_for;
+    }
+
+    fn resolve_package(&self, package: &GraphPackage) -> (Vec<usize>, Vec<usize>, Vec<String>) {
+        let mut depends = Vec::new();
+        let mut unresolved = Vec::new();
+        for dependency in &package.depends {
+            match self.resolve_index(dependency) {
+                Some(i) if !depends.contains(&i) => depends.push(i),
+                Some(_) => {}
+                None => unresolved.push(dependency.clone()),
+            }
+        }
+
+        let mut optional = Vec::new();
+        for entry in &package.optional_depends {
+            if let Some(i) = self.resolve_index(optional_dependency_name(entry)) {
+                if !optional.contains(&i) {
+                    optional.push(i);
+                }
+            }
+        }
+
+        (depends, optional, unresolved)
+    }
+
+    /// Pick the package satisfying a dependency the way pacman does: an
+    /// installed package by name or provides first, then a repository package
+    /// with that name, then the first provider in repository order.
+    fn resolve_index(&self, dependency: &str) -> Option<usize> {
+        let name = dependency_name(dependency);
+        let exact = self.by_name.get(name).copied();
+        let providers = self
+            .providers
+            .get(name)
+            .map(|p| p.as_slice())
+            .unwrap_or(&[]);
+
+        exact
+            .filter(|&i| self.packages[i].installed)
+            .or_else(|| {
+                providers
+                    .iter()
+                    .copied()
+                    .find(|&i| self.packages[i].installed)
+            })
+            .or(exact)
+            .or_else(|| providers.first().copied())
+    }
+
+    /// Every package in the graph
+    pub fn packages(&self) -> &[GraphPackage] {
+        &self.packages
+    }
+
+    /// Look up a package by exact name
+    pub fn get(&self, name: &str) -> Option<&GraphPackage> {
+        self.by_name.get(name).map(|&i| &self.packages[i])
+    }
+
+    /// The package that satisfies a dependency such as `sh` or `python>=3.11`
+    pub fn resolve(&self, dependency: &str) -> Option<&GraphPackage> {
+        self.resolve_index(dependency).map(|i| &self.packages[i])
+    }
+
+    /// Every installed package satisfying a dependency by name or provides
+    pub fn installed_satisfiers(&self, dependency: &str) -> Vec<&GraphPackage> {
+        let name = dependency_name(dependency);
+        let mut satisfiers: Vec<&GraphPackage> = Vec::new();
+        let candidates = self
+            .by_name
+            .get(name)
+            .into_iter()
+            .chain(self.providers.get(name).into_iter().flatten());
+        for &i in candidates {
+            let package = &self.packages[i];
+            if package.installed && !satisfiers.iter().any(|s| s.name == package.name) {
+                satisfiers.push(package);
+            }
+        }
+        satisfiers
+    }
+
+    /// Packages a package depends on, with virtual dependencies resolved
+    pub fn dependencies(&self, name: &str) -> Vec<&GraphPackage> {
+        self.edges(&self.depends, name)
+    }
+
+    /// Packages listed as optional dependencies
+    pub fn optional_dependencies(&self, name: &str) -> Vec<&GraphPackage> {
+        self.edges(&self.optional, name)
+    }
+
+    /// Installed packages that depend on a package
+    pub fn required_by(&self, name: &str) -> Vec<&GraphPackage> {
+        self.edges(&self.required_by, name)
+    }
+
+    /// Installed packages that list a package as an optional dependency
+    pub fn optional_for(&self, name: &str) -> Vec<&GraphPackage> {
+        self.edges(&self.optional_for, name)
+    }
+
+    /// Dependencies no known package satisfies
+    pub fn unresolved(&self, name: &str) -> &[String] {
+        self.by_name
+            .get(name)
+            .map(|&i| self.unresolved[i].as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Breadth-first walk from the roots, returning each reachable package with
+    /// its shortest distance. Roots unknown to the graph are skipped.
+    pub fn closure(
+        &self,
+        roots: &[&str],
+        direction: Direction,
+        max_depth: Option<usize>,
+        include_optional: bool,
+    ) -> Vec<(usize, &GraphPackage)> {
+        let mut levels: Vec<Option<usize>> = vec![None; self.packages.len()];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for root in roots {
+            if let Some(&i) = self.by_name.get(*root) {
+                if levels[i].is_none() {
+                    levels[i] = Some(0);
+                    queue.push_back(i);
+                }
+            }
+        }
+
+        while let Some(i) = queue.pop_front() {
+            let level = levels[i].unwrap_or(0);
+            order.push((level, &self.packages[i]));
+            if max_depth.is_some_and(|max| level >= max) {
+                continue;
+            }
+
+            let next: Box<dyn Iterator<Item = &usize>> = match direction {
+                Direction::Dependencies if include_optional => {
+                    Box::new(self.depends[i].iter().chain(&self.optional[i]))
+                }
+                Direction::Dependencies => Box::new(self.depends[i].iter()),
+                Direction::Dependents if include_optional => {
+                    Box::new(self.required_by[i].iter().chain(&self.optional_for[i]))
+                }
+                Direction::Dependents => Box::new(self.required_by[i].iter()),
+            };
+            for &j in next {
+                if levels[j].is_none() {
+                    levels[j] = Some(level + 1);
+                    queue.push_back(j);
+                }
+            }
+        }
+
+        order
+    }
+
+    fn edges(&self, edges: &[Vec<usize>], name: &str) -> Vec<&GraphPackage> {
+        self.by_name
+            .get(name)
+            .map(|&i| edges[i].iter().map(|&j| &self.packages[j]).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Get the dependency graph, rebuilding it when the package index changed
+pub fn package_graph() -> Result<Arc<PackageGraph>, String> {
+    let index = package_index()?;
+    if let Some(graph) = GRAPH
+        .read()
+        .map_err(|_| "Dependency graph lock poisoned".to_string())?
+        .clone()
+    {
+        if Arc::ptr_eq(&graph.index, &index) {
+            return Ok(graph);
+        }
+    }
+
+    let graph = Arc::new(PackageGraph::build()?);
+    *GRAPH
+        .write()
+        .map_err(|_| "Dependency graph lock poisoned".to_string())? = Some(graph.clone());
+    Ok(graph)
+}
+
+/// Strip a version constraint: `python>=3.11` -> `python`
+pub fn dependency_name(dependency: &str) -> &str {
+    dependency
+        .split(['<', '>', '='])
+        .next()
+        .unwrap_or(dependency)
+        .trim()
+}
+
+/// Package name of an optional dependency entry: `foo: does things [installed]` -> `foo`
+pub fn optional_dependency_name(entry: &str) -> &str {
+    dependency_name(entry.split(": ").next().unwrap_or(entry))
+}
+
+// Helper functions
+
+fn graph_package(record: &PackageRecord, local: Option<&PackageRecord>) -> GraphPackage {
+    let source = local.unwrap_or(record);
+    GraphPackage {
+        name: record.name.clone(),
+        version: source.version.clone(),
+        repo: record.repo.clone(),
+        installed: record.installed,
+        explicit: record.install_reason.as_deref() == Some("explicit"),
+        installed_size: source.installed_size,
+        provides: source.provides.clone(),
+        depends: source.depends.clone(),
+        optional_depends: source.optional_depends.clone(),
+    }
+}
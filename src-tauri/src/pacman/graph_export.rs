@@ -0,0 +1,293 @@
+use crate::pacman::graph::{Direction, GraphPackage, PackageGraph};
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Identifies the JSON layout; bump when fields change meaning
+pub const GRAPH_SCHEMA: &str = "guiman.dependency-graph.v1";
+
+/// Output formats for dependency graph exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    GraphMl,
+    /// The stable [`GraphDocument`] schema
+    Json,
+}
+
+impl GraphFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "dot" | "graphviz" => Some(GraphFormat::Dot),
+            "graphml" => Some(GraphFormat::GraphMl),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// What to include in an export
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub direction: Direction,
+    pub max_depth: Option<usize>,
+    pub include_optional: bool,
+    pub mark_installed: bool,
+    pub mark_explicit: bool,
+}
+
+/// Serializable dependency graph with sorted nodes and edges, so two exports
+/// of the same system produce identical output
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphDocument {
+    pub schema: String,
+    pub root: String,
+    /// `dependencies` or `dependents`
+    pub direction: String,
+    pub max_depth: Option<usize>,
+    pub nodes: Vec<GraphDocumentNode>,
+    pub edges: Vec<GraphDocumentEdge>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphDocumentNode {
+    pub name: String,
+    pub version: String,
+    pub repo: String,
+    /// Distance from the root
+    pub level: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+}
+
+/// `from` depends on `to`, in both directions of the walk
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GraphDocumentEdge {
+    pub from: String,
+    pub to: String,
+    pub optional: bool,
+}
+
+/// Collect the part of the graph reachable from a package
+pub fn graph_document(
+    graph: &PackageGraph,
+    root: &str,
+    options: &ExportOptions,
+) -> Result<GraphDocument, String> {
+    if graph.get(root).is_none() {
+        return Err(format!("Package {} not found", root));
+    }
+
+    let reached = graph.closure(
+        &[root],
+        options.direction,
+        options.max_depth,
+        options.include_optional,
+    );
+    let names: HashSet<&str> = reached.iter().map(|(_, p)| p.name.as_str()).collect();
+
+    let mut edges = Vec::new();
+    for (_, package) in &reached {
+        let mut add = |targets: Vec<&GraphPackage>, optional: bool| {
+            for target in targets {
+                if !names.contains(target.name.as_str()) {
+                    continue;
+                }
+                let (from, to) = match options.direction {
+                    Direction::Dependencies => (&package.name, &target.name),
+                    Direction::Dependents => (&target.name, &package.name),
+                };
+                edges.push(GraphDocumentEdge {
+                    from: from.clone(),
+                    to: to.clone(),
+                    optional,
+                });
+            }
+        };
+
+        match options.direction {
+            Direction::Dependencies => {
+                add(graph.dependencies(&package.name), false);
+                if options.include_optional {
+                    add(graph.optional_dependencies(&package.name), true);
+                }
+            }
+            Direction::Dependents => {
+                add(graph.required_by(&package.name), false);
+                if options.include_optional {
+                    add(graph.optional_for(&package.name), true);
+                }
+            }
+        }
+    }
+    edges.sort();
+    edges.dedup();
+
+    let mut nodes: Vec<GraphDocumentNode> = reached
+        .iter()
+        .map(|(level, package)| GraphDocumentNode {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            repo: package.repo.clone(),
+            level: *level,
+            installed: options.mark_installed.then_some(package.installed),
+            explicit: options.mark_explicit.then_some(package.explicit),
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(GraphDocument {
+        schema: GRAPH_SCHEMA.to_string(),
+        root: root.to_string(),
+        direction: match options.direction {
+            Direction::Dependencies => "dependencies".to_string(),
+            Direction::Dependents => "dependents".to_string(),
+        },
+        max_depth: options.max_depth,
+        nodes,
+        edges,
+    })
+}
+
+/// Export the graph around a package in the requested format
+pub fn export_graph(
+    graph: &PackageGraph,
+    root: &str,
+    format: GraphFormat,
+    options: &ExportOptions,
+) -> Result<String, String> {
+    let document = graph_document(graph, root, options)?;
+
+    match format {
+        GraphFormat::Dot => Ok(render_dot(&document)),
+        GraphFormat::GraphMl => Ok(render_graphml(&document)),
+        GraphFormat::Json => serde_json::to_string_pretty(&document)
+            .map_err(|e| format!("Failed to serialize graph: {}", e)),
+    }
+}
+
+// Helper functions
+
+fn render_dot(document: &GraphDocument) -> String {
+    let mut out = format!("digraph {} {{\n", dot_id(&document.root));
+    out.push_str("  rankdir=LR;\n");
+    out.push_str("  node [shape=box, style=\"rounded,filled\", fillcolor=white];\n");
+
+    for node in &document.nodes {
+        let mut attributes = vec![format!(
+            "label={}",
+            dot_id(&format!("{}\n{}", node.name, node.version))
+        )];
+        if node.name == document.root {
+            attributes.push("peripheries=2".to_string());
+        }
+        if node.installed == Some(true) {
+            attributes.push("fillcolor=palegreen".to_string());
+        }
+        if node.explicit == Some(true) {
+            attributes.push("penwidth=2".to_string());
+        }
+        out.push_str(&format!(
+            "  {} [{}];\n",
+            dot_id(&node.name),
+            attributes.join(", ")
+        ));
+    }
+
+    for edge in &document.edges {
+        let style = if edge.optional { " [style=dashed]" } else { "" };
+        out.push_str(&format!(
+            "  {} -> {}{};\n",
+            dot_id(&edge.from),
+            dot_id(&edge.to),
+            style
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn dot_id(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn render_graphml(document: &GraphDocument) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str(
+        "  <key id=\"version\" for=\"node\" attr.name=\"version\" attr.type=\"string\"/>\n",
+    );
+    out.push_str("  <key id=\"repo\" for=\"node\" attr.name=\"repo\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"level\" for=\"node\" attr.name=\"level\" attr.type=\"int\"/>\n");
+    out.push_str(
+        "  <key id=\"installed\" for=\"node\" attr.name=\"installed\" attr.type=\"boolean\"/>\n",
+    );
+    out.push_str(
+        "  <key id=\"explicit\" for=\"node\" attr.name=\"explicit\" attr.type=\"boolean\"/>\n",
+    );
+    out.push_str(
+        "  <key id=\"optional\" for=\"edge\" attr.name=\"optional\" attr.type=\"boolean\"/>\n",
+    );
+    out.push_str(&format!(
+        "  <graph id=\"{}\" edgedefault=\"directed\">\n",
+        escape(document.root.as_str())
+    ));
+
+    for node in &document.nodes {
+        out.push_str(&format!(
+            "    <node id=\"{}\">\n",
+            escape(node.name.as_str())
+        ));
+        out.push_str(&format!(
+            "      <data key=\"version\">{}</data>\n",
+            escape(node.version.as_str())
+        ));
+        out.push_str(&format!(
+            "      <data key=\"repo\">{}</data>\n",
+            escape(node.repo.as_str())
+        ));
+        out.push_str(&format!(
+            "      <data key=\"level\">{}</data>\n",
+            node.level
+        ));
+        if let Some(installed) = node.installed {
+            out.push_str(&format!(
+                "      <data key=\"installed\">{}</data>\n",
+                installed
+            ));
+        }
+        if let Some(explicit) = node.explicit {
+            out.push_str(&format!(
+                "      <data key=\"explicit\">{}</data>\n",
+                explicit
+            ));
+        }
+        out.push_str("    </node>\n");
+    }
+
+    for edge in &document.edges {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\">\n",
+            escape(edge.from.as_str()),
+            escape(edge.to.as_str())
+        ));
+        out.push_str(&format!(
+            "      <data key=\"optional\">{}</data>\n",
+            edge.optional
+        ));
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
@@ -0,0 +1,143 @@
+use crate::pacman::graph::{optional_dependency_name, GraphPackage, PackageGraph};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// An installed package that would lose hard dependencies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BrokenDependant {
+    pub package: String,
+    /// Dependency strings nothing left on the system satisfies
+    pub missing: Vec<String>,
+}
+
+/// An installed package that would lose optional features
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LostFeature {
+    pub package: String,
+    /// `name: description` entries of the optional dependencies being removed
+    pub features: Vec<String>,
+}
+
+/// What removing a set of packages does to the rest of the system
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemovalImpact {
+    pub packages: Vec<String>,
+    pub broken: Vec<BrokenDependant>,
+    pub lost_features: Vec<LostFeature>,
+    /// Dependencies nothing needs anymore once the packages are gone, transitively
+    pub new_orphans: Vec<String>,
+    /// Bytes freed by removing the packages themselves
+    pub freed_size: u64,
+    /// Bytes freed when the new orphans are removed as well
+    pub freed_with_orphans: u64,
+}
+
+/// Analyse the removal of installed packages, like `pacman -R` would perform it
+pub fn analyze_removal(graph: &PackageGraph, packages: &[String]) -> Result<RemovalImpact, String> {
+    let mut targets: Vec<&GraphPackage> = Vec::new();
+    for name in packages {
+        let package = graph
+            .get(name)
+            .filter(|p| p.installed)
+            .ok_or_else(|| format!("{} is not installed", name))?;
+        if !targets.iter().any(|t| t.name == package.name) {
+            targets.push(package);
+        }
+    }
+    let removed: HashSet<&str> = targets.iter().map(|p| p.name.as_str()).collect();
+
+    let mut broken: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut lost: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for target in &targets {
+        for dependant in graph.required_by(&target.name) {
+            if removed.contains(dependant.name.as_str()) {
+                continue;
+            }
+            for dependency in &dependant.depends {
+                if !still_satisfied(graph, dependency, &removed) {
+                    push_unique(broken.entry(&dependant.name).or_default(), dependency);
+                }
+            }
+        }
+
+        for dependant in graph.optional_for(&target.name) {
+            if removed.contains(dependant.name.as_str()) {
+                continue;
+            }
+            for entry in &dependant.optional_depends {
+                if !still_satisfied(graph, optional_dependency_name(entry), &removed) {
+                    push_unique(lost.entry(&dependant.name).or_default(), entry);
+                }
+            }
+        }
+    }
+
+    let orphans = new_orphans(graph, &removed);
+    let freed_size: u64 = targets.iter().map(|p| p.installed_size).sum();
+    let orphan_size: u64 = orphans.iter().map(|p| p.installed_size).sum();
+
+    Ok(RemovalImpact {
+        packages: targets.iter().map(|p| p.name.clone()).collect(),
+        broken: broken
+            .into_iter()
+            .map(|(package, missing)| BrokenDependant {
+                package: package.to_string(),
+                missing,
+            })
+            .collect(),
+        lost_features: lost
+            .into_iter()
+            .map(|(package, features)| LostFeature {
+                package: package.to_string(),
+                features,
+            })
+            .collect(),
+        new_orphans: orphans.iter().map(|p| p.name.clone()).collect(),
+        freed_size,
+        freed_with_orphans: freed_size + orphan_size,
+    })
+}
+
+// Helper functions
+
+/// Whether a package outside the removed set still satisfies a dependency.
+/// Dependencies that were unsatisfied to begin with are not blamed on the removal.
+fn still_satisfied(graph: &PackageGraph, dependency: &str, removed: &HashSet<&str>) -> bool {
+    let satisfiers = graph.installed_satisfiers(dependency);
+    satisfiers.is_empty()
+        || satisfiers
+            .iter()
+            .any(|p| !removed.contains(p.name.as_str()))
+}
+
+/// Dependency-installed packages that are needed now but not after the removal,
+/// following the chain as each orphan's own dependencies lose their last dependant
+fn new_orphans<'a>(graph: &'a PackageGraph, removed: &HashSet<&str>) -> Vec<&'a GraphPackage> {
+    let mut gone: HashSet<String> = removed.iter().map(|n| n.to_string()).collect();
+    let mut orphans: Vec<&GraphPackage> = Vec::new();
+    let mut candidates: Vec<&GraphPackage> = removed
+        .iter()
+        .flat_map(|name| graph.dependencies(name))
+        .collect();
+
+    while let Some(candidate) = candidates.pop() {
+        if !candidate.installed || candidate.explicit || gone.contains(&candidate.name) {
+            continue;
+        }
+        let dependants = graph.required_by(&candidate.name);
+        if dependants.iter().all(|d| gone.contains(&d.name)) {
+            gone.insert(candidate.name.clone());
+            orphans.push(candidate);
+            candidates.extend(graph.dependencies(&candidate.name));
+        }
+    }
+
+    orphans.sort_by(|a, b| a.name.cmp(&b.name));
+    orphans
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|v| v == value) {
+        list.push(value.to_string());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::catalog::appstream::package_keywords;
+use crate::pacman::database::{
+    load_local_records, load_sync_records, merge_records, PackageRecord,
+};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+const SYNC_DB_DIR: &str = "/var/lib/pacman/sync";
+const LOCAL_DB_DIR: &str = "/var/lib/pacman/local";
+
+static INDEX: RwLock<Option<Arc<PackageIndex>>> = RwLock::new(None);
+
+/// Lowercased text a package can be found by
+#[derive(Debug, Default)]
+pub struct SearchFields {
+    pub name: String,
+    pub description: String,
+    pub provides: Vec<String>,
+    pub groups: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
+impl SearchFields {
+    pub fn new(
+        name: &str,
+        description: &str,
+        provides: &[String],
+        groups: &[String],
+        keywords: &[String],
+    ) -> Self {
+        Self {
+            name: name.to_lowercase(),
+            description: description.to_lowercase(),
+            // Drop version constraints such as `java-runtime=21`
+            provides: provides
+                .iter()
+                .map(|p| p.split(['=', '<', '>']).next().unwrap_or(p).to_lowercase())
+                .collect(),
+            groups: groups.iter().map(|g| g.to_lowercase()).collect(),
+            keywords: keywords.iter().map(|k| k.to_lowercase()).collect(),
+        }
+    }
+}
+
+/// In-memory index of every package in the sync databases
+pub struct PackageIndex {
+    packages: Vec<PackageRecord>,
+    fields: Vec<SearchFields>,
+    by_name: HashMap<String, usize>,
+    /// AppStream keywords by package name
+    keywords: HashMap<String, Vec<String>>,
+    sync_stamp: Option<SystemTime>,
+    local_stamp: Option<SystemTime>,
+}
+
+impl PackageIndex {
+    /// Read the sync and local databases and build the index
+    pub fn build() -> Result<Self, String> {
+        let sync_stamp = sync_databases_stamp();
+        let local_stamp = local_database_stamp();
+        let packages = merge_records(load_sync_records()?, load_local_records()?);
+        // The AppStream catalog is optional, packages are still found by name
+        let keywords = package_keywords().unwrap_or_default();
+        Ok(Self::from_records(
+            packages,
+            keywords,
+            sync_stamp,
+            local_stamp,
+        ))
+    }
+
+    fn from_records(
+        packages: Vec<PackageRecord>,
+        keywords: HashMap<String, Vec<String>>,
+        sync_stamp: Option<SystemTime>,
+        local_stamp: Option<SystemTime>,
+    ) -> Self {
+        let fields = packages
+            .iter()
+            .map(|p| {
+                let package_keywords = keywords.get(&p.name).map(Vec::as_slice).unwrap_or(&[]);
+                SearchFields::new(
+                    &p.name,
+                    &p.description,
+                    &p.provides,
+                    &p.groups,
+                    package_keywords,
+                )
+            })
+            .collect();
+
+        let mut by_name = HashMap::new();
+        for (i, package) in packages.iter().enumerate() {
+            // The first repository in pacman.conf wins, like pacman itself
+            by_name.entry(package.name.clone()).or_insert(i);
+        }
+
+        Self {
+            packages,
+            fields,
+            by_name,
+            keywords,
+            sync_stamp,
+            local_stamp,
+        }
+    }
+
+    /// Re-read installed state only, keeping the sync metadata
+    fn with_local_state(&self, local_stamp: Option<SystemTime>) -> Result<Self, String> {
+        let sync: Vec<PackageRecord> = self
+            .packages
+            .iter()
+            .filter(|p| p.repo != "local")
+            .map(|p| PackageRecord {
+                installed: false,
+                installed_version: None,
+                install_reason: None,
+                ..p.clone()
+            })
+            .collect();
+
+        let packages = merge_records(sync, load_local_records()?);
+        Ok(Self::from_records(
+            packages,
+            self.keywords.clone(),
+            self.sync_stamp,
+            local_stamp,
+        ))
+    }
+
+    /// All indexed packages, including installed ones missing from the repositories
+    pub fn packages(&self) -> &[PackageRecord] {
+        &self.packages
+    }
+
+    /// Look up a package by exact name
+    pub fn get(&self, name: &str) -> Option<&PackageRecord> {
+        self.by_name.get(name).map(|&i| &self.packages[i])
+    }
+
+    /// Rank repository packages against lowercase search terms, best match first.
+    /// A term may be a phrase such as `visual studio`, which has to match as a whole.
+    pub fn search(&self, terms: &[String]) -> Vec<(i64, &PackageRecord)> {
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<(i64, usize)> = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.packages[*i].repo != "local")
+            .filter_map(|(i, fields)| score_fields(terms, fields).map(|score| (score, i)))
+            .collect();
+
+        // Nothing matched literally, fall back to fuzzy name matching for typos
+        if hits.is_empty() {
+            let matcher = SkimMatcherV2::default();
+            let pattern: String = terms.iter().flat_map(|t| t.split_whitespace()).collect();
+            hits = self
+                .fields
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| self.packages[*i].repo != "local")
+                .filter_map(|(i, fields)| {
+                    matcher
+                        .fuzzy_match(&fields.name, &pattern)
+                        .map(|score| (score / 10, i))
+                })
+                .collect();
+        }
+
+        hits.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| {
+                    self.packages[a.1]
+                        .name
+                        .len()
+                        .cmp(&self.packages[b.1].name.len())
+                })
+                .then_with(|| self.packages[a.1].name.cmp(&self.packages[b.1].name))
+        });
+
+        hits.into_iter()
+            .map(|(score, i)| (score, &self.packages[i]))
+            .collect()
+    }
+}
+
+/// Get the package index, building or refreshing it when the databases changed
+pub fn package_index() -> Result<Arc<PackageIndex>, String> {
+    let current = INDEX
+        .read()
+        .map_err(|_| "Package index lock poisoned".to_string())?
+        .clone();
+
+    let index = match current {
+        Some(index) if index.sync_stamp == sync_databases_stamp() => {
+            let local_stamp = local_database_stamp();
+            if index.local_stamp == local_stamp {
+                return Ok(index);
+            }
+            // A package was installed or removed since the index was built
+            index.with_local_state(local_stamp)?
+        }
+        _ => PackageIndex::build()?,
+    };
+
+    store_index(index)
+}
+
+/// Rebuild the package index from scratch
+pub fn refresh_package_index() -> Result<Arc<PackageIndex>, String> {
+    store_index(PackageIndex::build()?)
+}
+
+/// Score a package against every term; `None` unless all terms match somewhere
+pub fn score_fields(terms: &[String], fields: &SearchFields) -> Option<i64> {
+    let mut total = 0;
+
+    for term in terms {
+        let term = term.as_str();
+        let name_score = if fields.name == term {
+            100
+        } else if fields.name.starts_with(term) {
+            60
+        } else if fields.name.contains(term) {
+            40
+        } else {
+            0
+        };
+
+        let provides_score = if fields.provides.iter().any(|p| p == term) {
+            35
+        } else if fields.provides.iter().any(|p| p.contains(term)) {
+            20
+        } else {
+            0
+        };
+
+        let group_score = if fields.groups.iter().any(|g| g == term) {
+            25
+        } else {
+            0
+        };
+
+        let keyword_score = if fields.keywords.iter().any(|k| k == term) {
+            20
+        } else {
+            0
+        };
+
+        let description_score = if fields.description.contains(term) {
+            10
+        } else {
+            0
+        };
+
+        let best = name_score
+            .max(provides_score)
+            .max(group_score)
+            .max(keyword_score)
+            .max(description_score);
+        if best == 0 {
+            return None;
+        }
+
+        // Matches in several fields rank above a single strong match
+        let extra = [
+            provides_score,
+            group_score,
+            keyword_score,
+            description_score,
+        ]
+        .iter()
+        .filter(|&&score| score > 0)
+        .count() as i64;
+        total += best + extra;
+    }
+
+    Some(total)
+}
+
+// Helper functions
+
+fn store_index(index: PackageIndex) -> Result<Arc<PackageIndex>, String> {
+    let index = Arc::new(index);
+    *INDEX
+        .write()
+        .map_err(|_| "Package index lock poisoned".to_string())? = Some(index.clone());
+    Ok(index)
+}
+
+fn sync_databases_stamp() -> Option<SystemTime> {
+    fs::read_dir(SYNC_DB_DIR)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".db"))
+        .filter_map(|entry| entry.metadata().and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+fn local_database_stamp() -> Option<SystemTime> {
+    fs::metadata(LOCAL_DB_DIR)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pacman::database::parse_package_records;
+
+    fn terms(query: &str) -> Vec<String> {
+        query.split_whitespace().map(|t| t.to_lowercase()).collect()
+    }
+
+    fn fixture_index() -> PackageIndex {
+        let sync = parse_package_records(include_str!("../../tests/fixtures/pacman_si.txt"));
+        let local = vec![PackageRecord {
+            name: "yay".to_string(),
+            version: "12.3.5-1".to_string(),
+            repo: "local".to_string(),
+            description: "Yet another yogurt".to_string(),
+            installed: true,
+            ..PackageRecord::default()
+        }];
+        let keywords = HashMap::from([(
+            "gimp".to_string(),
+            vec!["Photoshop".to_string(), "painting".to_string()],
+        )]);
+        PackageIndex::from_records(merge_records(sync, local), keywords, None, None)
+    }
+
+    #[test]
+    fn ranks_names_above_descriptions() {
+        let index = fixture_index();
+        let names: Vec<_> = index
+            .search(&terms("editor"))
+            .into_iter()
+            .map(|(_, p)| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["vim", "gimp"]);
+    }
+
+    #[test]
+    fn finds_packages_by_provides_group_and_keyword() {
+        let index = fixture_index();
+        let first = |query: &str| {
+            index
+                .search(&terms(query))
+                .first()
+                .map(|(_, p)| p.name.clone())
+        };
+
+        assert_eq!(first("java-runtime").as_deref(), Some("jre-openjdk"));
+        assert_eq!(first("gnome-extra").as_deref(), Some("gimp"));
+        assert_eq!(first("photoshop").as_deref(), Some("gimp"));
+        // Every term has to match
+        assert!(index.search(&terms("vim photoshop")).is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_fuzzy_names_and_skips_foreign_packages() {
+        let index = fixture_index();
+        let names: Vec<_> = index
+            .search(&terms("jreopnjdk"))
+            .into_iter()
+            .map(|(_, p)| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["jre-openjdk"]);
+
+        assert!(index.get("yay").is_some());
+        assert!(index.search(&terms("yay")).is_empty());
+    }
+}
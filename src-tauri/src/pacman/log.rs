@@ -0,0 +1,519 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Default location of the pacman log; `GUIMAN_PACMAN_LOG` overrides it
+pub const PACMAN_LOG: &str = "/var/log/pacman.log";
+
+static HISTORY: RwLock<Option<Arc<History>>> = RwLock::new(None);
+
+/// What a transaction did to a package
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogAction {
+    Installed,
+    Removed,
+    Upgraded,
+    Downgraded,
+    Reinstalled,
+}
+
+impl LogAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "installed" | "install" => Some(LogAction::Installed),
+            "removed" | "remove" => Some(LogAction::Removed),
+            "upgraded" | "upgrade" => Some(LogAction::Upgraded),
+            "downgraded" | "downgrade" => Some(LogAction::Downgraded),
+            "reinstalled" | "reinstall" => Some(LogAction::Reinstalled),
+            _ => None,
+        }
+    }
+
+    /// The verb pacman logs, e.g. `upgraded`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogAction::Installed => "installed",
+            LogAction::Removed => "removed",
+            LogAction::Upgraded => "upgraded",
+            LogAction::Downgraded => "downgraded",
+            LogAction::Reinstalled => "reinstalled",
+        }
+    }
+}
+
+/// One package change recorded by ALPM
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LogEntry {
+    /// RFC 3339 timestamp with the offset pacman logged
+    pub timestamp: String,
+    pub action: LogAction,
+    pub package: String,
+    /// Version before the change; absent for installs
+    pub old_version: Option<String>,
+    /// Version after the change; absent for removals
+    pub new_version: Option<String>,
+}
+
+/// Package changes made by one pacman run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Transaction {
+    /// Start timestamp, suffixed with a counter when several start in the same second
+    pub id: String,
+    pub started: String,
+    pub finished: Option<String>,
+    /// Command line from the preceding `[PACMAN] Running '...'` line
+    pub command: Option<String>,
+    /// `completed`, `failed`, `interrupted`, or `unknown` when the log has no end marker
+    pub status: String,
+    pub entries: Vec<LogEntry>,
+}
+
+/// Criteria for [`query_history`]
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub since: Option<DateTime<FixedOffset>>,
+    pub until: Option<DateTime<FixedOffset>>,
+    pub package: Option<String>,
+    pub action: Option<LogAction>,
+}
+
+/// A page of transactions, newest first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryPage {
+    pub transactions: Vec<Transaction>,
+    /// Matching transactions across the whole log
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+struct History {
+    transactions: Arc<Vec<Transaction>>,
+    stamp: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+/// Path of the active pacman log
+pub fn pacman_log_path() -> PathBuf {
+    std::env::var("GUIMAN_PACMAN_LOG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(PACMAN_LOG))
+}
+
+/// Every transaction in the log and its rotated predecessors, oldest first
+pub fn load_transactions() -> Result<Arc<Vec<Transaction>>, String> {
+    Ok(history()?.transactions.clone())
+}
+
+/// Filter and paginate the transaction history, newest first
+pub fn query_history(
+    filter: &HistoryFilter,
+    offset: usize,
+    limit: usize,
+) -> Result<HistoryPage, String> {
+    let history = history()?;
+
+    let matching: Vec<Transaction> = history
+        .transactions
+        .iter()
+        .rev()
+        .filter_map(|transaction| filter_transaction(transaction, filter))
+        .collect();
+
+    Ok(HistoryPage {
+        total: matching.len(),
+        transactions: matching.into_iter().skip(offset).take(limit).collect(),
+        offset,
+        limit,
+    })
+}
+
+/// Parse pacman log text into transactions
+pub fn parse_log(content: &str) -> Vec<Transaction> {
+    let mut transactions: Vec<Transaction> = Vec::new();
+    let mut current: Option<Transaction> = None;
+    let mut pending_command: Option<String> = None;
+    let mut ids: HashMap<String, usize> = HashMap::new();
+
+    let mut open = |started: &str, command: Option<String>| -> Transaction {
+        let count = ids.entry(started.to_string()).or_insert(0);
+        *count += 1;
+        let id = if *count == 1 {
+            started.to_string()
+        } else {
+            format!("{}#{}", started, count)
+        };
+        Transaction {
+            id,
+            started: started.to_string(),
+            finished: None,
+            command,
+            status: "unknown".to_string(),
+            entries: Vec::new(),
+        }
+    };
+
+    for line in content.lines() {
+        let Some((timestamp, source, message)) = split_line(line) else {
+            continue;
+        };
+
+        if source == "PACMAN" {
+            if let Some(command) = message
+                .strip_prefix("Running '")
+                .and_then(|rest| rest.strip_suffix('\''))
+            {
+                pending_command = Some(command.to_string());
+                continue;
+            }
+        }
+
+        // Logs from before pacman 4 record package changes under [PACMAN]
+        if source != "ALPM" && source != "PACMAN" {
+            continue;
+        }
+
+        match message {
+            "transaction started" => {
+                if let Some(done) = current.take() {
+                    transactions.push(done);
+                }
+                current = Some(open(&timestamp, pending_command.take()));
+            }
+            "transaction completed" | "transaction failed" | "transaction interrupted" => {
+                if let Some(mut done) = current.take() {
+                    done.finished = Some(timestamp.clone());
+                    done.status = message.trim_start_matches("transaction ").to_string();
+                    transactions.push(done);
+                }
+            }
+            _ => {
+                let Some(entry) = parse_entry(&timestamp, message) else {
+                    continue;
+                };
+                // Old logs have no transaction markers: one command, one transaction
+                if current.is_none() || pending_command.is_some() {
+                    if let Some(done) = current.take() {
+                        transactions.push(done);
+                    }
+                    current = Some(open(&timestamp, pending_command.take()));
+                }
+                if let Some(transaction) = current.as_mut() {
+                    transaction.entries.push(entry);
+                }
+            }
+        }
+    }
+
+    if let Some(done) = current {
+        transactions.push(done);
+    }
+
+    transactions.retain(|t| !t.entries.is_empty());
+    transactions
+}
+
+/// Parse a log timestamp; pacman before 5.0 logged local time without an offset
+pub fn parse_log_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(parsed) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z") {
+        return Some(parsed);
+    }
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed);
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| local.fixed_offset())
+}
+
+/// Parse an upper bound for the history; a bare date covers the whole day
+pub fn parse_until_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Local
+            .from_local_datetime(&date.and_hms_opt(23, 59, 59)?)
+            .latest()
+            .map(|local| local.fixed_offset()),
+        Err(_) => parse_log_timestamp(value),
+    }
+}
+
+// Helper functions
+
+fn history() -> Result<Arc<History>, String> {
+    let files = log_files();
+    let stamp: Vec<(PathBuf, Option<SystemTime>, u64)> = files
+        .iter()
+        .map(|path| {
+            let meta = fs::metadata(path).ok();
+            (
+                path.clone(),
+                meta.as_ref().and_then(|m| m.modified().ok()),
+                meta.map(|m| m.len()).unwrap_or(0),
+            )
+        })
+        .collect();
+
+    if let Some(history) = HISTORY
+        .read()
+        .map_err(|_| "History lock poisoned".to_string())?
+        .clone()
+    {
+        if history.stamp == stamp {
+            return Ok(history);
+        }
+    }
+
+    if files.is_empty() {
+        return Err(format!(
+            "pacman log not found at {}",
+            pacman_log_path().display()
+        ));
+    }
+
+    let mut content = String::new();
+    for path in &files {
+        content.push_str(&read_log_file(path)?);
+        content.push('\n');
+    }
+
+    let history = Arc::new(History {
+        transactions: Arc::new(parse_log(&content)),
+        stamp,
+    });
+    *HISTORY
+        .write()
+        .map_err(|_| "History lock poisoned".to_string())? = Some(history.clone());
+    Ok(history)
+}
+
+/// The log and its logrotate copies (`pacman.log.1`, `pacman.log.2.gz`, ...), oldest first
+fn log_files() -> Vec<PathBuf> {
+    let path = pacman_log_path();
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy().to_string();
+
+    let mut rotated: Vec<(u32, PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let suffix = file_name.strip_prefix(&format!("{}.", name))?;
+                    let number = suffix.trim_end_matches(".gz").parse::<u32>().ok()?;
+                    Some((number, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    rotated.sort_by_key(|(number, _)| std::cmp::Reverse(*number));
+
+    let mut files: Vec<PathBuf> = rotated.into_iter().map(|(_, path)| path).collect();
+    if path.exists() {
+        files.push(path);
+    }
+    files
+}
+
+fn read_log_file(path: &Path) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let result = if path.extension().is_some_and(|ext| ext == "gz") {
+        GzDecoder::new(file).read_to_end(&mut bytes)
+    } else {
+        let mut file = file;
+        file.read_to_end(&mut bytes)
+    };
+    result.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// `[2025-10-02T14:00:05+0600] [ALPM] installed lsof (4.99.5-2)` ->
+/// (`2025-10-02T14:00:05+06:00`, `ALPM`, `installed lsof (4.99.5-2)`)
+fn split_line(line: &str) -> Option<(String, &str, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let (timestamp, rest) = rest.split_once("] ")?;
+    let timestamp = parse_log_timestamp(timestamp)?;
+
+    // Old logs have no source tag: treat them as pacman messages
+    let (source, message) = match rest.strip_prefix('[').and_then(|r| r.split_once("] ")) {
+        Some((source, message)) => (source, message),
+        None => ("PACMAN", rest),
+    };
+
+    Some((timestamp.to_rfc3339(), source, message.trim()))
+}
+
+fn parse_entry(timestamp: &str, message: &str) -> Option<LogEntry> {
+    let (verb, rest) = message.split_once(' ')?;
+    let action = match verb {
+        "installed" => LogAction::Installed,
+        "removed" => LogAction::Removed,
+        "upgraded" => LogAction::Upgraded,
+        "downgraded" => LogAction::Downgraded,
+        "reinstalled" => LogAction::Reinstalled,
+        _ => return None,
+    };
+
+    let (package, versions) = rest.split_once(" (")?;
+    let versions = versions.strip_suffix(')')?;
+    let (old_version, new_version) = match versions.split_once(" -> ") {
+        Some((old, new)) => (Some(old.to_string()), Some(new.to_string())),
+        None => match action {
+            LogAction::Removed => (Some(versions.to_string()), None),
+            LogAction::Reinstalled => (Some(versions.to_string()), Some(versions.to_string())),
+            _ => (None, Some(versions.to_string())),
+        },
+    };
+
+    Some(LogEntry {
+        timestamp: timestamp.to_string(),
+        action,
+        package: package.to_string(),
+        old_version,
+        new_version,
+    })
+}
+
+/// Apply the filter; package and action filters narrow the entries shown
+fn filter_transaction(transaction: &Transaction, filter: &HistoryFilter) -> Option<Transaction> {
+    let started = DateTime::parse_from_rfc3339(&transaction.started).ok()?;
+    if filter.since.is_some_and(|since| started < since)
+        || filter.until.is_some_and(|until| started > until)
+    {
+        return None;
+    }
+
+    let package = filter.package.as_deref().map(str::to_lowercase);
+    let entries: Vec<LogEntry> = transaction
+        .entries
+        .iter()
+        .filter(|entry| {
+            package
+                .as_deref()
+                .is_none_or(|p| entry.package.to_lowercase().contains(p))
+        })
+        .filter(|entry| filter.action.is_none_or(|action| entry.action == action))
+        .cloned()
+        .collect();
+
+    if entries.is_empty() {
+        return None;
+    }
+    Some(Transaction {
+        entries,
+        ..transaction.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<Transaction> {
+        parse_log(include_str!("../../tests/fixtures/pacman.log"))
+    }
+
+    fn filtered(filter: &HistoryFilter) -> Vec<String> {
+        fixture()
+            .iter()
+            .filter_map(|t| filter_transaction(t, filter))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    #[test]
+    fn groups_entries_into_transactions() {
+        let transactions = fixture();
+        assert_eq!(transactions.len(), 7);
+
+        // Old logs: one transaction per command, without an end marker
+        assert_eq!(transactions[0].command.as_deref(), Some("pacman -S vim"));
+        assert_eq!(transactions[0].status, "unknown");
+        assert_eq!(transactions[1].entries[0].action, LogAction::Removed);
+        assert_eq!(
+            transactions[1].entries[0].old_version.as_deref(),
+            Some("2.2.6-1")
+        );
+
+        let upgrade = &transactions[2];
+        assert_eq!(upgrade.id, "2026-09-30T21:00:05+00:00");
+        assert_eq!(upgrade.command.as_deref(), Some("pacman -Syu"));
+        assert_eq!(upgrade.status, "completed");
+        assert_eq!(
+            upgrade.finished.as_deref(),
+            Some("2026-09-30T21:00:09+00:00")
+        );
+        let actions: Vec<_> = upgrade.entries.iter().map(|e| e.action).collect();
+        assert_eq!(actions, [LogAction::Upgraded, LogAction::Downgraded]);
+        assert_eq!(
+            upgrade.entries[1].old_version.as_deref(),
+            Some("1:25.2.4-1")
+        );
+        assert_eq!(
+            upgrade.entries[1].new_version.as_deref(),
+            Some("1:25.2.3-1")
+        );
+
+        // Transactions starting in the same second get distinct ids
+        assert_eq!(transactions[3].id, "2026-10-01T09:00:00+00:00");
+        assert_eq!(transactions[4].id, "2026-10-01T09:00:00+00:00#2");
+        assert_eq!(transactions[4].entries[0].action, LogAction::Reinstalled);
+
+        assert_eq!(transactions[5].status, "failed");
+
+        // Empty transactions are dropped, unfinished ones kept
+        assert_eq!(
+            transactions[6].command.as_deref(),
+            Some("pacman -S firefox")
+        );
+        assert_eq!(transactions[6].status, "unknown");
+        assert_eq!(transactions[6].finished, None);
+    }
+
+    #[test]
+    fn date_only_until_includes_the_whole_day() {
+        let filter = HistoryFilter {
+            since: parse_log_timestamp("2026-10-01T00:00:00+0000"),
+            until: parse_until_timestamp("2026-10-01"),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered(&filter),
+            ["2026-10-01T09:00:00+00:00", "2026-10-01T09:00:00+00:00#2"]
+        );
+
+        let filter = HistoryFilter {
+            until: parse_until_timestamp("2026-10-01T09:00:00+0000"),
+            ..Default::default()
+        };
+        assert_eq!(filtered(&filter).len(), 5);
+    }
+
+    #[test]
+    fn filters_entries_by_package_and_action() {
+        let filter = HistoryFilter {
+            package: Some("LSOF".to_string()),
+            action: Some(LogAction::Installed),
+            ..Default::default()
+        };
+        assert_eq!(filtered(&filter), ["2026-10-01T09:00:00+00:00"]);
+    }
+}
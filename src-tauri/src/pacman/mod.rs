@@ -1,5 +1,23 @@
+pub mod database;
+pub mod explain;
+pub mod files;
+pub mod graph;
+pub mod graph_export;
+pub mod impact;
+pub mod index;
+pub mod log;
 pub mod operations;
+pub mod optdeps;
 pub mod query;
+pub mod rollback;
+pub mod timeline;
 
+pub use explain::*;
+pub use files::*;
+pub use graph::*;
+pub use graph_export::*;
+pub use impact::*;
+pub use index::*;
 pub use operations::*;
+pub use optdeps::*;
 pub use query::*;
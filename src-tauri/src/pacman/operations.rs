@@ -1,4 +1,7 @@
+use crate::backup;
 use crate::models::CommandResult;
+use crate::settings;
+use crate::snapshot::TransactionSnapshots;
 use serde_json;
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
@@ -14,6 +17,12 @@ pub async fn install_package_async(
     let pkg_clone = package.clone();
 
     tokio::spawn(async move {
+        let snapshots = TransactionSnapshots::begin(
+            &window,
+            "install-log",
+            &format!("pacman -S {}", pkg_clone),
+        );
+
         let mut child = match Command::new("/usr/bin/pkexec")
             .arg("/usr/bin/pacman")
             .args(["-S", "--needed", "--noconfirm", &pkg_clone])
@@ -62,6 +71,8 @@ pub async fn install_package_async(
             }
         };
 
+        snapshots.finish();
+
         let success = result.success();
         let message = if success {
             format!("✓ Installation of {} completed successfully!", pkg_clone)
@@ -92,6 +103,12 @@ pub async fn remove_package_async(
     let pkg_clone = package.clone();
 
     tokio::spawn(async move {
+        let snapshots = TransactionSnapshots::begin(
+            &window,
+            "remove-log",
+            &format!("pacman -Rs {}", pkg_clone),
+        );
+
         let mut child = match Command::new("/usr/bin/pkexec")
             .arg("/usr/bin/pacman")
             .args(["-Rs", "--noconfirm", &pkg_clone])
@@ -140,6 +157,8 @@ pub async fn remove_package_async(
             }
         };
 
+        snapshots.finish();
+
         let success = result.success();
         let message = if success {
             format!("✓ Removal of {} completed successfully!", pkg_clone)
@@ -165,6 +184,15 @@ pub async fn remove_package_async(
 /// Update the system with proper partial upgrade handling
 pub async fn update_system_async(window: Window) -> Result<CommandResult, String> {
     tokio::spawn(async move {
+        if settings::load_settings().backup_before_update {
+            let message = match backup::create_automatic_backup("pre-update").await {
+                Ok(backup) => format!("Backup: saved {}", backup.name),
+                Err(e) => format!("Backup: failed, updating anyway: {}", e),
+            };
+            let _ = window.emit("update-log", message);
+        }
+        let snapshots = TransactionSnapshots::begin(&window, "update-log", "pacman -Syu");
+
         // Use -Syu to avoid partial upgrade issues (sync and upgrade in one command)
         let child = Command::new("/usr/bin/pkexec")
             .arg("/usr/bin/pacman")
@@ -217,6 +245,8 @@ pub async fn update_system_async(window: Window) -> Result<CommandResult, String
                     }
                 };
 
+                snapshots.finish();
+
                 let success = result.success();
                 let exit_code = result.code().unwrap_or(-1);
                 let message = if success {
@@ -0,0 +1,170 @@
+use crate::backup::validate_package_name;
+use crate::models::CommandResult;
+use crate::pacman::graph::{dependency_name, PackageGraph};
+use crate::utils::run_streaming;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::Emitter;
+use tauri::Window;
+
+/// A parsed `Optional Deps` entry such as `foo>=2: does things [installed]`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OptionalDependency {
+    /// Dependency as written, e.g. `foo>=2`
+    pub dependency: String,
+    /// Package name without version constraint
+    pub name: String,
+    pub description: String,
+    pub installed: bool,
+    /// Installed package providing it, when different from `name`
+    pub satisfied_by: Option<String>,
+}
+
+/// A package installed as a dependency that no installed package requires or
+/// lists as optional anymore
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnneededOptionalDependency {
+    pub name: String,
+    pub version: String,
+    pub installed_size: u64,
+}
+
+/// Split an `Optional Deps` entry into its parts, ignoring pacman's status suffix.
+/// The description follows `": "`, so versions with an epoch (`foo>=1:2.0`) stay whole.
+pub fn parse_optional_dependency(entry: &str) -> OptionalDependency {
+    let entry = entry
+        .trim()
+        .trim_end_matches("[installed]")
+        .trim_end_matches("[pending]")
+        .trim();
+    let (dependency, description) = match entry.split_once(": ") {
+        Some((dependency, description)) => (dependency.trim(), description.trim()),
+        None => (entry, ""),
+    };
+
+    OptionalDependency {
+        dependency: dependency.to_string(),
+        name: dependency_name(dependency).to_string(),
+        description: description.to_string(),
+        ..Default::default()
+    }
+}
+
+/// Optional features of a package with whether each one is currently available
+pub fn optional_features(
+    graph: &PackageGraph,
+    package: &str,
+) -> Result<Vec<OptionalDependency>, String> {
+    let record = graph
+        .get(package)
+        .ok_or_else(|| format!("Package {} not found", package))?;
+
+    Ok(record
+        .optional_depends
+        .iter()
+        .map(|entry| {
+            let mut optional = parse_optional_dependency(entry);
+            if let Some(provider) = graph.installed_satisfiers(&optional.dependency).first() {
+                optional.installed = true;
+                if provider.name != optional.name {
+                    optional.satisfied_by = Some(provider.name.clone());
+                }
+            }
+            optional
+        })
+        .collect())
+}
+
+/// Installed dependencies that nothing installed depends on or lists as optional
+/// anymore, the same set as `pacman -Qdt`.
+///
+/// Which package an optional dependency was once installed for is not recorded,
+/// so leftovers of removed packages are reported together with other orphans.
+pub fn unneeded_optional_dependencies(graph: &PackageGraph) -> Vec<UnneededOptionalDependency> {
+    let mut unneeded: Vec<UnneededOptionalDependency> = graph
+        .packages()
+        .iter()
+        .filter(|p| p.installed && !p.explicit)
+        .filter(|p| graph.required_by(&p.name).is_empty() && graph.optional_for(&p.name).is_empty())
+        .map(|p| UnneededOptionalDependency {
+            name: p.name.clone(),
+            version: p.version.clone(),
+            installed_size: p.installed_size,
+        })
+        .collect();
+
+    unneeded.sort_by(|a, b| a.name.cmp(&b.name));
+    unneeded
+}
+
+/// Install optional dependencies marked as dependencies, so they become orphans
+/// once the package that wanted them is removed
+pub async fn install_optional_dependencies_async(
+    window: Window,
+    packages: Vec<String>,
+) -> Result<CommandResult, String> {
+    if packages.is_empty() {
+        return Err("No optional dependencies selected".to_string());
+    }
+    for package in &packages {
+        validate_package_name(package)?;
+    }
+
+    tokio::spawn(async move {
+        let success = run_streaming(
+            &window,
+            "install-log",
+            Command::new("/usr/bin/pkexec")
+                .args([
+                    "/usr/bin/pacman",
+                    "-S",
+                    "--needed",
+                    "--noconfirm",
+                    "--asdeps",
+                    "--",
+                ])
+                .args(&packages),
+        );
+
+        let message = if success {
+            format!("✓ Installed {}", packages.join(", "))
+        } else {
+            "✗ Installing optional dependencies failed!".to_string()
+        };
+
+        let _ = window.emit(
+            "install-complete",
+            serde_json::json!({
+                "success": success,
+                "message": message
+            }),
+        );
+    });
+
+    Ok(CommandResult::success(
+        "Optional dependency installation started".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_optional_dependency_entries() {
+        let optional = parse_optional_dependency("python: Python language support [installed]");
+        assert_eq!(optional.dependency, "python");
+        assert_eq!(optional.name, "python");
+        assert_eq!(optional.description, "Python language support");
+
+        let optional = parse_optional_dependency("foo>=1:2.0: needs epoch 1 [pending]");
+        assert_eq!(optional.dependency, "foo>=1:2.0");
+        assert_eq!(optional.name, "foo");
+        assert_eq!(optional.description, "needs epoch 1");
+
+        let optional = parse_optional_dependency("bar=2:1.5");
+        assert_eq!(optional.dependency, "bar=2:1.5");
+        assert_eq!(optional.name, "bar");
+        assert_eq!(optional.description, "");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::PackageInfo;
+use crate::pacman::log::{load_transactions, LogAction};
 use std::collections::HashMap;
 use std::process::Command;
 
@@ -129,104 +130,39 @@ pub fn list_orphan_packages() -> Result<Vec<String>, String> {
 
 /// Get package history from pacman log
 pub fn get_package_history() -> Result<Vec<PackageInfo>, String> {
-    let output = Command::new("/usr/bin/tail")
-        .args(["-n", "500", "/var/log/pacman.log"])
-        .output()
-        .map_err(|e| format!("Failed to read pacman log: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut history = Vec::new();
-
-    for line in stdout.lines() {
-        if line.contains("[ALPM]")
-            && (line.contains("installed") || line.contains("removed") || line.contains("upgraded"))
-        {
-            if let Some(parsed) = parse_log_entry(line) {
-                history.push(parsed);
-            }
-        }
-    }
-
-    history.reverse();
-    history.truncate(100);
-    Ok(history)
-}
-
-/// Parse a single pacman log entry
-fn parse_log_entry(line: &str) -> Option<PackageInfo> {
-    // Format: [2025-10-02T14:00:05+0600] [ALPM] installed lsof (4.99.5-2)
-    let parts: Vec<&str> = line.split("] [ALPM] ").collect();
-    if parts.len() != 2 {
-        return None;
-    }
-
-    let timestamp = parts[0].trim_start_matches('[').trim();
-    let content = parts[1].trim();
-
-    if content.starts_with("installed ") {
-        // Format: "installed lsof (4.99.5-2)"
-        let rest = content.strip_prefix("installed ").unwrap_or("");
-        if let Some(open_paren) = rest.find(" (") {
-            let name = rest[..open_paren].to_string();
-            let version_part = &rest[open_paren + 2..];
-            let version = version_part.trim_end_matches(')').to_string();
-
-            return Some(PackageInfo {
-                name,
-                version,
-                repo: "log".to_string(),
-                description: format!("📦 Installed on {}", format_timestamp(timestamp)),
-                installed: false,
-            });
-        }
-    } else if content.starts_with("removed ") {
-        // Format: "removed package-name (version)"
-        let rest = content.strip_prefix("removed ").unwrap_or("");
-        if let Some(open_paren) = rest.find(" (") {
-            let name = rest[..open_paren].to_string();
-            let version_part = &rest[open_paren + 2..];
-            let version = version_part.trim_end_matches(')').to_string();
-
-            return Some(PackageInfo {
-                name,
-                version,
-                repo: "log".to_string(),
-                description: format!("🗑️ Removed on {}", format_timestamp(timestamp)),
-                installed: false,
-            });
-        }
-    } else if content.starts_with("upgraded ") {
-        // Format: "upgraded package-name (old-version -> new-version)"
-        let rest = content.strip_prefix("upgraded ").unwrap_or("");
-        if let Some(open_paren) = rest.find(" (") {
-            let name = rest[..open_paren].to_string();
-            let version_part = &rest[open_paren + 2..];
-            let version_info = version_part.trim_end_matches(')');
-
-            // Extract new version (after ->)
-            let version = if let Some(arrow_pos) = version_info.find(" -> ") {
-                version_info[arrow_pos + 4..].to_string()
-            } else {
-                version_info.to_string()
+    let transactions = load_transactions()?;
+
+    Ok(transactions
+        .iter()
+        .rev()
+        .flat_map(|transaction| transaction.entries.iter().rev())
+        .take(100)
+        .map(|entry| {
+            let date = entry
+                .timestamp
+                .split('T')
+                .next()
+                .unwrap_or(&entry.timestamp);
+            let description = match entry.action {
+                LogAction::Installed => format!("📦 Installed on {}", date),
+                LogAction::Removed => format!("🗑️ Removed on {}", date),
+                LogAction::Upgraded => format!("⬆️ Upgraded on {}", date),
+                LogAction::Downgraded => format!("⬇️ Downgraded on {}", date),
+                LogAction::Reinstalled => format!("🔁 Reinstalled on {}", date),
             };
-
-            return Some(PackageInfo {
-                name,
-                version,
+            PackageInfo {
+                name: entry.package.clone(),
+                version: entry
+                    .new_version
+                    .clone()
+                    .or_else(|| entry.old_version.clone())
+                    .unwrap_or_default(),
                 repo: "log".to_string(),
-                description: format!("⬆️ Upgraded on {}", format_timestamp(timestamp)),
+                description,
                 installed: false,
-            });
-        }
-    }
-
-    None
-}
-
-/// Format timestamp for display
-fn format_timestamp(timestamp: &str) -> String {
-    // Convert ISO timestamp to readable format
-    timestamp.split('T').next().unwrap_or(timestamp).to_string()
+            }
+        })
+        .collect())
 }
 
 /// Get detailed package information
@@ -0,0 +1,529 @@
+use crate::models::CommandResult;
+use crate::pacman::log::{self, LogAction, Transaction};
+use crate::utils::run_streaming;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Emitter;
+use tauri::Window;
+
+/// Default package cache; `GUIMAN_PACMAN_CACHE` overrides the directories from `pacman.conf`
+pub const PACMAN_CACHE: &str = "/var/cache/pacman/pkg";
+
+/// What undoing a single log entry does
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RollbackAction {
+    /// Return an upgraded package to its previous version
+    Downgrade,
+    /// Return a downgraded package to its previous version
+    Upgrade,
+    /// Remove a package the transaction installed
+    Remove,
+    /// Install a package the transaction removed
+    Reinstall,
+}
+
+/// One inverse operation of a rollback
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RollbackStep {
+    pub package: String,
+    pub action: RollbackAction,
+    /// Installed version right now, if any
+    pub current_version: Option<String>,
+    /// Version restored by the step, `None` for removals
+    pub target_version: Option<String>,
+    /// Cached package file installed by the step
+    pub cache_file: Option<String>,
+    /// Set when a later transaction changed the package again
+    pub note: Option<String>,
+    /// Mark the reinstalled package as a dependency, because the transaction
+    /// only removed it along with the packages it was run on
+    pub as_dependency: bool,
+}
+
+/// A log entry the rollback leaves alone
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkippedEntry {
+    pub package: String,
+    pub reason: String,
+}
+
+/// A version the inverse needs but the package cache no longer has
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MissingPackage {
+    pub package: String,
+    pub version: String,
+}
+
+/// Preview of undoing a transaction
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RollbackPlan {
+    pub transaction: Transaction,
+    pub steps: Vec<RollbackStep>,
+    pub skipped: Vec<SkippedEntry>,
+    /// Steps that cannot run; they are not part of `steps`
+    pub missing: Vec<MissingPackage>,
+}
+
+impl RollbackPlan {
+    fn cache_files(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter_map(|step| step.cache_file.clone())
+            .collect()
+    }
+
+    fn removals(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|step| step.action == RollbackAction::Remove)
+            .map(|step| step.package.clone())
+            .collect()
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|step| step.as_dependency)
+            .map(|step| step.package.clone())
+            .collect()
+    }
+}
+
+/// Compute the inverse of a logged transaction against the current system
+pub fn plan_rollback(transaction_id: &str) -> Result<RollbackPlan, String> {
+    let transactions = log::load_transactions()?;
+    let transaction = transactions
+        .iter()
+        .find(|t| t.id == transaction_id)
+        .ok_or_else(|| format!("Transaction {} not found in the pacman log", transaction_id))?;
+
+    let installed = installed_versions()?;
+    let cache = cached_packages(&cache_dirs());
+    Ok(plan_transaction_rollback(transaction, &installed, &cache))
+}
+
+/// Compute the inverse of `transaction` given the installed versions and the
+/// package cache.
+///
+/// pacman does not log install reasons. Packages the transaction removed without
+/// naming them on its command line (`-Rs` dependencies) are reinstalled as
+/// dependencies, everything else keeps pacman's default.
+pub fn plan_transaction_rollback(
+    transaction: &Transaction,
+    installed: &HashMap<String, String>,
+    cache: &HashMap<(String, String), PathBuf>,
+) -> RollbackPlan {
+    let targets = command_targets(transaction.command.as_deref());
+    let mut plan = RollbackPlan {
+        transaction: transaction.clone(),
+        steps: Vec::new(),
+        skipped: Vec::new(),
+        missing: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+
+    for entry in &transaction.entries {
+        if !seen.insert(entry.package.as_str()) {
+            continue;
+        }
+        let current = installed.get(&entry.package).cloned();
+        let skip = |reason: &str| SkippedEntry {
+            package: entry.package.clone(),
+            reason: reason.to_string(),
+        };
+
+        let (action, target) = match entry.action {
+            LogAction::Reinstalled => {
+                plan.skipped
+                    .push(skip("Reinstalled in place, nothing to undo"));
+                continue;
+            }
+            LogAction::Installed => {
+                if current.is_none() {
+                    plan.skipped.push(skip("No longer installed"));
+                    continue;
+                }
+                (RollbackAction::Remove, None)
+            }
+            LogAction::Upgraded => (RollbackAction::Downgrade, entry.old_version.clone()),
+            LogAction::Downgraded => (RollbackAction::Upgrade, entry.old_version.clone()),
+            LogAction::Removed => (RollbackAction::Reinstall, entry.old_version.clone()),
+        };
+
+        if let Some(target) = &target {
+            if current.as_ref() == Some(target) {
+                plan.skipped
+                    .push(skip(&format!("Already at version {}", target)));
+                continue;
+            }
+        }
+
+        let expected = match entry.action {
+            LogAction::Removed => None,
+            _ => entry.new_version.as_ref(),
+        };
+        let note = match (&current, expected) {
+            (Some(current), Some(expected)) if current != expected => {
+                Some(format!("Changed to {} by a later transaction", current))
+            }
+            (Some(current), None) => Some(format!("Installed again since, at {}", current)),
+            (None, Some(_)) => Some("Removed by a later transaction".to_string()),
+            _ => None,
+        };
+
+        let cache_file = match &target {
+            Some(version) => match cache.get(&(entry.package.clone(), version.clone())) {
+                Some(path) => Some(path.to_string_lossy().to_string()),
+                None => {
+                    plan.missing.push(MissingPackage {
+                        package: entry.package.clone(),
+                        version: version.clone(),
+                    });
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let as_dependency = action == RollbackAction::Reinstall
+            && current.is_none()
+            && targets
+                .as_ref()
+                .is_some_and(|targets| !targets.contains(entry.package.as_str()));
+
+        plan.steps.push(RollbackStep {
+            package: entry.package.clone(),
+            action,
+            current_version: current,
+            target_version: target,
+            cache_file,
+            note,
+            as_dependency,
+        });
+    }
+
+    plan
+}
+
+/// Undo a transaction following the previewed `plan`: install the previous
+/// versions from the cache, then remove what it installed. Refuses to run a
+/// partial rollback unless asked to, and when the system, the log or the cache
+/// changed since the preview.
+pub async fn execute_rollback_async(
+    window: Window,
+    plan: RollbackPlan,
+    allow_partial: bool,
+) -> Result<CommandResult, String> {
+    let id = plan.transaction.id.clone();
+    let current = tokio::task::spawn_blocking(move || plan_rollback(&id))
+        .await
+        .map_err(|e| format!("Failed to plan rollback: {}", e))??;
+    if current != plan {
+        return Err(
+            "The system or the package cache changed since the preview. Review the new plan and roll back again."
+                .to_string(),
+        );
+    }
+
+    if !plan.missing.is_empty() && !allow_partial {
+        return Err(format!(
+            "Versions no longer in the package cache: {}",
+            describe_missing(&plan.missing)
+        ));
+    }
+    if plan.steps.is_empty() {
+        return Err("Nothing to roll back".to_string());
+    }
+
+    let transaction_id = plan.transaction.id.clone();
+    tokio::spawn(async move {
+        let files = plan.cache_files();
+        let removals = plan.removals();
+        let dependencies = plan.dependencies();
+
+        let mut success = true;
+        if !files.is_empty() {
+            success = run_streaming(
+                &window,
+                "install-log",
+                Command::new("/usr/bin/pkexec")
+                    .args(["/usr/bin/pacman", "-U", "--noconfirm", "--"])
+                    .args(&files),
+            );
+        }
+        if success && !dependencies.is_empty() {
+            success = run_streaming(
+                &window,
+                "install-log",
+                Command::new("/usr/bin/pkexec")
+                    .args(["/usr/bin/pacman", "-D", "--asdeps", "--"])
+                    .args(&dependencies),
+            );
+        }
+        if success && !removals.is_empty() {
+            success = run_streaming(
+                &window,
+                "install-log",
+                Command::new("/usr/bin/pkexec")
+                    .args(["/usr/bin/pacman", "-R", "--noconfirm", "--"])
+                    .args(&removals),
+            );
+        }
+
+        let message = match (success, plan.missing.is_empty()) {
+            (true, true) => format!("✓ Rolled back transaction {}", plan.transaction.id),
+            (true, false) => format!(
+                "✓ Partially rolled back transaction {}; not in cache: {}",
+                plan.transaction.id,
+                describe_missing(&plan.missing)
+            ),
+            (false, _) => format!("✗ Rollback of transaction {} failed!", plan.transaction.id),
+        };
+
+        let _ = window.emit(
+            "install-complete",
+            serde_json::json!({
+                "success": success,
+                "message": message
+            }),
+        );
+    });
+
+    Ok(CommandResult::success(format!(
+        "Rollback of transaction {} started",
+        transaction_id
+    )))
+}
+
+/// Package cache directories, from `pacman-conf` when available
+pub fn cache_dirs() -> Vec<PathBuf> {
+    if let Ok(path) = std::env::var("GUIMAN_PACMAN_CACHE") {
+        return vec![PathBuf::from(path)];
+    }
+
+    let dirs: Vec<PathBuf> = Command::new("/usr/bin/pacman-conf")
+        .arg("CacheDir")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if dirs.is_empty() {
+        vec![PathBuf::from(PACMAN_CACHE)]
+    } else {
+        dirs
+    }
+}
+
+/// Split a cached file name like `foo-1:2.0-1-x86_64.pkg.tar.zst` into name and version
+pub fn parse_cache_file_name(file_name: &str) -> Option<(String, String)> {
+    let (stem, extension) = file_name.split_once(".pkg.tar")?;
+    if extension.ends_with(".sig") || extension.ends_with(".part") {
+        return None;
+    }
+
+    // name-pkgver-pkgrel-arch; package names may contain dashes, the rest cannot
+    let mut parts = stem.rsplitn(4, '-');
+    let _arch = parts.next()?;
+    let pkgrel = parts.next()?;
+    let pkgver = parts.next()?;
+    let name = parts.next()?;
+    if name.is_empty() || pkgver.is_empty() || pkgrel.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), format!("{}-{}", pkgver, pkgrel)))
+}
+
+/// Installed packages with their versions, from `pacman -Q`
+pub fn installed_versions() -> Result<HashMap<String, String>, String> {
+    let output = Command::new("/usr/bin/pacman")
+        .arg("-Q")
+        .output()
+        .map_err(|e| format!("Failed to list installed packages: {}", e))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(name, version)| (name.to_string(), version.trim().to_string()))
+        .collect())
+}
+
+/// Cached package files keyed on (name, version); the first directory wins
+pub fn cached_packages(dirs: &[PathBuf]) -> HashMap<(String, String), PathBuf> {
+    let mut cache = HashMap::new();
+    for dir in dirs {
+        collect_cache_dir(dir, &mut cache);
+    }
+    cache
+}
+
+// Helper functions
+
+fn collect_cache_dir(dir: &Path, cache: &mut HashMap<(String, String), PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        if let Some(key) = parse_cache_file_name(&file_name.to_string_lossy()) {
+            cache.entry(key).or_insert_with(|| entry.path());
+        }
+    }
+}
+
+/// Package arguments of a logged pacman command line, `None` when unknown
+fn command_targets(command: Option<&str>) -> Option<HashSet<&str>> {
+    let mut words = command?.split_whitespace();
+    words.next()?;
+    Some(words.filter(|word| !word.starts_with('-')).collect())
+}
+
+fn describe_missing(missing: &[MissingPackage]) -> String {
+    missing
+        .iter()
+        .map(|m| format!("{} {}", m.package, m.version))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<Transaction> {
+        log::parse_log(include_str!("../../tests/fixtures/pacman_rollback.log"))
+    }
+
+    fn versions(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    fn cache(pairs: &[(&str, &str)]) -> HashMap<(String, String), PathBuf> {
+        pairs
+            .iter()
+            .map(|(name, version)| {
+                let file = format!(
+                    "/var/cache/pacman/pkg/{}-{}-x86_64.pkg.tar.zst",
+                    name, version
+                );
+                ((name.to_string(), version.to_string()), PathBuf::from(file))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_cache_file_names() {
+        assert_eq!(
+            parse_cache_file_name("mesa-1:25.2.3-1-x86_64.pkg.tar.zst"),
+            Some(("mesa".to_string(), "1:25.2.3-1".to_string()))
+        );
+        assert_eq!(
+            parse_cache_file_name("lib32-gcc-libs-15.2.1+r22-1-x86_64.pkg.tar.xz"),
+            Some(("lib32-gcc-libs".to_string(), "15.2.1+r22-1".to_string()))
+        );
+        assert_eq!(
+            parse_cache_file_name("mesa-1:25.2.3-1-x86_64.pkg.tar.zst.sig"),
+            None
+        );
+        assert_eq!(
+            parse_cache_file_name("mesa-1:25.2.3-1-x86_64.pkg.tar.zst.part"),
+            None
+        );
+        assert_eq!(parse_cache_file_name("1.0-1-any.pkg.tar.zst"), None);
+        assert_eq!(parse_cache_file_name("core.db"), None);
+    }
+
+    #[test]
+    fn plans_inverse_of_an_upgrade() {
+        let transactions = fixture();
+        let installed = versions(&[
+            ("linux", "6.16.9.arch1-1"),
+            ("mesa", "1:25.2.4-1"),
+            ("openssl", "3.5.5-1"),
+            ("python", "3.13.7-1"),
+            ("libnew", "1.0-1"),
+            ("bash", "5.3.3-2"),
+        ]);
+        let cache = cache(&[
+            ("linux", "6.16.8.arch1-1"),
+            ("openssl", "3.5.3-1"),
+            ("python", "3.13.8-1"),
+        ]);
+
+        let plan = plan_transaction_rollback(&transactions[0], &installed, &cache);
+
+        let steps: Vec<_> = plan
+            .steps
+            .iter()
+            .map(|s| (s.package.as_str(), s.action, s.target_version.as_deref()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("linux", RollbackAction::Downgrade, Some("6.16.8.arch1-1")),
+                ("openssl", RollbackAction::Downgrade, Some("3.5.3-1")),
+                ("python", RollbackAction::Upgrade, Some("3.13.8-1")),
+                ("libnew", RollbackAction::Remove, None),
+            ]
+        );
+        assert_eq!(
+            plan.steps[1].note.as_deref(),
+            Some("Changed to 3.5.5-1 by a later transaction")
+        );
+        assert_eq!(
+            plan.steps[0].cache_file.as_deref(),
+            Some("/var/cache/pacman/pkg/linux-6.16.8.arch1-1-x86_64.pkg.tar.zst")
+        );
+        assert!(plan.steps.iter().all(|s| !s.as_dependency));
+
+        assert_eq!(
+            plan.missing,
+            [MissingPackage {
+                package: "mesa".to_string(),
+                version: "1:25.2.3-1".to_string()
+            }]
+        );
+        let skipped: Vec<_> = plan.skipped.iter().map(|s| s.package.as_str()).collect();
+        assert_eq!(skipped, ["gone", "bash"]);
+        assert_eq!(plan.removals(), ["libnew"]);
+    }
+
+    #[test]
+    fn reinstalls_removed_dependencies_as_dependencies() {
+        let transactions = fixture();
+        let installed = versions(&[("lm_sensors", "1:3.6.2-1")]);
+        let cache = cache(&[("htop", "3.4.1-1"), ("libnl", "3.11.0-1")]);
+
+        let plan = plan_transaction_rollback(&transactions[1], &installed, &cache);
+
+        let steps: Vec<_> = plan
+            .steps
+            .iter()
+            .map(|s| (s.package.as_str(), s.action, s.as_dependency))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("htop", RollbackAction::Reinstall, false),
+                ("libnl", RollbackAction::Reinstall, true),
+            ]
+        );
+        assert_eq!(plan.skipped[0].package, "lm_sensors");
+        assert_eq!(plan.dependencies(), ["libnl"]);
+        assert_eq!(plan.cache_files().len(), 2);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::pacman::index::package_index;
+use crate::pacman::log::{self, LogAction};
+use crate::settings::{self, default_data_path};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+const LOCAL_DB_DIR: &str = "/var/lib/pacman/local";
+
+/// Packaging clones are fetched again once they are older than this
+const FETCH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// One change to a package recorded in the pacman log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimelineEvent {
+    pub timestamp: String,
+    pub action: LogAction,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    pub transaction_id: String,
+    pub command: Option<String>,
+}
+
+/// A packaging commit between two versions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpstreamCommit {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub subject: String,
+}
+
+/// Packaging commits for one upgrade or downgrade of the timeline
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VersionChange {
+    pub from: String,
+    pub to: String,
+    /// For downgrades these are the commits that were undone
+    pub commits: Vec<UpstreamCommit>,
+    pub error: Option<String>,
+}
+
+/// Everything known about how a package changed on this system
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PackageTimeline {
+    pub package: String,
+    /// Sync repository, `None` for foreign packages
+    pub repo: Option<String>,
+    /// Oldest first
+    pub events: Vec<TimelineEvent>,
+    /// Output of `pacman -Qc`, when the installed package ships a changelog
+    pub changelog: Option<String>,
+    /// Packaging repository the commits come from
+    pub upstream_url: Option<String>,
+    pub changes: Vec<VersionChange>,
+    /// Why the packaging repository could not be read
+    pub upstream_error: Option<String>,
+}
+
+/// Build the timeline of a package, with packaging commits when `include_upstream` is set
+pub fn package_timeline(package: &str, include_upstream: bool) -> Result<PackageTimeline, String> {
+    let transactions = log::load_transactions()?;
+    let events: Vec<TimelineEvent> = transactions
+        .iter()
+        .flat_map(|transaction| {
+            transaction
+                .entries
+                .iter()
+                .filter(|entry| entry.package == package)
+                .map(|entry| TimelineEvent {
+                    timestamp: entry.timestamp.clone(),
+                    action: entry.action,
+                    old_version: entry.old_version.clone(),
+                    new_version: entry.new_version.clone(),
+                    transaction_id: transaction.id.clone(),
+                    command: transaction.command.clone(),
+                })
+        })
+        .collect();
+
+    let index = package_index()?;
+    let repo = index
+        .get(package)
+        .map(|record| record.repo.clone())
+        .filter(|repo| repo != "local");
+
+    let mut timeline = PackageTimeline {
+        package: package.to_string(),
+        repo,
+        changelog: changelog(package),
+        upstream_url: None,
+        changes: Vec::new(),
+        upstream_error: None,
+        events,
+    };
+
+    if include_upstream && timeline.repo.is_some() {
+        let template = settings::load_settings().packaging_git_url;
+        if !template.trim().is_empty() {
+            let url = packaging_url(&template, &package_base(package));
+            match sync_packaging_repo(&url) {
+                Ok(git_dir) => {
+                    timeline.changes = version_changes(&git_dir, &timeline.events);
+                }
+                Err(e) => timeline.upstream_error = Some(e),
+            }
+            timeline.upstream_url = Some(url);
+        }
+    }
+
+    Ok(timeline)
+}
+
+/// Packaging repository URL for a package base. Arch's GitLab renames
+/// projects with `+` in them, e.g. `libc++` becomes `libcplusplus`.
+pub fn packaging_url(template: &str, pkgbase: &str) -> String {
+    let pkgbase = if template.contains("gitlab.archlinux.org") {
+        gitlab_project_name(pkgbase)
+    } else {
+        pkgbase.to_string()
+    };
+    template.replace("{pkgbase}", &pkgbase)
+}
+
+// Helper functions
+
+fn changelog(package: &str) -> Option<String> {
+    let output = Command::new("/usr/bin/pacman")
+        .args(["-Qc", package])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!stdout.is_empty()).then_some(stdout)
+}
+
+/// `%BASE%` from the local database, falling back to the package name
+fn package_base(package: &str) -> String {
+    let Ok(entries) = fs::read_dir(LOCAL_DB_DIR) else {
+        return package.to_string();
+    };
+
+    for entry in entries.flatten() {
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        // Directories are named `name-pkgver-pkgrel`
+        let name = dir_name.rsplitn(3, '-').nth(2);
+        if name != Some(package) {
+            continue;
+        }
+        let Ok(desc) = fs::read_to_string(entry.path().join("desc")) else {
+            break;
+        };
+        let mut lines = desc.lines();
+        while let Some(line) = lines.next() {
+            if line == "%BASE%" {
+                if let Some(base) = lines.next().filter(|b| !b.is_empty()) {
+                    return base.to_string();
+                }
+            }
+        }
+        break;
+    }
+
+    package.to_string()
+}
+
+fn gitlab_project_name(pkgbase: &str) -> String {
+    if pkgbase == "tree" {
+        return "unix-tree".to_string();
+    }
+
+    let chars: Vec<char> = pkgbase.chars().collect();
+    let mut name = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        match c {
+            // `foo+bar` becomes `foo-bar`, any other `+` is spelled out
+            '+' if i > 0
+                && chars[i - 1].is_ascii_alphanumeric()
+                && chars.get(i + 1).is_some_and(|n| n.is_ascii_alphabetic()) =>
+            {
+                name.push('-')
+            }
+            '+' => name.push_str("plus"),
+            c if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') => name.push(*c),
+            _ => name.push('-'),
+        }
+    }
+    name
+}
+
+/// Blobless bare clone of the packaging repository, fetched at most once an hour
+fn sync_packaging_repo(url: &str) -> Result<PathBuf, String> {
+    let cache = PathBuf::from(default_data_path("packaging"));
+    let git_dir = cache.join(format!("{}.git", cache_key(url)));
+
+    if !git_dir.exists() {
+        fs::create_dir_all(&cache)
+            .map_err(|e| format!("Failed to create packaging cache: {}", e))?;
+        run_git(
+            None,
+            &[
+                "clone",
+                "--bare",
+                "--filter=blob:none",
+                "--quiet",
+                url,
+                &git_dir.to_string_lossy(),
+            ],
+        )?;
+        return Ok(git_dir);
+    }
+
+    let stamp = git_dir.join("FETCH_HEAD");
+    let fresh = fs::metadata(&stamp)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < FETCH_INTERVAL);
+    if !fresh {
+        // Offline: the existing clone is better than nothing
+        let _ = run_git(
+            Some(&git_dir),
+            &[
+                "fetch",
+                "--quiet",
+                "--tags",
+                "--force",
+                "--prune",
+                "origin",
+                "+refs/heads/*:refs/heads/*",
+            ],
+        );
+    }
+
+    Ok(git_dir)
+}
+
+fn cache_key(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn version_changes(git_dir: &Path, events: &[TimelineEvent]) -> Vec<VersionChange> {
+    events
+        .iter()
+        .filter(|event| matches!(event.action, LogAction::Upgraded | LogAction::Downgraded))
+        .filter_map(|event| {
+            let from = event.old_version.clone()?;
+            let to = event.new_version.clone()?;
+            let (older, newer) = match event.action {
+                LogAction::Downgraded => (&to, &from),
+                _ => (&from, &to),
+            };
+
+            let mut change = VersionChange {
+                from: from.clone(),
+                to: to.clone(),
+                commits: Vec::new(),
+                error: None,
+            };
+            match commits_between(git_dir, older, newer) {
+                Ok(commits) => change.commits = commits,
+                Err(e) => change.error = Some(e),
+            }
+            Some(change)
+        })
+        .collect()
+}
+
+/// Commits after the `older` release tag up to the `newer` one
+fn commits_between(
+    git_dir: &Path,
+    older: &str,
+    newer: &str,
+) -> Result<Vec<UpstreamCommit>, String> {
+    let older = release_tag(git_dir, older)?;
+    let newer = release_tag(git_dir, newer)?;
+    let stdout = run_git(
+        Some(git_dir),
+        &[
+            "log",
+            "--format=%H%x1f%an%x1f%aI%x1f%s",
+            &format!("{}..{}", older, newer),
+        ],
+    )?;
+
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\u{1f}');
+            Some(UpstreamCommit {
+                hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Packaging repositories tag each release with its version, `:` becoming `-`
+fn release_tag(git_dir: &Path, version: &str) -> Result<String, String> {
+    let tag = format!("refs/tags/{}", version.replace(':', "-"));
+    run_git(
+        Some(git_dir),
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", tag),
+        ],
+    )
+    .map(|_| tag)
+    .map_err(|_| format!("No tag for version {}", version))
+}
+
+fn run_git(git_dir: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("/usr/bin/git");
+    if let Some(git_dir) = git_dir {
+        command.arg("--git-dir").arg(git_dir);
+    }
+    let output = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
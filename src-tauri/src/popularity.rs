@@ -0,0 +1,499 @@
+use crate::aur::rpc::{fetch_aur_info, AurRpcPackage};
+use crate::pacman::database::PackageRecord;
+use crate::pacman::package_index;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// Default pkgstats endpoint
+pub const PKGSTATS_BASE_URL: &str = "https://pkgstats.archlinux.de";
+
+/// Downloaded statistics are reused for a day
+const CACHE_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// pkgstats pages are capped at this many entries
+const PAGE_SIZE: usize = 10_000;
+const MAX_PAGES: usize = 5;
+
+/// How many AUR packages get their RPC popularity looked up
+const AUR_LOOKUP_LIMIT: usize = 200;
+
+/// AUR popularity (decayed votes) added to the pkgstats percentage of AUR packages
+const AUR_POPULARITY_WEIGHT: f64 = 0.5;
+
+/// A package with its popularity among pkgstats submitters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PopularPackage {
+    pub name: String,
+    pub version: String,
+    pub repo: String,
+    pub description: String,
+    pub installed: bool,
+    /// Percentage of pkgstats submitters that have the package installed
+    pub popularity: f64,
+    /// Ranking score, the popularity combined with AUR popularity or relatedness
+    pub score: f64,
+    pub aur_popularity: Option<f64>,
+    pub aur_votes: Option<i64>,
+    /// Why the package was recommended (for similarity based lists)
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PkgstatsEntry {
+    name: String,
+    #[serde(default)]
+    popularity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PkgstatsPage {
+    #[serde(default)]
+    total: usize,
+    #[serde(default)]
+    package_popularities: Vec<PkgstatsEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PkgstatsCache {
+    fetched: i64,
+    packages: Vec<PkgstatsEntry>,
+}
+
+/// Base URL of the pkgstats API; `GUIMAN_PKGSTATS_URL` overrides it
+pub fn pkgstats_base_url() -> String {
+    std::env::var("GUIMAN_PKGSTATS_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| PKGSTATS_BASE_URL.to_string())
+}
+
+/// Most popular packages, optionally limited to a category.
+///
+/// A category is a repository (`core`, `extra`, `aur`, ...) or a package group.
+/// AUR packages are ranked by their pkgstats share plus their AUR popularity.
+pub async fn get_popular_packages(
+    category: Option<&str>,
+    limit: usize,
+) -> Result<Vec<PopularPackage>, String> {
+    let stats = load_pkgstats().await?;
+    let index = tokio::task::spawn_blocking(package_index)
+        .await
+        .map_err(|e| format!("Failed to load package index: {}", e))??;
+
+    let category = category.map(|c| c.trim().to_lowercase());
+    let (mut packages, foreign) =
+        popular_candidates(&stats, |name| index.get(name), category.as_deref(), limit);
+
+    // Offline, no foreign package can be confirmed to come from the AUR
+    let infos = if foreign.is_empty() {
+        Vec::new()
+    } else {
+        fetch_aur_info(&foreign).await.unwrap_or_default()
+    };
+    add_aur_popularity(&mut packages, &infos);
+    if category.as_deref() == Some("aur") {
+        packages.retain(|package| package.repo == "aur");
+    }
+
+    Ok(rank_popular(packages, limit))
+}
+
+/// Popular packages related to what is installed: group mates, packages built on
+/// top of installed ones and their optional dependencies.
+pub async fn get_recommended_packages(limit: usize) -> Result<Vec<PopularPackage>, String> {
+    let stats = load_pkgstats().await?;
+    let popularity: HashMap<&str, f64> = stats
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.popularity))
+        .collect();
+
+    let index = tokio::task::spawn_blocking(package_index)
+        .await
+        .map_err(|e| format!("Failed to load package index: {}", e))??;
+
+    let explicit: Vec<&PackageRecord> = index
+        .packages()
+        .iter()
+        .filter(|p| p.install_reason.as_deref() == Some("explicit"))
+        .collect();
+    let explicit_names: HashSet<&str> = explicit.iter().map(|p| p.name.as_str()).collect();
+    let groups: HashSet<&str> = explicit
+        .iter()
+        .flat_map(|p| p.groups.iter().map(|g| g.as_str()))
+        .collect();
+
+    // Optional dependencies are listed as `name: description`
+    let mut optional_for: HashMap<&str, &str> = HashMap::new();
+    for package in &explicit {
+        for optional in &package.optional_depends {
+            let name = optional.split(':').next().unwrap_or("").trim();
+            optional_for.entry(name).or_insert(package.name.as_str());
+        }
+    }
+
+    let mut scored = Vec::new();
+    for record in index.packages() {
+        if record.installed || record.repo == "local" {
+            continue;
+        }
+        let Some(&score) = popularity.get(record.name.as_str()) else {
+            continue;
+        };
+
+        let mut reasons = Vec::new();
+        if let Some(parent) = optional_for.get(record.name.as_str()) {
+            reasons.push(format!("Optional dependency of {}", parent));
+        }
+        if let Some(dependency) = record
+            .depends
+            .iter()
+            .map(|d| d.split(['=', '<', '>']).next().unwrap_or(d))
+            .find(|d| explicit_names.contains(d))
+        {
+            reasons.push(format!("Extends {}", dependency));
+        }
+        if let Some(group) = record.groups.iter().find(|g| groups.contains(g.as_str())) {
+            reasons.push(format!("In group {}", group));
+        }
+
+        if reasons.is_empty() {
+            continue;
+        }
+
+        let mut package = popular_from_record(record, score, Some(reasons.join(", ")));
+        package.score = score * (1.0 + reasons.len() as f64);
+        scored.push(package);
+    }
+
+    Ok(rank_popular(scored, limit))
+}
+
+// Helper functions
+
+fn in_category(record: &PackageRecord, category: Option<&str>) -> bool {
+    match category {
+        None => true,
+        Some(category) => record.repo == category || record.groups.iter().any(|g| g == category),
+    }
+}
+
+fn popular_from_record(
+    record: &PackageRecord,
+    popularity: f64,
+    reason: Option<String>,
+) -> PopularPackage {
+    PopularPackage {
+        name: record.name.clone(),
+        version: record.version.clone(),
+        repo: record.repo.clone(),
+        description: record.description.clone(),
+        installed: record.installed,
+        popularity,
+        score: popularity,
+        aur_popularity: None,
+        aur_votes: None,
+        reason,
+    }
+}
+
+/// Walk the statistics in order and collect up to `limit` sync packages in
+/// `category`, plus the packages found in no sync repository (AUR candidates).
+/// Candidates are labelled `aur` only once `add_aur_popularity` confirms them.
+fn popular_candidates<'a>(
+    stats: &[PkgstatsEntry],
+    lookup: impl Fn(&str) -> Option<&'a PackageRecord>,
+    category: Option<&str>,
+    limit: usize,
+) -> (Vec<PopularPackage>, Vec<String>) {
+    let aur_only = category == Some("aur");
+    let want_foreign = category.is_none() || aur_only;
+
+    let mut packages = Vec::new();
+    let mut synced = 0;
+    let mut foreign = Vec::new();
+
+    for entry in stats {
+        let sync_full = aur_only || synced >= limit;
+        let foreign_full = !want_foreign || foreign.len() >= AUR_LOOKUP_LIMIT;
+        if sync_full && foreign_full {
+            break;
+        }
+
+        match lookup(&entry.name) {
+            Some(record) if record.repo != "local" => {
+                if sync_full || !in_category(record, category) {
+                    continue;
+                }
+                synced += 1;
+                packages.push(popular_from_record(record, entry.popularity, None));
+            }
+            other => {
+                if foreign_full {
+                    continue;
+                }
+                foreign.push(entry.name.clone());
+                packages.push(PopularPackage {
+                    name: entry.name.clone(),
+                    version: other.map(|r| r.version.clone()).unwrap_or_default(),
+                    repo: String::new(),
+                    description: other.map(|r| r.description.clone()).unwrap_or_default(),
+                    installed: other.is_some(),
+                    popularity: entry.popularity,
+                    score: entry.popularity,
+                    aur_popularity: None,
+                    aur_votes: None,
+                    reason: None,
+                });
+            }
+        }
+    }
+
+    (packages, foreign)
+}
+
+/// Label candidates found in the AUR as `aur` and add their AUR popularity to
+/// the score. Installed candidates unknown to the AUR stay `local`; the rest
+/// cannot be installed from anywhere and are dropped.
+fn add_aur_popularity(packages: &mut Vec<PopularPackage>, infos: &[AurRpcPackage]) {
+    let by_name: HashMap<&str, &AurRpcPackage> =
+        infos.iter().map(|i| (i.name.as_str(), i)).collect();
+
+    packages.retain_mut(|package| {
+        if !package.repo.is_empty() {
+            return true;
+        }
+        let Some(info) = by_name.get(package.name.as_str()) else {
+            package.repo = "local".to_string();
+            return package.installed;
+        };
+
+        package.repo = "aur".to_string();
+        package.aur_popularity = Some(info.popularity);
+        package.aur_votes = Some(info.num_votes);
+        package.score = package.popularity + info.popularity * AUR_POPULARITY_WEIGHT;
+        if package.version.is_empty() {
+            package.version = info.version.clone();
+        }
+        if package.description.is_empty() {
+            package.description = info.description.clone().unwrap_or_default();
+        }
+        true
+    });
+}
+
+/// Highest scores first; the stable sort keeps pkgstats order for ties
+fn rank_popular(mut packages: Vec<PopularPackage>, limit: usize) -> Vec<PopularPackage> {
+    packages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    packages.truncate(limit);
+    packages
+}
+
+/// pkgstats entries sorted by popularity, from cache or freshly downloaded.
+///
+/// `GUIMAN_PKGSTATS_FIXTURE` points at a saved API response used instead of the network.
+async fn load_pkgstats() -> Result<Vec<PkgstatsEntry>, String> {
+    if let Ok(path) = std::env::var("GUIMAN_PKGSTATS_FIXTURE") {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read pkgstats fixture {}: {}", path, e))?;
+        let page: PkgstatsPage = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse pkgstats fixture: {}", e))?;
+        return Ok(sorted(page.package_popularities));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let cached = load_cache();
+    if let Some(cache) = &cached {
+        if now - cache.fetched < CACHE_TTL_SECS {
+            return Ok(cache.packages.clone());
+        }
+    }
+
+    match download_pkgstats().await {
+        Ok(packages) => {
+            let packages = sorted(packages);
+            let _ = save_cache(&PkgstatsCache {
+                fetched: now,
+                packages: packages.clone(),
+            });
+            Ok(packages)
+        }
+        // Offline: stale statistics are better than none
+        Err(e) => cached.map(|cache| cache.packages).ok_or(e),
+    }
+}
+
+async fn download_pkgstats() -> Result<Vec<PkgstatsEntry>, String> {
+    let client = reqwest::Client::new();
+    let mut packages = Vec::new();
+
+    for page in 0..MAX_PAGES {
+        let response = client
+            .get(format!("{}/api/packages", pkgstats_base_url()))
+            .header("User-Agent", "GuiMan")
+            .query(&[
+                ("limit", PAGE_SIZE.to_string()),
+                ("offset", (page * PAGE_SIZE).to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch pkgstats data: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("pkgstats request failed: {}", response.status()));
+        }
+
+        let page: PkgstatsPage = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse pkgstats data: {}", e))?;
+
+        let received = page.package_popularities.len();
+        packages.extend(page.package_popularities);
+        if received < PAGE_SIZE || packages.len() >= page.total {
+            break;
+        }
+    }
+
+    Ok(packages)
+}
+
+fn sorted(mut packages: Vec<PkgstatsEntry>) -> Vec<PkgstatsEntry> {
+    packages.sort_by(|a, b| b.popularity.total_cmp(&a.popularity));
+    packages
+}
+
+fn cache_file() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("guiman").join("pkgstats.json"))
+}
+
+fn load_cache() -> Option<PkgstatsCache> {
+    let content = fs::read_to_string(cache_file()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_cache(cache: &PkgstatsCache) -> Result<(), String> {
+    let path = cache_file().ok_or("Failed to get cache directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string(cache)
+        .map_err(|e| format!("Failed to serialize pkgstats cache: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save pkgstats cache: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saved `/api/packages` response from pkgstats, trimmed and shuffled
+    const PKGSTATS_PAGE: &str = include_str!("../tests/fixtures/pkgstats_packages.json");
+
+    fn record(name: &str, repo: &str, installed: bool) -> PackageRecord {
+        PackageRecord {
+            name: name.to_string(),
+            version: "1.0-1".to_string(),
+            repo: repo.to_string(),
+            installed,
+            ..Default::default()
+        }
+    }
+
+    fn aur_info(name: &str, popularity: f64, votes: i64) -> AurRpcPackage {
+        serde_json::from_value(serde_json::json!({
+            "ID": 1,
+            "Name": name,
+            "PackageBaseID": 1,
+            "PackageBase": name,
+            "Version": "2.0-1",
+            "NumVotes": votes,
+            "Popularity": popularity,
+        }))
+        .unwrap()
+    }
+
+    fn ranked(category: Option<&str>, limit: usize) -> Vec<PopularPackage> {
+        let page: PkgstatsPage = serde_json::from_str(PKGSTATS_PAGE).unwrap();
+        let stats = sorted(page.package_popularities);
+        let records: HashMap<String, PackageRecord> = [
+            record("pacman", "core", true),
+            record("linux", "core", true),
+            record("firefox", "extra", false),
+            record("vlc", "extra", false),
+            record("htop", "extra", true),
+            record("custom-thing", "local", true),
+        ]
+        .into_iter()
+        .map(|r| (r.name.clone(), r))
+        .collect();
+
+        let (mut packages, foreign) =
+            popular_candidates(&stats, |name| records.get(name), category, limit);
+        assert_eq!(foreign, ["yay", "custom-thing", "gone-pkg", "paru"]);
+
+        add_aur_popularity(
+            &mut packages,
+            &[aur_info("yay", 40.0, 2500), aur_info("paru", 30.0, 900)],
+        );
+        rank_popular(packages, limit)
+    }
+
+    #[test]
+    fn ranks_pkgstats_with_aur_popularity() {
+        let packages = ranked(None, 7);
+        let summary: Vec<(&str, &str, f64)> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.repo.as_str(), p.score))
+            .collect();
+
+        // yay outranks vlc thanks to its AUR popularity, gone-pkg exists nowhere
+        assert_eq!(
+            summary,
+            [
+                ("pacman", "core", 100.0),
+                ("linux", "core", 98.5),
+                ("firefox", "extra", 60.25),
+                ("yay", "aur", 50.25),
+                ("vlc", "extra", 45.0),
+                ("paru", "aur", 35.0),
+                ("custom-thing", "local", 25.0),
+            ]
+        );
+
+        let yay = &packages[3];
+        assert_eq!(yay.popularity, 30.25);
+        assert_eq!(yay.aur_popularity, Some(40.0));
+        assert_eq!(yay.aur_votes, Some(2500));
+        assert_eq!(yay.version, "2.0-1");
+        assert_eq!(packages[6].aur_popularity, None);
+    }
+
+    #[test]
+    fn limits_sync_packages_to_category() {
+        let page: PkgstatsPage = serde_json::from_str(PKGSTATS_PAGE).unwrap();
+        let stats = sorted(page.package_popularities);
+        let firefox = record("firefox", "extra", false);
+        let htop = record("htop", "extra", true);
+        let pacman = record("pacman", "core", true);
+
+        let (packages, foreign) = popular_candidates(
+            &stats,
+            |name| match name {
+                "firefox" => Some(&firefox),
+                "htop" => Some(&htop),
+                "pacman" => Some(&pacman),
+                _ => None,
+            },
+            Some("extra"),
+            10,
+        );
+
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["firefox", "htop"]);
+        assert!(foreign.is_empty());
+    }
+}
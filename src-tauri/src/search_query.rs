@@ -0,0 +1,432 @@
+use crate::aur::rpc::AurRpcPackage;
+use crate::pacman::database::PackageRecord;
+
+/// Keys recognised in `key:value` tokens
+const FILTER_KEYS: [&str; 10] = [
+    "repo",
+    "installed",
+    "size",
+    "votes",
+    "license",
+    "provides",
+    "depends",
+    "group",
+    "maintainer",
+    // Alias of `maintainer`
+    "packager",
+];
+
+/// How a numeric filter compares against the package value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn matches<T: PartialOrd>(self, value: T, target: T) -> bool {
+        match self {
+            Comparison::Less => value < target,
+            Comparison::LessOrEqual => value <= target,
+            Comparison::Equal => value == target,
+            Comparison::GreaterOrEqual => value >= target,
+            Comparison::Greater => value > target,
+        }
+    }
+}
+
+/// A single `key:value` filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Repo(String),
+    Installed(bool),
+    Size(Comparison, u64),
+    License(String),
+    Provides(String),
+    Depends(String),
+    Group(String),
+    /// `maintainer:` or `packager:`; the AUR maintainer, or the packager of
+    /// repository packages
+    Maintainer(String),
+    Votes(Comparison, i64),
+}
+
+/// A filter, optionally negated with a leading `-`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub filter: Filter,
+    pub negated: bool,
+}
+
+/// Parsed form of a search like `repo:extra size:>50M license:GPL -git`.
+///
+/// Plain words are ranked as free text and `"double quotes"` keep a phrase
+/// together as one term. `-word` excludes packages with that `-`-separated name
+/// segment (`-git` drops `foo-git`, not `digital`) and `key:value` pairs filter
+/// on package metadata. Tokens with an unknown key, such as URLs, are treated
+/// as text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub excluded: Vec<String>,
+    pub conditions: Vec<Condition>,
+}
+
+/// Package metadata a query is evaluated against, from either source
+#[derive(Debug, Default)]
+pub struct Candidate<'a> {
+    pub name: &'a str,
+    pub repo: &'a str,
+    pub installed: bool,
+    pub size: Option<u64>,
+    pub licenses: &'a [String],
+    pub provides: &'a [String],
+    pub depends: &'a [String],
+    pub groups: &'a [String],
+    pub maintainer: Option<&'a str>,
+    pub votes: Option<i64>,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn from_record(record: &'a PackageRecord) -> Self {
+        Self {
+            name: &record.name,
+            repo: &record.repo,
+            installed: record.installed,
+            size: Some(record.installed_size),
+            licenses: &record.licenses,
+            provides: &record.provides,
+            depends: &record.depends,
+            groups: &record.groups,
+            maintainer: Some(&record.packager),
+            votes: None,
+        }
+    }
+
+    pub fn from_aur(package: &'a AurRpcPackage, installed: bool) -> Self {
+        Self {
+            name: &package.name,
+            repo: "aur",
+            installed,
+            size: None,
+            licenses: &package.license,
+            provides: &package.provides,
+            depends: &package.depends,
+            groups: &[],
+            maintainer: package.maintainer.as_deref(),
+            votes: Some(package.num_votes),
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Parse a query string, rejecting malformed filter values
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = SearchQuery::default();
+
+        for token in tokenize(input) {
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+
+            let filter = match body.split_once(':') {
+                Some((key, value)) if !key.is_empty() => parse_filter(key, value)?,
+                _ => None,
+            };
+            match filter {
+                Some(filter) => query.conditions.push(Condition { filter, negated }),
+                None if negated => query.excluded.push(body.to_lowercase()),
+                None => query.terms.push(body.to_lowercase()),
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Free-text part of the query as a single string, for searches that
+    /// cannot take separate terms
+    pub fn text(&self) -> String {
+        self.terms.join(" ")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.excluded.is_empty() && self.conditions.is_empty()
+    }
+
+    /// Whether packages from `repo` can match at all
+    pub fn allows_repo(&self, repo: &str) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| match &condition.filter {
+                Filter::Repo(value) => (value == repo) != condition.negated,
+                _ => true,
+            })
+    }
+
+    /// Whether filters need metadata beyond name, repo and installed state
+    pub fn needs_metadata(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|c| !matches!(c.filter, Filter::Repo(_) | Filter::Installed(_)))
+    }
+
+    /// Check the exclusions and every filter against a package
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        let name = candidate.name.to_lowercase();
+        if self
+            .excluded
+            .iter()
+            .any(|word| has_name_segments(&name, word))
+        {
+            return false;
+        }
+
+        self.conditions
+            .iter()
+            .all(|condition| evaluate(&condition.filter, candidate) != condition.negated)
+    }
+}
+
+// Helper functions
+
+/// Split on whitespace, keeping double-quoted sections together
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for ch in input.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// The filter for `key:value`, or `None` when `key` is not a filter
+fn parse_filter(key: &str, value: &str) -> Result<Option<Filter>, String> {
+    let key = key.to_lowercase();
+    if !FILTER_KEYS.contains(&key.as_str()) {
+        return Ok(None);
+    }
+    if value.is_empty() {
+        return Err(format!("Missing value for filter '{}'", key));
+    }
+
+    let text = value.to_lowercase();
+    let filter = match key.as_str() {
+        "repo" => Filter::Repo(text),
+        "installed" => match text.as_str() {
+            "true" | "yes" | "1" => Filter::Installed(true),
+            "false" | "no" | "0" => Filter::Installed(false),
+            _ => return Err(format!("Invalid value for installed: '{}'", value)),
+        },
+        "size" => {
+            let (comparison, amount) = parse_comparison(value);
+            let bytes =
+                parse_size_value(amount).ok_or_else(|| format!("Invalid size: '{}'", value))?;
+            Filter::Size(comparison, bytes)
+        }
+        "votes" => {
+            let (comparison, amount) = parse_comparison(value);
+            let votes = amount
+                .parse()
+                .map_err(|_| format!("Invalid vote count: '{}'", value))?;
+            Filter::Votes(comparison, votes)
+        }
+        "license" => Filter::License(text),
+        "provides" => Filter::Provides(text),
+        "depends" => Filter::Depends(text),
+        "group" => Filter::Group(text),
+        _ => Filter::Maintainer(text),
+    };
+    Ok(Some(filter))
+}
+
+fn parse_comparison(value: &str) -> (Comparison, &str) {
+    for (prefix, comparison) in [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (comparison, rest);
+        }
+    }
+    (Comparison::Equal, value)
+}
+
+/// Parse sizes like `50M`, `1.5GiB` or `300k` to bytes
+fn parse_size_value(value: &str) -> Option<u64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number: f64 = value[..split].parse().ok()?;
+
+    let multiplier = match value[split..].to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" | "kib" => 1024.0,
+        "m" | "mb" | "mib" => 1024.0 * 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    Some((number * multiplier) as u64)
+}
+
+fn evaluate(filter: &Filter, candidate: &Candidate) -> bool {
+    match filter {
+        Filter::Repo(repo) => candidate.repo.eq_ignore_ascii_case(repo),
+        Filter::Installed(installed) => candidate.installed == *installed,
+        Filter::Size(comparison, bytes) => candidate
+            .size
+            .is_some_and(|size| comparison.matches(size, *bytes)),
+        Filter::Votes(comparison, votes) => candidate
+            .votes
+            .is_some_and(|count| comparison.matches(count, *votes)),
+        Filter::License(license) => contains_value(candidate.licenses, license),
+        Filter::Depends(depends) => contains_name(candidate.depends, depends),
+        Filter::Provides(provides) => {
+            candidate.name.eq_ignore_ascii_case(provides)
+                || contains_name(candidate.provides, provides)
+        }
+        Filter::Group(group) => candidate
+            .groups
+            .iter()
+            .any(|g| g.eq_ignore_ascii_case(group)),
+        Filter::Maintainer(maintainer) => candidate
+            .maintainer
+            .is_some_and(|m| m.to_lowercase().contains(maintainer.as_str())),
+    }
+}
+
+/// Whether the `-`-separated segments of `word` appear in a row in `name`
+fn has_name_segments(name: &str, word: &str) -> bool {
+    let segments: Vec<&str> = name.split('-').collect();
+    let wanted: Vec<&str> = word.split('-').collect();
+    segments
+        .windows(wanted.len())
+        .any(|window| window == wanted)
+}
+
+/// Case-insensitive substring match, so `license:gpl` finds `GPL-3.0-or-later`
+fn contains_value(values: &[String], needle: &str) -> bool {
+    values.iter().any(|v| v.to_lowercase().contains(needle))
+}
+
+/// Match package names while ignoring version constraints like `java-runtime=21`
+fn contains_name(values: &[String], needle: &str) -> bool {
+    values.iter().any(|v| {
+        v.split(['=', '<', '>'])
+            .next()
+            .is_some_and(|name| name.eq_ignore_ascii_case(needle))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> SearchQuery {
+        SearchQuery::parse(input).unwrap()
+    }
+
+    fn condition(filter: Filter, negated: bool) -> Condition {
+        Condition { filter, negated }
+    }
+
+    #[test]
+    fn parses_terms_exclusions_and_filters() {
+        let query = parse("Editor repo:extra size:>=50M -git -license:GPL votes:<10 qt");
+        assert_eq!(query.terms, ["editor", "qt"]);
+        assert_eq!(query.excluded, ["git"]);
+        assert_eq!(
+            query.conditions,
+            [
+                condition(Filter::Repo("extra".to_string()), false),
+                condition(
+                    Filter::Size(Comparison::GreaterOrEqual, 50 * 1024 * 1024),
+                    false
+                ),
+                condition(Filter::License("gpl".to_string()), true),
+                condition(Filter::Votes(Comparison::Less, 10), false),
+            ]
+        );
+        assert!(query.needs_metadata());
+        assert!(!query.allows_repo("aur"));
+    }
+
+    #[test]
+    fn keeps_quoted_phrases_together() {
+        let query = parse(r#""Visual Studio" code "maintainer:John Doe""#);
+        assert_eq!(query.terms, ["visual studio", "code"]);
+        assert_eq!(
+            query.conditions,
+            [condition(Filter::Maintainer("john doe".to_string()), false)]
+        );
+    }
+
+    #[test]
+    fn treats_unknown_keys_and_lone_dashes_as_text() {
+        let query = parse("https://example.org - c++:");
+        assert_eq!(query.terms, ["https://example.org", "-", "c++:"]);
+        assert!(query.conditions.is_empty());
+        assert!(!query.needs_metadata());
+    }
+
+    #[test]
+    fn rejects_malformed_filter_values() {
+        assert!(SearchQuery::parse("size:huge").is_err());
+        assert!(SearchQuery::parse("size:>10T").is_err());
+        assert!(SearchQuery::parse("votes:many").is_err());
+        assert!(SearchQuery::parse("installed:maybe").is_err());
+        assert!(SearchQuery::parse("repo:").is_err());
+    }
+
+    #[test]
+    fn packager_is_an_alias_of_maintainer() {
+        assert_eq!(parse("packager:Levente"), parse("maintainer:levente"));
+    }
+
+    #[test]
+    fn matches_candidates() {
+        let licenses = vec!["GPL-3.0-or-later".to_string()];
+        let provides = vec!["java-runtime=21".to_string()];
+        let candidate = Candidate {
+            name: "jre-openjdk-git",
+            repo: "extra",
+            installed: true,
+            size: Some(200 * 1024 * 1024),
+            licenses: &licenses,
+            provides: &provides,
+            maintainer: Some("Levente Polyak <anthraxx@archlinux.org>"),
+            ..Default::default()
+        };
+
+        assert!(parse("size:>100M license:gpl provides:java-runtime").matches(&candidate));
+        assert!(parse("packager:anthraxx installed:yes").matches(&candidate));
+        assert!(!parse("-git").matches(&candidate));
+        assert!(!parse("-openjdk-git").matches(&candidate));
+        assert!(parse("-jdk").matches(&candidate));
+        assert!(!parse("-repo:extra").matches(&candidate));
+        // AUR only values never match repository packages
+        assert!(!parse("votes:>0").matches(&candidate));
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Arch Linux packaging repositories on GitLab
+pub const DEFAULT_PACKAGING_GIT_URL: &str =
+    "https://gitlab.archlinux.org/archlinux/packaging/packages/{pkgbase}.git";
+
+/// Backend settings persisted in `~/.config/guiman/settings.json`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Build AUR packages inside a clean chroot instead of on the host
+    pub clean_chroot_builds: bool,
+    /// Directory holding the clean build chroot (`<dir>/root`)
+    pub chroot_directory: String,
+    /// Add every AUR package GuiMan builds to the `[guiman-local]` repository
+    pub local_repo_enabled: bool,
+    /// Directory of the `[guiman-local]` repository database and package files
+    pub local_repo_directory: String,
+    /// Git URL of a package's packaging repository; `{pkgbase}` is replaced,
+    /// empty disables upstream commit lookups
+    pub packaging_git_url: String,
+    /// Take pre/post filesystem snapshots around package transactions
+    pub snapshots_enabled: bool,
+    /// `auto`, `snapper`, `btrfs` or `none`; `GUIMAN_SNAPSHOT_BACKEND` overrides it
+    pub snapshot_backend: String,
+    /// Snapper configuration to snapshot
+    pub snapper_config: String,
+    /// Where the btrfs backend puts read-only snapshots of `/`
+    pub btrfs_snapshot_directory: String,
+    /// Transactions whose snapshots are kept; older GuiMan snapshots are deleted
+    pub snapshot_keep: usize,
+    /// Where package backups are stored
+    pub backup_directory: String,
+    /// Back up the package state before each system update
+    pub backup_before_update: bool,
+    /// Hours between scheduled backups, 0 disables them
+    pub backup_interval_hours: u64,
+    /// Days for which the newest automatic backup is kept
+    pub backup_keep_daily: usize,
+    /// Weeks for which the newest automatic backup is kept
+    pub backup_keep_weekly: usize,
+    /// Write backups gzip-compressed
+    pub backup_compress: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            clean_chroot_builds: false,
+            chroot_directory: default_data_path("chroot"),
+            local_repo_enabled: true,
+            local_repo_directory: default_data_path("repo"),
+            packaging_git_url: DEFAULT_PACKAGING_GIT_URL.to_string(),
+            snapshots_enabled: false,
+            snapshot_backend: "auto".to_string(),
+            snapper_config: "root".to_string(),
+            btrfs_snapshot_directory: "/.guiman-snapshots".to_string(),
+            snapshot_keep: 10,
+            backup_directory: dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("/tmp"))
+                .join("guiman")
+                .join("backups")
+                .to_string_lossy()
+                .to_string(),
+            backup_before_update: false,
+            backup_interval_hours: 0,
+            backup_keep_daily: 7,
+            backup_keep_weekly: 4,
+            backup_compress: false,
+        }
+    }
+}
+
+/// Load settings, falling back to defaults for missing or invalid files
+pub fn load_settings() -> Settings {
+    settings_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Persist settings
+pub fn save_settings(settings: &Settings) -> Result<(), String> {
+    let path = settings_file().ok_or("Failed to get config directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+fn settings_file() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("guiman").join("settings.json"))
+}
+
+/// Path below `~/.local/share/guiman`
+pub fn default_data_path(name: &str) -> String {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("guiman")
+        .join(name)
+        .to_string_lossy()
+        .to_string()
+}
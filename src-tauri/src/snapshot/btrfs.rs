@@ -0,0 +1,120 @@
+use super::{load_index, now, save_index, Snapshot, SnapshotBackend, SnapshotKind};
+use crate::settings::default_data_path;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Read-only btrfs snapshots of `/`, taken through pkexec. Btrfs keeps no
+/// descriptions, so GuiMan records its snapshots in its own index.
+pub struct BtrfsBackend {
+    directory: PathBuf,
+}
+
+impl BtrfsBackend {
+    pub fn new(directory: &str) -> Self {
+        Self {
+            directory: PathBuf::from(directory),
+        }
+    }
+
+    /// Whether `/` is on btrfs and the btrfs tools are installed
+    pub fn is_available() -> bool {
+        Path::new("/usr/bin/btrfs").exists()
+            && Command::new("/usr/bin/stat")
+                .args(["-f", "-c", "%T", "/"])
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "btrfs")
+                .unwrap_or(false)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        PathBuf::from(default_data_path("snapshots")).join("btrfs.json")
+    }
+}
+
+impl SnapshotBackend for BtrfsBackend {
+    fn name(&self) -> &'static str {
+        "btrfs"
+    }
+
+    fn create(
+        &self,
+        kind: SnapshotKind,
+        pre_id: Option<&str>,
+        description: &str,
+    ) -> Result<Snapshot, String> {
+        let suffix = match kind {
+            SnapshotKind::Pre => "pre",
+            SnapshotKind::Post => "post",
+            SnapshotKind::Single => "single",
+        };
+        let id = format!("{}-{}", Local::now().format("%Y%m%d-%H%M%S"), suffix);
+        let path = self.directory.join(&id);
+
+        if !self.directory.exists() {
+            run_privileged(&["/usr/bin/mkdir", "-p", &self.directory.to_string_lossy()])?;
+        }
+        run_privileged(&[
+            "/usr/bin/btrfs",
+            "subvolume",
+            "snapshot",
+            "-r",
+            "/",
+            &path.to_string_lossy(),
+        ])?;
+
+        let snapshot = Snapshot {
+            id,
+            kind,
+            pre_id: pre_id.map(str::to_string),
+            created: now(),
+            description: description.to_string(),
+            backend: self.name().to_string(),
+            managed: true,
+        };
+        let mut snapshots = load_index(&self.index_path());
+        snapshots.push(snapshot.clone());
+        save_index(&self.index_path(), &snapshots)?;
+        Ok(snapshot)
+    }
+
+    fn list(&self) -> Result<Vec<Snapshot>, String> {
+        // Drop snapshots deleted behind GuiMan's back
+        Ok(load_index(&self.index_path())
+            .into_iter()
+            .filter(|s| self.directory.join(&s.id).exists())
+            .collect())
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        if id.is_empty() || id.contains('/') || id.starts_with('.') {
+            return Err(format!("Invalid snapshot id: {}", id));
+        }
+        run_privileged(&[
+            "/usr/bin/btrfs",
+            "subvolume",
+            "delete",
+            &self.directory.join(id).to_string_lossy(),
+        ])?;
+
+        let mut snapshots = load_index(&self.index_path());
+        snapshots.retain(|s| s.id != id);
+        save_index(&self.index_path(), &snapshots)
+    }
+}
+
+fn run_privileged(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("/usr/bin/pkexec")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", args[0], e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
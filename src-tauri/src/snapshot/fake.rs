@@ -0,0 +1,279 @@
+use super::{load_index, now, save_index, Snapshot, SnapshotBackend, SnapshotKind};
+use std::path::PathBuf;
+
+/// Records snapshots in a JSON file without touching the filesystem, for
+/// machines without btrfs. Selected with `GUIMAN_SNAPSHOT_BACKEND=fake`;
+/// `GUIMAN_SNAPSHOT_DIR` chooses where the records go.
+pub struct FakeBackend {
+    directory: PathBuf,
+}
+
+impl FakeBackend {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.directory.join("snapshots.json")
+    }
+}
+
+impl SnapshotBackend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn create(
+        &self,
+        kind: SnapshotKind,
+        pre_id: Option<&str>,
+        description: &str,
+    ) -> Result<Snapshot, String> {
+        let mut snapshots = load_index(&self.index_path());
+        let next = snapshots
+            .iter()
+            .filter_map(|s| s.id.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let snapshot = Snapshot {
+            id: next.to_string(),
+            kind,
+            pre_id: pre_id.map(str::to_string),
+            created: now(),
+            description: description.to_string(),
+            backend: self.name().to_string(),
+            managed: true,
+        };
+        snapshots.push(snapshot.clone());
+        save_index(&self.index_path(), &snapshots)?;
+        Ok(snapshot)
+    }
+
+    fn list(&self) -> Result<Vec<Snapshot>, String> {
+        Ok(load_index(&self.index_path()))
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        let mut snapshots = load_index(&self.index_path());
+        let before = snapshots.len();
+        snapshots.retain(|s| s.id != id);
+        if snapshots.len() == before {
+            return Err(format!("Snapshot {} not found", id));
+        }
+        save_index(&self.index_path(), &snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{cleanup, SnapshotLog, TransactionSnapshots};
+    use chrono::{Duration, Local};
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    /// The pacman log location is read from the environment
+    static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+    struct Fixture {
+        directory: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let directory = std::env::temp_dir().join(format!(
+                "guiman-snapshots-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&directory);
+            fs::create_dir_all(&directory).unwrap();
+            std::env::set_var("GUIMAN_PACMAN_LOG", directory.join("pacman.log"));
+            Self { directory }
+        }
+
+        fn backend(&self) -> Box<FakeBackend> {
+            Box::new(FakeBackend::new(self.directory.clone()))
+        }
+
+        /// Log a transaction that installed `package`, a little after now
+        fn log_install(&self, package: &str) {
+            let time = (Local::now() + Duration::seconds(1)).format("%Y-%m-%dT%H:%M:%S%z");
+            let mut content =
+                fs::read_to_string(self.directory.join("pacman.log")).unwrap_or_default();
+            content.push_str(&format!(
+                "[{time}] [PACMAN] Running 'pacman -S {package}'\n\
+                 [{time}] [ALPM] transaction started\n\
+                 [{time}] [ALPM] installed {package} (1.0-1)\n\
+                 [{time}] [ALPM] transaction completed\n"
+            ));
+            fs::write(self.directory.join("pacman.log"), content).unwrap();
+        }
+
+        fn empty_log(&self) {
+            fs::write(self.directory.join("pacman.log"), "").unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+
+    fn collect_log() -> (Arc<Mutex<Vec<String>>>, SnapshotLog) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = lines.clone();
+        (lines, Box::new(move |line| sink.lock().unwrap().push(line)))
+    }
+
+    fn run_transaction(fixture: &Fixture, package: &str, keep: usize) {
+        let (_, log) = collect_log();
+        let snapshots = TransactionSnapshots::start(
+            fixture.backend(),
+            keep,
+            &format!("pacman -S {}", package),
+            log,
+        );
+        fixture.log_install(package);
+        snapshots.finish();
+    }
+
+    #[test]
+    fn pairs_post_with_pre() {
+        let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixture = Fixture::new("pair");
+        fixture.empty_log();
+
+        run_transaction(&fixture, "foo", 5);
+
+        let snapshots = fixture.backend().list().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].kind, SnapshotKind::Pre);
+        assert_eq!(snapshots[0].description, "GuiMan: pacman -S foo");
+        assert_eq!(snapshots[1].kind, SnapshotKind::Post);
+        assert_eq!(
+            snapshots[1].pre_id.as_deref(),
+            Some(snapshots[0].id.as_str())
+        );
+        assert_eq!(snapshots[1].description, "GuiMan: installed foo 1.0-1");
+    }
+
+    #[test]
+    fn dropping_without_transaction_removes_pre() {
+        let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixture = Fixture::new("cancelled");
+        fixture.empty_log();
+
+        // An early return, e.g. after the password prompt was cancelled
+        let (lines, log) = collect_log();
+        let snapshots = TransactionSnapshots::start(fixture.backend(), 5, "pacman -S foo", log);
+        drop(snapshots);
+
+        assert!(fixture.backend().list().unwrap().is_empty());
+        assert!(lines.lock().unwrap()[1].contains("nothing changed"));
+    }
+
+    #[test]
+    fn dropping_after_transaction_takes_post() {
+        let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixture = Fixture::new("dropped");
+        fixture.empty_log();
+
+        let (_, log) = collect_log();
+        let snapshots = TransactionSnapshots::start(fixture.backend(), 5, "pacman -S foo", log);
+        fixture.log_install("foo");
+        drop(snapshots);
+
+        let kinds: Vec<SnapshotKind> = fixture
+            .backend()
+            .list()
+            .unwrap()
+            .iter()
+            .map(|s| s.kind)
+            .collect();
+        assert_eq!(kinds, [SnapshotKind::Pre, SnapshotKind::Post]);
+    }
+
+    #[test]
+    fn keeps_newest_transactions() {
+        let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixture = Fixture::new("keep");
+        fixture.empty_log();
+
+        for package in ["a", "b", "c"] {
+            run_transaction(&fixture, package, 2);
+        }
+
+        // Only the two newest transactions remain, each pre with its post
+        let snapshots = fixture.backend().list().unwrap();
+        let summary: Vec<(SnapshotKind, &str, Option<&str>)> = snapshots
+            .iter()
+            .map(|s| (s.kind, s.id.as_str(), s.pre_id.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (SnapshotKind::Pre, "3", None),
+                (SnapshotKind::Post, "4", Some("3")),
+                (SnapshotKind::Pre, "5", None),
+                (SnapshotKind::Post, "6", Some("5")),
+            ]
+        );
+        assert_eq!(snapshots[0].description, "GuiMan: pacman -S b");
+        assert_eq!(snapshots[2].description, "GuiMan: pacman -S c");
+    }
+
+    #[test]
+    fn cleanup_orders_by_time_and_deletes_posts_first() {
+        let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixture = Fixture::new("cleanup");
+        let older = Snapshot {
+            id: "10".to_string(),
+            kind: SnapshotKind::Pre,
+            pre_id: None,
+            // Sorts after the newer one as a string, but is an hour earlier
+            created: "2026-01-01T12:30:00+02:00".to_string(),
+            description: "older".to_string(),
+            backend: "fake".to_string(),
+            managed: true,
+        };
+        let older_post = Snapshot {
+            id: "11".to_string(),
+            kind: SnapshotKind::Post,
+            pre_id: Some("10".to_string()),
+            ..older.clone()
+        };
+        let newer = Snapshot {
+            id: "12".to_string(),
+            created: "2026-01-01T11:00:00+00:00".to_string(),
+            description: "newer".to_string(),
+            ..older.clone()
+        };
+        let unmanaged = Snapshot {
+            id: "13".to_string(),
+            created: "2025-01-01T00:00:00+00:00".to_string(),
+            managed: false,
+            ..older.clone()
+        };
+        save_index(
+            &fixture.directory.join("snapshots.json"),
+            &[older, older_post, newer, unmanaged],
+        )
+        .unwrap();
+
+        let deleted = cleanup(fixture.backend().as_ref(), 1).unwrap();
+        assert_eq!(deleted, ["11", "10"]);
+
+        let remaining: Vec<String> = fixture
+            .backend()
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(remaining, ["12", "13"]);
+    }
+}
@@ -0,0 +1,368 @@
+pub mod btrfs;
+pub mod fake;
+pub mod snapper;
+
+use crate::pacman::log::{self, LogAction};
+use crate::settings::{self, default_data_path, Settings};
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{Emitter, Window};
+
+pub use btrfs::BtrfsBackend;
+pub use fake::FakeBackend;
+pub use snapper::SnapperBackend;
+
+/// Longest transaction summary put into a snapshot description
+const MAX_DESCRIPTION: usize = 200;
+
+/// Role of a snapshot in a transaction
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotKind {
+    Pre,
+    Post,
+    Single,
+}
+
+/// A filesystem snapshot as reported by a backend
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub kind: SnapshotKind,
+    /// The pre snapshot a post snapshot belongs to
+    pub pre_id: Option<String>,
+    /// RFC 3339 creation time
+    pub created: String,
+    pub description: String,
+    pub backend: String,
+    /// Created by GuiMan, and therefore subject to its cleanup policy
+    pub managed: bool,
+}
+
+/// Something that can take, list and delete filesystem snapshots
+pub trait SnapshotBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Take a snapshot; post snapshots name their pre snapshot
+    fn create(
+        &self,
+        kind: SnapshotKind,
+        pre_id: Option<&str>,
+        description: &str,
+    ) -> Result<Snapshot, String>;
+
+    /// All snapshots, oldest first
+    fn list(&self) -> Result<Vec<Snapshot>, String>;
+
+    fn delete(&self, id: &str) -> Result<(), String>;
+}
+
+/// Which backends the system supports and which one is used
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotStatus {
+    pub enabled: bool,
+    /// Backend used for transactions, `None` when nothing is available
+    pub backend: Option<String>,
+    pub available: Vec<String>,
+    /// snap-pac already snapshots every pacman transaction through snapper
+    pub snap_pac: bool,
+}
+
+/// The backend selected by the settings, or by `GUIMAN_SNAPSHOT_BACKEND`
+pub fn active_backend(settings: &Settings) -> Option<Box<dyn SnapshotBackend>> {
+    let choice = std::env::var("GUIMAN_SNAPSHOT_BACKEND")
+        .unwrap_or_else(|_| settings.snapshot_backend.clone());
+
+    match choice.trim().to_lowercase().as_str() {
+        "fake" => Some(Box::new(FakeBackend::new(fake_directory()))),
+        "snapper" => Some(Box::new(SnapperBackend::new(&settings.snapper_config))),
+        "btrfs" => Some(Box::new(BtrfsBackend::new(
+            &settings.btrfs_snapshot_directory,
+        ))),
+        "auto" => {
+            if SnapperBackend::is_available(&settings.snapper_config) {
+                Some(Box::new(SnapperBackend::new(&settings.snapper_config)))
+            } else if BtrfsBackend::is_available() {
+                Some(Box::new(BtrfsBackend::new(
+                    &settings.btrfs_snapshot_directory,
+                )))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+pub fn snapshot_status() -> SnapshotStatus {
+    let settings = settings::load_settings();
+    let mut available = Vec::new();
+    if SnapperBackend::is_available(&settings.snapper_config) {
+        available.push("snapper".to_string());
+    }
+    if BtrfsBackend::is_available() {
+        available.push("btrfs".to_string());
+    }
+
+    SnapshotStatus {
+        enabled: settings.snapshots_enabled,
+        backend: active_backend(&settings).map(|backend| backend.name().to_string()),
+        available,
+        snap_pac: snap_pac_installed(),
+    }
+}
+
+/// Delete managed snapshots of all but the newest `keep` transactions.
+/// Returns the ids of the deleted snapshots.
+pub fn cleanup(backend: &dyn SnapshotBackend, keep: usize) -> Result<Vec<String>, String> {
+    let snapshots = backend.list()?;
+    // Newest first; snapshots taken in the same second keep their listed order
+    let mut heads: Vec<(Option<DateTime<FixedOffset>>, usize, &Snapshot)> = snapshots
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.managed && s.kind != SnapshotKind::Post)
+        .map(|(i, s)| (DateTime::parse_from_rfc3339(&s.created).ok(), i, s))
+        .collect();
+    heads.sort_by_key(|(created, index, _)| std::cmp::Reverse((*created, *index)));
+
+    let mut deleted = Vec::new();
+    for (_, _, head) in heads.into_iter().skip(keep) {
+        // Posts first, snapper refuses to delete a pre snapshot that still has one
+        for post in snapshots
+            .iter()
+            .filter(|s| s.pre_id.as_deref() == Some(head.id.as_str()))
+        {
+            backend.delete(&post.id)?;
+            deleted.push(post.id.clone());
+        }
+        backend.delete(&head.id)?;
+        deleted.push(head.id.clone());
+    }
+
+    Ok(deleted)
+}
+
+/// Receives the progress lines of [`TransactionSnapshots`]
+pub type SnapshotLog = Box<dyn Fn(String) + Send>;
+
+/// Pre/post snapshot pair around one package transaction. Snapshot failures
+/// are logged but never stop the transaction.
+///
+/// The post snapshot is taken by [`finish`](Self::finish), or on drop when the
+/// transaction ends early.
+pub struct TransactionSnapshots {
+    backend: Option<Box<dyn SnapshotBackend>>,
+    pre: Option<Snapshot>,
+    keep: usize,
+    log: SnapshotLog,
+}
+
+impl TransactionSnapshots {
+    /// Take the pre snapshot, if snapshots are enabled. Progress is emitted as `event`.
+    pub fn begin(window: &Window, event: &str, command: &str) -> Self {
+        let settings = settings::load_settings();
+        let disabled = TransactionSnapshots {
+            backend: None,
+            pre: None,
+            keep: settings.snapshot_keep,
+            log: Box::new(|_| {}),
+        };
+        if !settings.snapshots_enabled && std::env::var("GUIMAN_SNAPSHOT_BACKEND").is_err() {
+            return disabled;
+        }
+
+        let Some(backend) = active_backend(&settings) else {
+            let _ = window.emit(event, "Snapshots: no snapper or btrfs backend available");
+            return disabled;
+        };
+        if backend.name() == "snapper" && snap_pac_installed() {
+            let _ = window.emit(event, "Snapshots: left to snap-pac");
+            return disabled;
+        }
+
+        let window = window.clone();
+        let event = event.to_string();
+        let log = Box::new(move |line: String| {
+            let _ = window.emit(&event, line);
+        });
+        Self::start(backend, settings.snapshot_keep, command, log)
+    }
+
+    /// Take the pre snapshot with `backend`, keeping `keep` transactions on cleanup
+    pub fn start(
+        backend: Box<dyn SnapshotBackend>,
+        keep: usize,
+        command: &str,
+        log: SnapshotLog,
+    ) -> Self {
+        let mut snapshots = TransactionSnapshots {
+            backend: None,
+            pre: None,
+            keep,
+            log,
+        };
+
+        match backend.create(SnapshotKind::Pre, None, &format!("GuiMan: {}", command)) {
+            Ok(pre) => {
+                (snapshots.log)(format!(
+                    "Snapshots: created {} pre snapshot {}",
+                    backend.name(),
+                    pre.id
+                ));
+                snapshots.pre = Some(pre);
+                snapshots.backend = Some(backend);
+            }
+            Err(e) => (snapshots.log)(format!("ERROR: Snapshots: {}", e)),
+        }
+        snapshots
+    }
+
+    /// Take the post snapshot, describing what the transaction changed, then apply the cleanup policy
+    pub fn finish(mut self) {
+        self.complete();
+    }
+
+    fn complete(&mut self) {
+        let (Some(backend), Some(pre)) = (self.backend.take(), self.pre.take()) else {
+            return;
+        };
+        let log = &self.log;
+
+        let description = match logged_changes(&pre.created) {
+            // Nothing was installed or removed, e.g. the password prompt was cancelled
+            Ok(changes) if changes.is_empty() => {
+                match backend.delete(&pre.id) {
+                    Ok(()) => log(format!(
+                        "Snapshots: nothing changed, removed pre snapshot {}",
+                        pre.id
+                    )),
+                    Err(e) => log(format!("ERROR: Snapshots: {}", e)),
+                }
+                return;
+            }
+            Ok(changes) => summarize_changes(&changes),
+            Err(_) => pre.description.clone(),
+        };
+
+        match backend.create(SnapshotKind::Post, Some(&pre.id), &description) {
+            Ok(post) => log(format!(
+                "Snapshots: created {} post snapshot {}",
+                backend.name(),
+                post.id
+            )),
+            Err(e) => {
+                log(format!("ERROR: Snapshots: {}", e));
+                return;
+            }
+        }
+
+        match cleanup(backend.as_ref(), self.keep) {
+            Ok(deleted) if !deleted.is_empty() => log(format!(
+                "Snapshots: removed old snapshots {}",
+                deleted.join(", ")
+            )),
+            Ok(_) => {}
+            Err(e) => log(format!("ERROR: Snapshots cleanup: {}", e)),
+        }
+    }
+}
+
+impl Drop for TransactionSnapshots {
+    fn drop(&mut self) {
+        self.complete();
+    }
+}
+
+// Helper functions
+
+fn snap_pac_installed() -> bool {
+    Command::new("/usr/bin/pacman")
+        .args(["-Qq", "snap-pac"])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn fake_directory() -> PathBuf {
+    std::env::var("GUIMAN_SNAPSHOT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(default_data_path("fake-snapshots")))
+}
+
+/// Package changes of the transactions logged since the pre snapshot,
+/// e.g. `upgraded linux 6.1-1 -> 6.2-1`
+fn logged_changes(since: &str) -> Result<Vec<String>, String> {
+    let since =
+        DateTime::parse_from_rfc3339(since).map_err(|e| format!("Invalid snapshot time: {}", e))?;
+    let transactions = log::load_transactions()?;
+
+    Ok(transactions
+        .iter()
+        .filter(|t| log::parse_log_timestamp(&t.started).is_some_and(|started| started >= since))
+        .flat_map(|t| t.entries.iter())
+        .map(|entry| match entry.action {
+            LogAction::Upgraded | LogAction::Downgraded => format!(
+                "{} {} {} -> {}",
+                entry.action.as_str(),
+                entry.package,
+                entry.old_version.as_deref().unwrap_or("?"),
+                entry.new_version.as_deref().unwrap_or("?")
+            ),
+            _ => format!(
+                "{} {} {}",
+                entry.action.as_str(),
+                entry.package,
+                entry
+                    .new_version
+                    .as_deref()
+                    .or(entry.old_version.as_deref())
+                    .unwrap_or("")
+            ),
+        })
+        .collect())
+}
+
+/// Snapshot description listing `changes`, e.g. `GuiMan: upgraded linux 6.1-1 -> 6.2-1, installed foo 1.0-1`
+fn summarize_changes(changes: &[String]) -> String {
+    let mut summary = String::from("GuiMan: ");
+    for (i, change) in changes.iter().enumerate() {
+        if summary.len() + change.len() > MAX_DESCRIPTION {
+            summary.push_str(&format!(" and {} more", changes.len() - i));
+            break;
+        }
+        if i > 0 {
+            summary.push_str(", ");
+        }
+        summary.push_str(change);
+    }
+    summary
+}
+
+/// Current time in the format used by [`Snapshot::created`]
+fn now() -> String {
+    // Whole seconds, like the pacman log it is compared with
+    Local::now()
+        .fixed_offset()
+        .to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Snapshot metadata kept in a JSON file, for backends that have no place of their own for it
+fn load_index(path: &Path) -> Vec<Snapshot> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(path: &Path, snapshots: &[Snapshot]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create snapshot index directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(snapshots)
+        .map_err(|e| format!("Failed to serialize snapshot index: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to save snapshot index: {}", e))
+}
@@ -0,0 +1,159 @@
+use super::{now, Snapshot, SnapshotBackend, SnapshotKind};
+use chrono::{Local, NaiveDateTime, SecondsFormat, TimeZone};
+use std::path::Path;
+use std::process::Command;
+
+/// Userdata key marking the snapshots GuiMan manages
+const USERDATA_KEY: &str = "guiman";
+
+/// Snapshots through snapper. The user needs to be in the config's
+/// `ALLOW_USERS` since snapper runs without pkexec.
+pub struct SnapperBackend {
+    config: String,
+}
+
+impl SnapperBackend {
+    pub fn new(config: &str) -> Self {
+        Self {
+            config: config.to_string(),
+        }
+    }
+
+    pub fn is_available(config: &str) -> bool {
+        Path::new("/usr/bin/snapper").exists()
+            && Path::new("/etc/snapper/configs").join(config).exists()
+    }
+
+    fn snapper(&self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("/usr/bin/snapper")
+            .args(["-c", &self.config])
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run snapper: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "snapper failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+impl SnapshotBackend for SnapperBackend {
+    fn name(&self) -> &'static str {
+        "snapper"
+    }
+
+    fn create(
+        &self,
+        kind: SnapshotKind,
+        pre_id: Option<&str>,
+        description: &str,
+    ) -> Result<Snapshot, String> {
+        let kind_arg = match kind {
+            SnapshotKind::Pre => "pre",
+            SnapshotKind::Post => "post",
+            SnapshotKind::Single => "single",
+        };
+        let userdata = format!("{}=yes", USERDATA_KEY);
+        let mut args = vec![
+            "create",
+            "--type",
+            kind_arg,
+            "--print-number",
+            "--cleanup-algorithm",
+            "number",
+            "--description",
+            description,
+            "--userdata",
+            &userdata,
+        ];
+        if let (SnapshotKind::Post, Some(pre_id)) = (kind, pre_id) {
+            args.extend(["--pre-number", pre_id]);
+        }
+
+        let id = self.snapper(&args)?.trim().to_string();
+        if id.is_empty() {
+            return Err("snapper did not report a snapshot number".to_string());
+        }
+
+        Ok(Snapshot {
+            id,
+            kind,
+            pre_id: pre_id.map(str::to_string),
+            created: now(),
+            description: description.to_string(),
+            backend: self.name().to_string(),
+            managed: true,
+        })
+    }
+
+    fn list(&self) -> Result<Vec<Snapshot>, String> {
+        let stdout = self.snapper(&["--jsonout", "list"])?;
+        let json: serde_json::Value = serde_json::from_str(&stdout)
+            .map_err(|e| format!("Failed to parse snapper output: {}", e))?;
+
+        let entries = json
+            .get(&self.config)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let number = entry.get("number")?.as_u64()?;
+                // Snapshot 0 is the live filesystem
+                if number == 0 {
+                    return None;
+                }
+                let kind = match entry.get("type")?.as_str()? {
+                    "pre" => SnapshotKind::Pre,
+                    "post" => SnapshotKind::Post,
+                    _ => SnapshotKind::Single,
+                };
+                let pre_id = entry
+                    .get("pre-number")
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n.to_string());
+                let managed = entry
+                    .get("userdata")
+                    .and_then(|u| u.get(USERDATA_KEY))
+                    .is_some();
+
+                Some(Snapshot {
+                    id: number.to_string(),
+                    kind,
+                    pre_id,
+                    created: snapper_date(entry.get("date")?.as_str()?),
+                    description: entry
+                        .get("description")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    backend: self.name().to_string(),
+                    managed,
+                })
+            })
+            .collect())
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        self.snapper(&["delete", id]).map(|_| ())
+    }
+}
+
+/// Snapper prints local `YYYY-MM-DD HH:MM:SS` dates
+fn snapper_date(value: &str) -> String {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|local| {
+            local
+                .fixed_offset()
+                .to_rfc3339_opts(SecondsFormat::Secs, false)
+        })
+        .unwrap_or_else(|| value.to_string())
+}
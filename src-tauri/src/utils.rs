@@ -1,4 +1,6 @@
-use std::process::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use tauri::{Emitter, Window};
 
 /// Strip ANSI escape sequences from a string
 pub fn strip_ansi_codes(input: &str) -> String {
@@ -32,3 +34,69 @@ pub fn parse_package_info(stdout: &str) -> serde_json::Map<String, serde_json::V
 
     info
 }
+
+/// Run a command, emitting each output line as `event`; returns whether it succeeded
+pub fn run_streaming(window: &Window, event: &str, command: &mut Command) -> bool {
+    let mut child = match command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = window.emit(event, format!("ERROR: Failed to start process: {}", e));
+            return false;
+        }
+    };
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // Read both pipes concurrently so a full stderr buffer cannot block the child
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            if let Some(stderr) = stderr {
+                let reader = BufReader::new(stderr);
+                for line in reader.lines().map_while(Result::ok) {
+                    let _ = window.emit(event, format!("ERROR: {}", line));
+                }
+            }
+        });
+
+        if let Some(stdout) = stdout {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().map_while(Result::ok) {
+                let _ = window.emit(event, line);
+            }
+        }
+    });
+
+    child.wait().map(|status| status.success()).unwrap_or(false)
+}
+
+/// Replace a root-owned file such as /etc/pacman.conf with `content` (requires root).
+///
+/// The content goes through a private temporary file, which `install` copies
+/// into place with mode 644.
+pub fn write_system_file(destination: &str, content: &str) -> Result<(), String> {
+    let mut temp = tempfile::Builder::new()
+        .prefix("guiman-")
+        .tempfile()
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    temp.write_all(content.as_bytes())
+        .and_then(|_| temp.flush())
+        .map_err(|e| format!("Failed to write temporary file: {}", e))?;
+
+    let output = Command::new("/usr/bin/pkexec")
+        .args(["/usr/bin/install", "-m644"])
+        .arg(temp.path())
+        .arg(destination)
+        .output()
+        .map_err(|e| format!("Failed to update {}: {}", destination, e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
@@ -9,6 +9,7 @@ const DEFAULT_CONFIG = {
   checkUpdatesOnStartup: true,
   aurSupport: false,
   aurHelper: 'yay',
+  maxAurRisk: null, // 'low', 'medium' or null for no filter
   maxConcurrentDownloads: 3,
   keepPackageCache: true,
   language: 'en',